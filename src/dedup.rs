@@ -0,0 +1,57 @@
+use std::collections::{HashSet, VecDeque};
+
+/// Bounded set of recently emitted message uuids.
+///
+/// Once `capacity` uuids are stored, inserting a new one evicts the oldest.
+#[derive(Debug)]
+pub struct SeenUuids {
+    capacity: usize,
+    seen: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl SeenUuids {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Record `uuid`, returning false if it was already seen
+    pub fn insert(&mut self, uuid: &str) -> bool {
+        if self.capacity == 0 {
+            return true;
+        }
+        if self.seen.contains(uuid) {
+            return false;
+        }
+
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(uuid.to_string());
+        self.order.push_back(uuid.to_string());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_oldest() {
+        let mut seen = SeenUuids::new(2);
+        assert!(seen.insert("a"));
+        assert!(!seen.insert("a"));
+        assert!(seen.insert("b"));
+        assert!(seen.insert("c"));
+        // "a" was evicted to make room for "c"
+        assert!(seen.insert("a"));
+        assert!(!seen.insert("c"));
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use reqwest::Client;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use url::Url;
+
+use crate::parser::ParseStats;
+use crate::watcher::collect_projects;
+
+/// Outcome of a single diagnostic check
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed: true,
+            detail: detail.into(),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Diagnose the local setup: projects directory, sessions and webhook reachability
+pub async fn run_checks(claude_dirs: Result<&[PathBuf]>, webhook_url: Option<&Url>) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    match claude_dirs {
+        Ok(dirs) => {
+            for dir in dirs {
+                match fs::read_dir(dir) {
+                    Ok(_) => {
+                        checks.push(Check::pass(
+                            "Claude projects directory",
+                            format!("{dir:?} is readable"),
+                        ));
+                        checks.extend(check_projects(dir));
+                    }
+                    Err(e) => checks.push(Check::fail(
+                        "Claude projects directory",
+                        format!("{dir:?}: {e}"),
+                    )),
+                }
+            }
+        }
+        Err(e) => checks.push(Check::fail("Claude projects directory", e.to_string())),
+    }
+
+    if let Some(url) = webhook_url {
+        checks.push(check_webhook(url).await);
+    }
+
+    checks
+}
+
+fn check_projects(dir: &Path) -> Vec<Check> {
+    match collect_projects(dir) {
+        Ok(projects) if projects.is_empty() => vec![Check::fail(
+            "Projects",
+            "no projects found; start Claude Code in a project first",
+        )],
+        Ok(projects) => {
+            let sessions: usize = projects.iter().map(|p| p.sessions).sum();
+            let mut parse = ParseStats::default();
+            for project in &projects {
+                parse += &project.parse;
+            }
+            vec![
+                Check::pass(
+                    "Projects",
+                    format!("{} projects, {sessions} sessions", projects.len()),
+                ),
+                check_parse(&parse),
+            ]
+        }
+        Err(e) => vec![Check::fail("Projects", e.to_string())],
+    }
+}
+
+/// Report log lines that were skipped because they could not be parsed
+fn check_parse(parse: &ParseStats) -> Check {
+    let counts = format!(
+        "{} lines: {} messages, {} other entries, {} malformed",
+        parse.lines, parse.parsed, parse.filtered, parse.malformed
+    );
+    match parse.last_error {
+        Some(ref error) if parse.malformed > 0 => {
+            Check::fail("Log lines", format!("{counts}; last error at {error}"))
+        }
+        _ => Check::pass("Log lines", counts),
+    }
+}
+
+async fn check_webhook(url: &Url) -> Check {
+    let client = match Client::builder().timeout(Duration::from_secs(5)).build() {
+        Ok(client) => client,
+        Err(e) => return Check::fail("Webhook reachable", e.to_string()),
+    };
+
+    // Any HTTP response means the endpoint is reachable; many webhooks
+    // reject HEAD with 4xx, which is fine for this purpose
+    match client.head(url.clone()).send().await {
+        Ok(response) => Check::pass(
+            "Webhook reachable",
+            format!(
+                "{} responded with {}",
+                url.host_str().unwrap_or(""),
+                response.status()
+            ),
+        ),
+        Err(e) => Check::fail("Webhook reachable", e.to_string()),
+    }
+}
+
+/// Print checks as a pass/fail checklist
+pub fn print_report(checks: &[Check]) {
+    for check in checks {
+        let mark = if check.passed { "✅" } else { "❌" };
+        println!("{mark} {}: {}", check.name, check.detail);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_missing_projects_dir_fails_check() {
+        let root = TempDir::new().unwrap();
+        let missing = root.path().join("does-not-exist");
+
+        let checks = run_checks(Ok(&[missing]), None).await;
+
+        assert_eq!(checks.len(), 1);
+        assert!(!checks[0].passed);
+        assert_eq!(checks[0].name, "Claude projects directory");
+    }
+
+    #[tokio::test]
+    async fn test_empty_projects_dir_reports_no_projects() {
+        let root = TempDir::new().unwrap();
+
+        let checks = run_checks(Ok(&[root.path().to_path_buf()]), None).await;
+
+        assert!(checks[0].passed);
+        assert!(!checks[1].passed);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_lines_reported() {
+        let root = TempDir::new().unwrap();
+        let project = root.path().join("-home-user-app");
+        fs::create_dir(&project).unwrap();
+        let user = r#"{"type":"user","message":{"role":"user","content":"hi"},"timestamp":"2025-01-01T00:00:00Z","sessionId":"s","uuid":"u1"}"#;
+        fs::write(project.join("s.jsonl"), format!("{user}\n{{broken\n")).unwrap();
+
+        let checks = run_checks(Ok(&[root.path().to_path_buf()]), None).await;
+
+        let check = checks.iter().find(|c| c.name == "Log lines").unwrap();
+        assert!(!check.passed);
+        assert!(check
+            .detail
+            .starts_with("2 lines: 1 messages, 0 other entries, 1 malformed"));
+        assert!(
+            check.detail.contains("last error at byte"),
+            "{}",
+            check.detail
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::fs;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast;
+
+use crate::parser::LogMessage;
+
+/// Events a slow client may fall behind by before it starts missing some
+const EVENTS_BUFFER: usize = 256;
+
+/// Fans emitted messages out to every client of the events socket as JSON lines
+#[derive(Debug, Clone)]
+pub struct EventPublisher {
+    tx: broadcast::Sender<Arc<str>>,
+}
+
+impl Default for EventPublisher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventPublisher {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(EVENTS_BUFFER);
+        Self { tx }
+    }
+
+    /// Send a message to all connected clients; a no-op when there are none
+    pub fn publish(&self, message: &LogMessage, formatted: &str) {
+        let event = json!({
+            "timestamp": message.timestamp.to_rfc3339(),
+            "role": message.role,
+            "session_id": message.session_id,
+            "project": message.project_name,
+            "uuid": message.uuid,
+            "content": message.content,
+            "formatted": formatted,
+        });
+        let _ = self.tx.send(format!("{event}\n").into());
+    }
+
+    /// Number of currently connected clients
+    pub fn client_count(&self) -> usize {
+        self.tx.receiver_count()
+    }
+}
+
+/// Bind the events socket, replacing a socket left behind by an earlier run
+pub fn bind(path: &Path) -> Result<UnixListener> {
+    if fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        fs::remove_file(path).with_context(|| format!("Cannot remove stale socket {path:?}"))?;
+    }
+    UnixListener::bind(path).with_context(|| format!("Cannot bind events socket {path:?}"))
+}
+
+/// Accept clients and stream published events to them until the process exits
+pub async fn serve(listener: UnixListener, publisher: EventPublisher) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(forward(stream, publisher.tx.subscribe()));
+            }
+            Err(e) => tracing::error!("Events socket accept error: {e}"),
+        }
+    }
+}
+
+/// Write events to one client until it disconnects
+async fn forward(mut stream: UnixStream, mut events: broadcast::Receiver<Arc<str>>) {
+    loop {
+        match events.recv().await {
+            Ok(event) => {
+                if stream.write_all(event.as_bytes()).await.is_err() {
+                    tracing::debug!("Events client disconnected");
+                    return;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                tracing::warn!("Events client fell behind; dropped {missed} events");
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::LogParser;
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    fn test_message() -> LogMessage {
+        let line = r#"{"type":"assistant","message":{"role":"assistant","content":"Hello"},"timestamp":"2025-01-01T00:00:00Z","sessionId":"s","uuid":"u1"}"#;
+        let mut message = LogParser::new().parse_line(line).unwrap().unwrap();
+        message.project_name = "demo".to_string();
+        message
+    }
+
+    #[tokio::test]
+    async fn test_client_receives_published_message() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("events.sock");
+        let publisher = EventPublisher::new();
+        tokio::spawn(serve(bind(&path).unwrap(), publisher.clone()));
+
+        let client = UnixStream::connect(&path).await.unwrap();
+        while publisher.client_count() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        publisher.publish(&test_message(), "🤖 Claude: Hello");
+
+        let mut line = String::new();
+        BufReader::new(client).read_line(&mut line).await.unwrap();
+        let event: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(event["role"], "assistant");
+        assert_eq!(event["uuid"], "u1");
+        assert_eq!(event["formatted"], "🤖 Claude: Hello");
+    }
+
+    #[tokio::test]
+    async fn test_disconnected_client_is_dropped() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("events.sock");
+        let publisher = EventPublisher::new();
+        tokio::spawn(serve(bind(&path).unwrap(), publisher.clone()));
+
+        drop(UnixStream::connect(&path).await.unwrap());
+        while publisher.client_count() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // The first write may still succeed into the socket buffer
+        for _ in 0..100 {
+            publisher.publish(&test_message(), "🤖 Claude: Hello");
+            if publisher.client_count() == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("disconnected client was never dropped");
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::{bail, Context, Result};
+use serde_json::json;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::time::{timeout, Duration};
+
+use crate::parser::LogMessage;
+
+/// How long the program may take to answer one message
+pub const DEFAULT_EXEC_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Formats messages with an external program.
+///
+/// The program is started once and kept running. Each message is written to
+/// its stdin as one JSON line, and the next line it prints is used as the
+/// formatted message. If it exits or stops answering, it is restarted for
+/// the next message.
+#[derive(Debug)]
+pub struct ExecFormatter {
+    command: String,
+    timeout: Duration,
+    process: Option<Process>,
+}
+
+#[derive(Debug)]
+struct Process {
+    // Held so the child is killed when the formatter is dropped
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+impl ExecFormatter {
+    /// Run `command` through the shell
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            timeout: DEFAULT_EXEC_TIMEOUT,
+            process: None,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Format `message`, whose built-in rendering is `formatted`. On error
+    /// the program is stopped and started again on the next call.
+    pub async fn format(&mut self, message: &LogMessage, formatted: &str) -> Result<String> {
+        let request = json!({
+            "timestamp": message.timestamp.to_rfc3339(),
+            "role": message.role,
+            "session_id": message.session_id,
+            "project": message.project_name,
+            "uuid": message.uuid,
+            "content": message.content,
+            "formatted": formatted,
+        });
+
+        let result = match timeout(self.timeout, self.exchange(format!("{request}\n"))).await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow::anyhow!(
+                "no answer within {}ms",
+                self.timeout.as_millis()
+            )),
+        };
+        if result.is_err() {
+            self.process = None;
+        }
+        result.with_context(|| format!("Formatter command `{}` failed", self.command))
+    }
+
+    async fn exchange(&mut self, request: String) -> Result<String> {
+        if self.process.is_none() {
+            self.process = Some(self.spawn()?);
+        }
+        let process = self.process.as_mut().expect("process was just started");
+
+        process.stdin.write_all(request.as_bytes()).await?;
+        process.stdin.flush().await?;
+        match process.stdout.next_line().await? {
+            Some(line) => Ok(line),
+            None => bail!("the program exited"),
+        }
+    }
+
+    fn spawn(&self) -> Result<Process> {
+        let mut command = if cfg!(windows) {
+            let mut command = Command::new("cmd");
+            command.arg("/C");
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.arg("-c");
+            command
+        };
+        let mut child = command
+            .arg(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Cannot start the program")?;
+
+        let stdin = child.stdin.take().context("No stdin")?;
+        let stdout = child.stdout.take().context("No stdout")?;
+        Ok(Process {
+            _child: child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::parser::LogParser;
+
+    fn message(uuid: &str) -> LogMessage {
+        let line = format!(
+            r#"{{"type":"user","message":{{"role":"user","content":"hi"}},"timestamp":"2025-01-01T00:00:00Z","sessionId":"s","uuid":"{uuid}"}}"#
+        );
+        LogParser::new().parse_line(&line).unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_output_of_program_is_used() {
+        let mut exec = ExecFormatter::new("cat");
+        for uuid in ["u1", "u2"] {
+            let line = exec.format(&message(uuid), "👤 User: hi").await.unwrap();
+            let echoed: serde_json::Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(echoed["uuid"], uuid);
+            assert_eq!(echoed["formatted"], "👤 User: hi");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restarts_after_exit_and_timeout() {
+        // Answers one message, then exits
+        let mut exec = ExecFormatter::new("head -n 1");
+        assert!(exec.format(&message("u1"), "").await.is_ok());
+        assert!(exec.format(&message("u2"), "").await.is_err());
+        assert!(exec.format(&message("u3"), "").await.is_ok());
+
+        let mut exec =
+            ExecFormatter::new("read line; sleep 5").with_timeout(Duration::from_millis(100));
+        let error = exec.format(&message("u1"), "").await.unwrap_err();
+        assert!(format!("{error:#}").contains("no answer"), "{error:#}");
+    }
+}
@@ -1,75 +1,674 @@
 use crate::parser::{LogMessage, MessageRole};
+use crate::redact::Redactor;
+use crate::Theme;
 use anyhow::Result;
-use chrono::{Local, TimeZone};
+use chrono::{DateTime, Local, TimeZone, Utc};
 use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Timestamp prefix format used unless `with_time_format` overrides it
+pub const DEFAULT_TIME_FORMAT: &str = "%H:%M:%S";
+
+/// Check that `format` is a strftime string chrono can render
+pub fn validate_time_format(format: &str) -> Result<String, String> {
+    let invalid = chrono::format::StrftimeItems::new(format)
+        .any(|item| matches!(item, chrono::format::Item::Error));
+    if invalid {
+        Err(format!("invalid time format {format:?}"))
+    } else {
+        Ok(format.to_string())
+    }
+}
+
+/// Stands in for line breaks when newlines are flattened
+const FLATTEN_SEPARATOR: &str = " ⏎ ";
+
+/// Starts every line of a sub-agent message in the terminal, nesting it
+/// under the `Task` call that spawned it
+const SIDECHAIN_GUTTER: &str = "  │ ";
+
+/// Characters of thinking text shown with `with_show_thinking`
+const THINKING_EXCERPT_CHARS: usize = 200;
+
+/// Characters of a tool's input shown in detailed mode, unless changed with
+/// `with_tool_input_len`
+pub const DEFAULT_TOOL_INPUT_LEN: usize = 50;
+
+/// Whether formatted text has nothing worth emitting. Shared by stdout and
+/// webhooks so both drop the same messages.
+pub fn is_blank(text: &str) -> bool {
+    text.trim().is_empty()
+}
+
+/// Indent a formatted sub-agent message for the terminal. Webhooks and
+/// other consumers get the message without the gutter.
+pub fn nest_sidechain(line: &str) -> String {
+    line.lines()
+        .map(|line| format!("{SIDECHAIN_GUTTER}{line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Remove ANSI escape sequences, e.g. before formatted text leaves the terminal
+pub fn strip_ansi(text: &str) -> Cow<'_, str> {
+    if !text.contains('\x1b') {
+        return Cow::Borrowed(text);
+    }
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' && chars.peek() == Some(&'[') {
+            // Parameters and intermediates run up to a final byte in @..=~
+            for c in chars.by_ref().skip(1) {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    Cow::Owned(stripped)
+}
+
+/// Emoji icon for a tool, falling back to 🔧 for tools without a dedicated one
+pub fn tool_icon(tool_name: &str) -> &'static str {
+    if split_mcp_tool(tool_name).is_some() {
+        return "🔌";
+    }
+    match tool_name {
+        "TodoWrite" => "📝",
+        "Bash" => "💻",
+        "Read" => "📖",
+        "Write" => "✍️",
+        "Edit" => "✏️",
+        "Grep" => "🔍",
+        "WebFetch" => "🌐",
+        "Task" => "🤖",
+        _ => "🔧",
+    }
+}
+
+/// Split an MCP tool name (`mcp__server__tool`) into its server and tool parts
+pub fn split_mcp_tool(tool_name: &str) -> Option<(&str, &str)> {
+    let (server, tool) = tool_name.strip_prefix("mcp__")?.split_once("__")?;
+    (!server.is_empty() && !tool.is_empty()).then_some((server, tool))
+}
+
+/// Name shown for a tool: `server/tool` for MCP tools, unchanged otherwise
+pub fn tool_display_name(tool_name: &str) -> String {
+    match split_mcp_tool(tool_name) {
+        Some((server, tool)) => format!("{server}/{tool}"),
+        None => tool_name.to_string(),
+    }
+}
+
+/// First 8 characters of a session id, or the whole id if it is shorter
+pub fn short_session_id(session_id: &str) -> &str {
+    match session_id.char_indices().nth(8) {
+        Some((index, _)) => &session_id[..index],
+        None => session_id,
+    }
+}
+
+/// Input keys worth showing for a tool call, in order of preference
+const TOOL_INPUT_KEYS: &[&str] = &["command", "url", "query", "pattern", "file_path"];
+
+/// Lines of file content previewed for `Write` in detailed mode, unless
+/// `with_max_result_lines` caps the message
+const WRITE_PREVIEW_LINES: usize = 5;
+
+/// The most meaningful string argument of a tool input object, truncated
+/// to `max` characters. File paths are shortened from the middle so the
+/// file name stays visible.
+pub fn primary_tool_input(input: &serde_json::Map<String, Value>, max: usize) -> Option<String> {
+    TOOL_INPUT_KEYS.iter().find_map(|&key| {
+        let value = input.get(key)?.as_str()?;
+        Some(if key == "file_path" {
+            truncate_path_middle(value, max)
+        } else {
+            truncate_chars(value, max)
+        })
+    })
+}
+
+/// Shorten a path to `max` characters by eliding the middle, keeping the
+/// leading directories and the full file name
+pub fn truncate_path_middle(path: &str, max: usize) -> String {
+    if path.chars().count() <= max {
+        return path.to_string();
+    }
+
+    let tail = match path.rfind('/') {
+        Some(index) => &path[index..],
+        None => path,
+    };
+    let head_len = max.saturating_sub(tail.chars().count() + 3);
+    let head = path.chars().take(head_len).collect::<String>();
+    format!("{head}...{tail}")
+}
+
+/// The `text` blocks of a content array joined by newlines; a plain string
+/// content is returned as is
+fn text_blocks(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter(|block| block["type"] == "text")
+            .filter_map(|block| block["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Readable text of a `toolUseResult`: command output, file contents, a
+/// diff or a file list, depending on the tool that produced it
+pub(crate) fn tool_use_result_text(result: &Value) -> Option<String> {
+    if let Some(text) = result.as_str() {
+        return Some(text.to_string());
+    }
+    if let Some(stdout) = result["stdout"].as_str() {
+        let stderr = result["stderr"].as_str().unwrap_or("");
+        return Some(match (stdout.is_empty(), stderr.is_empty()) {
+            (_, true) => stdout.to_string(),
+            (true, false) => stderr.to_string(),
+            (false, false) => format!("{stdout}\n{stderr}"),
+        });
+    }
+    if let Some(content) = result["file"]["content"].as_str() {
+        return Some(content.to_string());
+    }
+    if let Some(hunks) = result["structuredPatch"].as_array() {
+        let lines = hunks
+            .iter()
+            .filter_map(|hunk| hunk["lines"].as_array())
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>();
+        return (!lines.is_empty()).then(|| lines.join("\n"));
+    }
+    if let Some(filenames) = result["filenames"].as_array() {
+        let names = filenames.iter().filter_map(Value::as_str);
+        return Some(names.collect::<Vec<_>>().join("\n"));
+    }
+    result["content"].as_str().map(str::to_string)
+}
+
+/// Distinct sources cited by text blocks, from either `citations` or
+/// `annotations`, as `title <url>` or whichever of the two is present
+fn citation_sources(content: &Value) -> Vec<String> {
+    let mut sources = Vec::new();
+    let Value::Array(blocks) = content else {
+        return sources;
+    };
+    let citations = blocks
+        .iter()
+        .filter(|block| block["type"] == "text")
+        .flat_map(|block| ["citations", "annotations"].map(|key| &block[key]))
+        .filter_map(Value::as_array)
+        .flatten();
+    for citation in citations {
+        let title = citation["title"]
+            .as_str()
+            .or_else(|| citation["document_title"].as_str());
+        let source = match (title, citation["url"].as_str()) {
+            (Some(title), Some(url)) => format!("{title} <{url}>"),
+            (Some(title), None) => title.to_string(),
+            (None, Some(url)) => url.to_string(),
+            (None, None) => continue,
+        };
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+    sources
+}
+
+/// Truncate to `max` characters, appending "..." when something was cut
+pub fn truncate_chars(s: &str, max: usize) -> String {
+    let truncated = s.chars().take(max).collect::<String>();
+    truncated + if s.chars().count() > max { "..." } else { "" }
+}
+
+/// Built-in Claude Code tools accepted by `--tool-verbosity`
+pub const KNOWN_TOOLS: &[&str] = &[
+    "Bash",
+    "Edit",
+    "ExitPlanMode",
+    "Glob",
+    "Grep",
+    "LS",
+    "MultiEdit",
+    "NotebookEdit",
+    "NotebookRead",
+    "Read",
+    "Task",
+    "TodoWrite",
+    "WebFetch",
+    "WebSearch",
+    "Write",
+];
 
 struct ToolContent {
+    /// Name of the tool for tool_use blocks
+    tool_name: Option<String>,
     simple_format: String,
     detailed_format: String,
 }
 
+#[derive(Clone)]
 pub struct LogFormatter {
     show_timestamp: bool,
+    /// strftime format of the timestamp prefix
+    time_format: String,
+    /// Show timestamps in UTC instead of the local timezone
+    utc: bool,
     show_session_id: bool,
     compact_mode: bool,
+    /// Indent continuation lines of multi-line content
+    indent: bool,
+    /// Join lines with `FLATTEN_SEPARATOR` so each message is one line
+    flatten_newlines: bool,
     tool_display_mode: crate::ToolDisplayMode,
+    /// Per-tool display modes that take precedence over `tool_display_mode`
+    tool_overrides: HashMap<String, crate::ToolDisplayMode>,
+    /// Show an excerpt of thinking blocks in detailed mode
+    show_thinking: bool,
+    /// List the sources cited by text blocks in detailed mode
+    show_citations: bool,
+    /// Leave completed items out of Slack TodoWrite checklists
+    hide_completed_todos: bool,
+    /// Width of the TodoWrite progress bar in detailed mode, if shown
+    todo_progress: Option<usize>,
+    /// Cap on lines shown per message; also lets tool results span lines
+    max_result_lines: Option<usize>,
+    /// Characters of tool input shown in detailed mode
+    tool_input_len: usize,
+    theme: Theme,
+    /// Color the role indicator with ANSI codes, leaving content plain
+    role_color: bool,
+    /// Color tool results green, or red when they report an error
+    result_color: bool,
+    /// Masks sensitive text in finished lines
+    redactor: Option<Redactor>,
+    /// Only show messages with this role
+    role_filter: Option<MessageRole>,
+    /// Hide entries marked `isMeta`
+    hide_meta: bool,
+    /// Hide sub-agent (sidechain) messages; shown ones are marked
+    hide_sidechains: bool,
+    /// Keep only the prose of a message, dropping tool and thinking markers
+    text_only: bool,
+    /// Hide messages whose content is shorter than this many characters
+    min_content_len: Option<usize>,
+}
+
+impl Default for LogFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl LogFormatter {
     pub fn new() -> Self {
         Self {
             show_timestamp: true,
+            time_format: DEFAULT_TIME_FORMAT.to_string(),
+            utc: false,
             show_session_id: false,
             compact_mode: false,
+            indent: true,
+            flatten_newlines: false,
             tool_display_mode: crate::ToolDisplayMode::Simple,
+            tool_overrides: HashMap::new(),
+            max_result_lines: None,
+            tool_input_len: DEFAULT_TOOL_INPUT_LEN,
+            todo_progress: None,
+            hide_completed_todos: false,
+            show_thinking: false,
+            show_citations: false,
+            theme: Theme::Emoji,
+            role_color: false,
+            result_color: false,
+            redactor: None,
+            role_filter: None,
+            hide_meta: false,
+            hide_sidechains: true,
+            text_only: false,
+            min_content_len: None,
         }
     }
 
-    #[allow(dead_code)]
     pub fn with_timestamp(mut self, show: bool) -> Self {
         self.show_timestamp = show;
         self
     }
 
-    #[allow(dead_code)]
+    /// Set the strftime format of the timestamp prefix, e.g. `%H:%M:%S%.3f`
+    pub fn with_time_format(mut self, format: impl Into<String>) -> Self {
+        self.time_format = format.into();
+        self
+    }
+
+    /// Show timestamps in UTC rather than local time
+    pub fn with_utc(mut self, utc: bool) -> Self {
+        self.utc = utc;
+        self
+    }
+
     pub fn with_session_id(mut self, show: bool) -> Self {
         self.show_session_id = show;
         self
     }
 
-    #[allow(dead_code)]
     pub fn with_compact_mode(mut self, compact: bool) -> Self {
         self.compact_mode = compact;
         self
     }
 
+    pub fn with_indent(mut self, indent: bool) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Mask sensitive text in every formatted message
+    pub fn with_redactor(mut self, redactor: Option<Redactor>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// Hide messages whose role is not `role`
+    pub fn with_role_filter(mut self, role: Option<MessageRole>) -> Self {
+        self.role_filter = role;
+        self
+    }
+
+    /// Hide meta entries such as injected command caveats
+    pub fn with_hide_meta(mut self, hide: bool) -> Self {
+        self.hide_meta = hide;
+        self
+    }
+
+    /// Hide sub-agent conversations (on by default). When shown, their
+    /// messages are marked `[sidechain]` and indented under the `Task` call.
+    pub fn with_hide_sidechains(mut self, hide: bool) -> Self {
+        self.hide_sidechains = hide;
+        self
+    }
+
+    /// Show only the text blocks of each message, so tool calls, results and
+    /// thinking disappear even when they share a message with prose
+    pub fn with_text_only(mut self, text_only: bool) -> Self {
+        self.text_only = text_only;
+        self
+    }
+
+    /// Hide messages whose content, as displayed, has fewer than `min`
+    /// characters. Tool calls count the length of their detailed form.
+    pub fn with_min_content_len(mut self, min: Option<usize>) -> Self {
+        self.min_content_len = min;
+        self
+    }
+
+    /// Print each message on a single line, marking line breaks with ` ⏎ `
+    pub fn with_flatten_newlines(mut self, flatten: bool) -> Self {
+        self.flatten_newlines = flatten;
+        self
+    }
+
+    /// Show an excerpt of thinking text in detailed mode instead of just a marker
+    pub fn with_show_thinking(mut self, show: bool) -> Self {
+        self.show_thinking = show;
+        self
+    }
+
+    /// In detailed mode, list the titles and URLs cited by text blocks
+    pub fn with_show_citations(mut self, show: bool) -> Self {
+        self.show_citations = show;
+        self
+    }
+
+    /// Show at most `max` lines per message, including multi-line tool results
+    pub fn with_max_result_lines(mut self, max: Option<usize>) -> Self {
+        self.max_result_lines = max;
+        self
+    }
+
+    /// Cut tool inputs in detailed mode, such as commands, paths and
+    /// unrecognized arguments, to `len` characters
+    pub fn with_tool_input_len(mut self, len: usize) -> Self {
+        self.tool_input_len = len;
+        self
+    }
+
+    /// Head detailed TodoWrite checklists with a progress bar `width` cells wide
+    pub fn with_todo_progress(mut self, width: Option<usize>) -> Self {
+        self.todo_progress = width;
+        self
+    }
+
+    /// List only open items in Slack TodoWrite checklists, under a count of
+    /// the completed ones
+    pub fn with_hide_completed_todos(mut self, hide: bool) -> Self {
+        self.hide_completed_todos = hide;
+        self
+    }
+
     pub fn with_tool_display_mode(mut self, mode: crate::ToolDisplayMode) -> Self {
         self.tool_display_mode = mode;
         self
     }
 
+    /// Override the display mode for individual tools. Unknown tool names
+    /// are ignored with a warning; MCP tools (`mcp__...`) are always accepted.
+    pub fn with_tool_verbosity(
+        mut self,
+        overrides: impl IntoIterator<Item = (String, crate::ToolDisplayMode)>,
+    ) -> Self {
+        for (tool_name, mode) in overrides {
+            if KNOWN_TOOLS.contains(&tool_name.as_str()) || tool_name.starts_with("mcp__") {
+                self.tool_overrides.insert(tool_name, mode);
+            } else {
+                tracing::warn!("Ignoring verbosity override for unknown tool {tool_name:?}");
+            }
+        }
+        self
+    }
+
+    /// Display mode for a tool, honoring per-tool overrides
+    fn mode_for(&self, tool_name: Option<&str>) -> &crate::ToolDisplayMode {
+        tool_name
+            .and_then(|name| self.tool_overrides.get(name))
+            .unwrap_or(&self.tool_display_mode)
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Color only the role indicator, so content stays free of escape codes
+    pub fn with_role_color(mut self, color: bool) -> Self {
+        self.role_color = color;
+        self
+    }
+
+    /// Color tool results by outcome with ANSI escape codes
+    pub fn with_result_color(mut self, color: bool) -> Self {
+        self.result_color = color;
+        self
+    }
+
+    /// Prefix `text` with the icon matching the current theme
+    fn decorate(&self, emoji: &str, ascii: &str, text: &str) -> String {
+        match self.theme {
+            Theme::Emoji => format!("{emoji} {text}"),
+            Theme::Ascii => format!("{ascii} {text}"),
+            Theme::None => text.to_string(),
+        }
+    }
+
+    /// Pick the standalone label matching the current theme
+    fn label<'a>(&self, emoji: &'a str, ascii: &'a str, plain: &'a str) -> &'a str {
+        match self.theme {
+            Theme::Emoji => emoji,
+            Theme::Ascii => ascii,
+            Theme::None => plain,
+        }
+    }
+
+    /// Color each line of a tool result green, or red for errors, when
+    /// result coloring is on. Every line is reset on its own, so cutting
+    /// or indenting lines later cannot leave the color on.
+    fn color_result(&self, text: String, is_error: bool) -> String {
+        if !self.result_color {
+            return text;
+        }
+        let code = if is_error { 31 } else { 32 };
+        text.lines()
+            .map(|line| {
+                if line.is_empty() {
+                    String::new()
+                } else {
+                    format!("\x1b[{code}m{line}\x1b[0m")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Fail on options that cannot be honored together
+    pub fn validate(&self) -> Result<()> {
+        if self.compact_mode {
+            let detailed = self.tool_display_mode == crate::ToolDisplayMode::Detailed
+                || self
+                    .tool_overrides
+                    .values()
+                    .any(|mode| *mode == crate::ToolDisplayMode::Detailed);
+            if detailed {
+                anyhow::bail!(
+                    "Compact mode cuts messages to one line, so detailed tool display cannot be shown"
+                );
+            }
+            if self.max_result_lines.is_some() {
+                anyhow::bail!("Compact mode shows one line per message; it cannot be combined with a result line limit");
+            }
+        }
+        Ok(())
+    }
+
     /// Format message
     pub fn format_message(&self, message: &LogMessage) -> Result<String> {
-        let mut output = String::new();
+        self.format_message_with_min(message, self.min_content_len)
+    }
+
+    fn format_message_with_min(&self, message: &LogMessage, min: Option<usize>) -> Result<String> {
+        let body = self.format_body_with_min(message, min)?;
+        let line = if body.is_empty() || !self.show_timestamp {
+            body
+        } else {
+            format!("[{}] {body}", self.clock_time(message.timestamp))
+        };
+
+        let line = if self.flatten_newlines {
+            line.lines().collect::<Vec<_>>().join(FLATTEN_SEPARATOR)
+        } else {
+            line
+        };
 
-        // Timestamp
-        if self.show_timestamp {
-            let local_time = Local.from_utc_datetime(&message.timestamp.naive_utc());
-            output.push_str(&format!("[{}] ", local_time.format("%H:%M:%S")));
+        match self.redactor {
+            Some(ref redactor) => Ok(redactor.redact(&line).into_owned()),
+            None => Ok(line),
         }
+    }
+
+    /// Render `timestamp` for the prefix. Local time uses the offset in
+    /// effect at that instant, so messages keep their wall-clock time across
+    /// DST changes.
+    fn clock_time(&self, timestamp: DateTime<Utc>) -> String {
+        if self.utc {
+            self.clock_time_in(timestamp, &Utc)
+        } else {
+            self.clock_time_in(timestamp, &Local)
+        }
+    }
+
+    fn clock_time_in<Tz: TimeZone>(&self, timestamp: DateTime<Utc>, zone: &Tz) -> String
+    where
+        Tz::Offset: fmt::Display,
+    {
+        timestamp
+            .with_timezone(zone)
+            .format(&self.time_format)
+            .to_string()
+    }
+
+    /// Format a message, or `None` if it has no visible content
+    pub fn format_visible(&self, message: &LogMessage) -> Result<Option<String>> {
+        let formatted = self.format_message(message)?;
+        Ok((!is_blank(&formatted)).then_some(formatted))
+    }
+
+    /// Like `format_visible`, but keeps messages under the minimum length
+    pub fn format_visible_any_length(&self, message: &LogMessage) -> Result<Option<String>> {
+        let formatted = self.format_message_with_min(message, None)?;
+        Ok((!is_blank(&formatted)).then_some(formatted))
+    }
+
+    /// Format everything but the timestamp: role, session and content.
+    /// Returns an empty string for messages that should not be shown.
+    pub fn format_body(&self, message: &LogMessage) -> Result<String> {
+        self.format_body_with_min(message, self.min_content_len)
+    }
+
+    fn format_body_with_min(&self, message: &LogMessage, min: Option<usize>) -> Result<String> {
+        let hidden = self
+            .role_filter
+            .as_ref()
+            .is_some_and(|role| *role != message.role)
+            || (self.hide_meta && message.is_meta)
+            || (self.hide_sidechains && message.is_sidechain);
+        if hidden {
+            return Ok(String::new());
+        }
+
+        let mut output = String::new();
 
         // Role indicator
         let role_indicator = match message.role {
-            MessageRole::User => "👤 User",
-            MessageRole::Assistant => "🤖 Claude",
-            MessageRole::System => "⚙️  System",
+            MessageRole::User => self.label("👤 User", "[user]", "User"),
+            MessageRole::Assistant => self.label("🤖 Claude", "[claude]", "Claude"),
+            MessageRole::System => self.label("⚙️  System", "[system]", "System"),
+            MessageRole::Summary => self.label("📋 Summary", "[summary]", "Summary"),
         };
 
-        output.push_str(role_indicator);
+        if self.role_color {
+            let code = match message.role {
+                MessageRole::User => "36",
+                MessageRole::Assistant => "32",
+                MessageRole::System => "33",
+                MessageRole::Summary => "35",
+            };
+            output.push_str(&format!("\x1b[1;{code}m{role_indicator}\x1b[0m"));
+        } else {
+            output.push_str(role_indicator);
+        }
 
         // Session ID
         if self.show_session_id {
-            output.push_str(&format!(" ({})", &message.session_id[..8]));
+            output.push_str(&format!(" ({})", short_session_id(&message.session_id)));
+        }
+
+        match message.task {
+            Some(ref task) if message.is_sidechain => {
+                output.push_str(&format!(" [sidechain: {task}]"))
+            }
+            _ if message.is_sidechain => output.push_str(" [sidechain]"),
+            _ => {}
         }
 
         output.push_str(": ");
@@ -78,18 +677,21 @@ impl LogFormatter {
         let formatted_content = self.format_message_content(message)?;
 
         // Skip empty messages (filtered tool messages in none mode)
-        if formatted_content.trim().is_empty() {
+        if is_blank(&formatted_content) || self.is_too_short(message, &formatted_content, min) {
             return Ok(String::new());
         }
 
         if self.compact_mode {
             // Compact mode: show only first 100 characters
-            let content = if formatted_content.len() > 100 {
-                format!("{}...", &formatted_content[..100])
-            } else {
-                formatted_content
-            };
+            let content = truncate_chars(&formatted_content, 100);
             output.push_str(&content.replace('\n', " "));
+            if self.result_color && content.contains('\x1b') {
+                // The cut may have dropped a result's reset code
+                output.push_str("\x1b[0m");
+            }
+        } else if let Some(max) = self.max_result_lines {
+            let content = truncate_lines(&formatted_content, max);
+            output.push_str(&self.format_content(&content));
         } else {
             // Normal mode: show full content
             output.push_str(&self.format_content(&formatted_content));
@@ -98,6 +700,25 @@ impl LogFormatter {
         Ok(output)
     }
 
+    /// Whether `content` falls under the minimum length. Tool messages are
+    /// also measured in detailed form, so a terse `🔧 Bash` line for a long
+    /// command is kept.
+    fn is_too_short(&self, message: &LogMessage, content: &str, min: Option<usize>) -> bool {
+        let Some(min) = min else {
+            return false;
+        };
+        let long_enough = |text: &str| text.trim().chars().count() >= min;
+        if long_enough(content) {
+            return false;
+        }
+        let detailed = message
+            .raw_content
+            .as_ref()
+            .filter(|_| !self.text_only)
+            .and_then(|raw| self.format_blocks(raw, message.tool_use_result.as_ref(), true));
+        !detailed.is_some_and(|detailed| long_enough(&detailed))
+    }
+
     /// Format message content based on tool display mode
     fn format_message_content(&self, message: &LogMessage) -> Result<String> {
         // If no raw content, fallback to simple content
@@ -106,126 +727,223 @@ impl LogFormatter {
             None => return Ok(message.content.clone()),
         };
 
+        if self.text_only {
+            return Ok(text_blocks(raw_content));
+        }
+
         // Check if this is a tool-related message
-        if let Some(tool_content) = self.extract_tool_content(raw_content) {
-            match self.tool_display_mode {
-                crate::ToolDisplayMode::None => {
-                    // Filter out tool messages, but keep text content
-                    if message.content.trim().is_empty()
-                        || message.content.starts_with("[Tool")
-                        || message.content.starts_with("[Thinking")
-                    {
-                        return Ok(String::new());
-                    }
-                }
-                crate::ToolDisplayMode::Simple => {
-                    return Ok(tool_content.simple_format);
-                }
-                crate::ToolDisplayMode::Detailed => {
-                    return Ok(tool_content.detailed_format);
-                }
-            }
+        if let Some(content) =
+            self.format_blocks(raw_content, message.tool_use_result.as_ref(), false)
+        {
+            return Ok(content);
         }
 
         // Not a tool message, return normal content
+        let sources =
+            if self.show_citations && self.tool_display_mode == crate::ToolDisplayMode::Detailed {
+                citation_sources(raw_content)
+            } else {
+                Vec::new()
+            };
+        if !sources.is_empty() {
+            let mut content = message.content.clone();
+            content.push('\n');
+            content.push_str(self.label("📚 Sources:", "[sources]", "Sources:"));
+            for (index, source) in sources.iter().enumerate() {
+                content.push_str(&format!("\n[{}] {source}", index + 1));
+            }
+            return Ok(content);
+        }
         Ok(message.content.clone())
     }
 
-    /// Extract tool information from raw content
-    fn extract_tool_content(&self, content: &Value) -> Option<ToolContent> {
-        if let Value::Array(arr) = content {
-            for item in arr {
-                if let Some(obj) = item.as_object() {
-                    if let Some(content_type) = obj.get("type") {
-                        match content_type.as_str().unwrap_or("") {
-                            "tool_use" => {
-                                let tool_name = obj
-                                    .get("name")
-                                    .and_then(|n| n.as_str())
-                                    .unwrap_or("Unknown");
-
-                                let tool_icon = if tool_name == "TodoWrite" {
-                                    "📝"
-                                } else {
-                                    "🔧"
-                                };
-                                let simple = format!("{tool_icon} {tool_name}");
-
-                                let detailed = if let Some(input) = obj.get("input") {
-                                    let input_str = self.format_tool_input(input);
-                                    format!("{tool_icon} {tool_name}: {input_str}")
-                                } else {
-                                    simple.clone()
-                                };
+    /// Render the text, tool and thinking blocks of a message in order, each
+    /// tool block in its display mode (or detailed, if `detailed` is set).
+    /// Returns `None` for messages without tool or thinking blocks.
+    fn format_blocks(
+        &self,
+        content: &Value,
+        mut tool_use_result: Option<&Value>,
+        detailed: bool,
+    ) -> Option<String> {
+        let Value::Array(blocks) = content else {
+            return None;
+        };
 
-                                return Some(ToolContent {
-                                    simple_format: simple,
-                                    detailed_format: detailed,
-                                });
-                            }
-                            "tool_result" => {
-                                let simple = "✅ Result".to_string();
+        let mut has_tools = false;
+        let mut parts = Vec::new();
+        for obj in blocks.iter().filter_map(Value::as_object) {
+            if obj.get("type").and_then(Value::as_str) == Some("text") {
+                if let Some(text) = obj.get("text").and_then(Value::as_str) {
+                    if !text.trim().is_empty() {
+                        parts.push(text.to_string());
+                    }
+                }
+                continue;
+            }
 
-                                let detailed = if let Some(content) = obj.get("content") {
-                                    let content_str = self.format_tool_result(content);
-                                    format!("✅ {content_str}")
-                                } else {
-                                    simple.clone()
-                                };
+            // An entry's structured result belongs to its first tool result
+            let structured = if obj.get("type").and_then(Value::as_str) == Some("tool_result") {
+                tool_use_result.take()
+            } else {
+                None
+            };
+            let Some(tool_content) = self.block_content(obj, structured) else {
+                continue;
+            };
+            has_tools = true;
+            if detailed {
+                parts.push(tool_content.detailed_format);
+                continue;
+            }
+            match self.mode_for(tool_content.tool_name.as_deref()) {
+                crate::ToolDisplayMode::None => {}
+                crate::ToolDisplayMode::Simple => parts.push(tool_content.simple_format),
+                crate::ToolDisplayMode::Detailed => parts.push(tool_content.detailed_format),
+            }
+        }
+        has_tools.then(|| parts.join("\n"))
+    }
 
-                                return Some(ToolContent {
-                                    simple_format: simple,
-                                    detailed_format: detailed,
-                                });
-                            }
-                            "thinking" => {
-                                let simple = "💭 Thinking...".to_string();
-                                return Some(ToolContent {
-                                    simple_format: simple.clone(),
-                                    detailed_format: simple,
-                                });
-                            }
-                            _ => {}
-                        }
+    /// Tool information for one content block. Tool results prefer the
+    /// entry's structured `tool_use_result` when it can be rendered.
+    fn block_content(
+        &self,
+        obj: &serde_json::Map<String, Value>,
+        tool_use_result: Option<&Value>,
+    ) -> Option<ToolContent> {
+        match obj.get("type")?.as_str()? {
+            "tool_use" => {
+                let tool_name = obj
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or("Unknown");
+
+                let ascii = if tool_name == "TodoWrite" {
+                    "[todo]"
+                } else if split_mcp_tool(tool_name).is_some() {
+                    "[mcp]"
+                } else {
+                    "[tool]"
+                };
+                let simple =
+                    self.decorate(tool_icon(tool_name), ascii, &tool_display_name(tool_name));
+
+                let detailed = if let Some(input) = obj.get("input") {
+                    let mode = self.mode_for(Some(tool_name));
+                    let input_str = self.format_tool_input(input, mode);
+                    format!("{simple}: {input_str}")
+                } else {
+                    simple.clone()
+                };
+
+                Some(ToolContent {
+                    tool_name: Some(tool_name.to_string()),
+                    simple_format: simple,
+                    detailed_format: detailed,
+                })
+            }
+            "tool_result" => {
+                let is_error = obj.get("is_error") == Some(&Value::Bool(true));
+                let (emoji, ascii) = if is_error {
+                    ("❌", "[error]")
+                } else {
+                    ("✅", "[result]")
+                };
+                let simple = self
+                    .label(&format!("{emoji} Result"), ascii, "Result")
+                    .to_string();
+
+                let structured = tool_use_result
+                    .and_then(tool_use_result_text)
+                    .map(Value::String);
+                let content = structured.as_ref().or(obj.get("content"));
+                let detailed = if let Some(content) = content {
+                    let content_str = self.format_tool_result(content);
+                    self.decorate(emoji, ascii, &content_str)
+                } else {
+                    simple.clone()
+                };
+
+                Some(ToolContent {
+                    tool_name: None,
+                    simple_format: self.color_result(simple, is_error),
+                    detailed_format: self.color_result(detailed, is_error),
+                })
+            }
+            "thinking" => {
+                let simple = self
+                    .label("💭 Thinking...", "[thinking]", "Thinking...")
+                    .to_string();
+                let excerpt = obj
+                    .get("thinking")
+                    .and_then(|t| t.as_str())
+                    .filter(|_| self.show_thinking)
+                    .map(|text| {
+                        let text = text.split_whitespace().collect::<Vec<_>>();
+                        truncate_chars(&text.join(" "), THINKING_EXCERPT_CHARS)
+                    })
+                    .filter(|excerpt| !excerpt.is_empty());
+                let detailed = match excerpt {
+                    Some(excerpt) => {
+                        self.decorate("💭", "[thinking]", &format!("Thinking: {excerpt}"))
                     }
-                }
+                    None => simple.clone(),
+                };
+                Some(ToolContent {
+                    tool_name: None,
+                    simple_format: simple,
+                    detailed_format: detailed,
+                })
             }
+            _ => None,
         }
-        None
     }
 
     /// Format tool input for detailed display
-    fn format_tool_input(&self, input: &Value) -> String {
+    fn format_tool_input(&self, input: &Value, mode: &crate::ToolDisplayMode) -> String {
         match input {
             Value::Object(obj) => {
                 // Handle TodoWrite specially
                 if let Some(todos) = obj.get("todos") {
-                    return self.format_todos_input(todos);
+                    return self.format_todos_for_terminal(todos, mode);
                 }
 
-                if let Some(command) = obj.get("command") {
-                    if let Some(cmd_str) = command.as_str() {
-                        let truncated = cmd_str.chars().take(50).collect::<String>();
-                        return truncated + if cmd_str.len() > 50 { "..." } else { "" };
-                    }
+                // Files being written get a preview of their content
+                if let (Some(path), Some(content)) = (
+                    obj.get("file_path").and_then(Value::as_str),
+                    obj.get("content").and_then(Value::as_str),
+                ) {
+                    let path = truncate_path_middle(path, self.tool_input_len);
+                    return format!("{path}\n```\n{}\n```", self.write_preview(content));
                 }
-                "(...)".to_string()
-            }
-            Value::String(s) => {
-                let truncated = s.chars().take(50).collect::<String>();
-                truncated + if s.len() > 50 { "..." } else { "" }
+
+                // Unrecognized inputs show their arguments as one-line JSON
+                let max = self.tool_input_len;
+                primary_tool_input(obj, max).unwrap_or_else(|| {
+                    truncate_chars(&serde_json::to_string(input).unwrap_or_default(), max)
+                })
             }
+            Value::String(s) => truncate_chars(s, self.tool_input_len),
             _ => "(...)".to_string(),
         }
     }
 
-    /// Format TodoWrite todos input
-    fn format_todos_input(&self, todos: &Value) -> String {
-        self.format_todos_for_terminal(todos)
+    /// First lines of `content`, few enough that the path, the fences and a
+    /// "more lines" marker still fit within `max_result_lines`
+    fn write_preview(&self, content: &str) -> String {
+        let budget = self
+            .max_result_lines
+            .map_or(WRITE_PREVIEW_LINES, |max| max.saturating_sub(3).max(1));
+        if content.lines().count() <= budget {
+            content.to_string()
+        } else {
+            truncate_lines(content, budget.saturating_sub(1).max(1))
+        }
     }
 
     /// Format todos for terminal display
-    fn format_todos_for_terminal(&self, todos: &Value) -> String {
+    fn format_todos_for_terminal(&self, todos: &Value, mode: &crate::ToolDisplayMode) -> String {
         if let Value::Array(todo_array) = todos {
             let mut completed_count = 0;
             let mut pending_count = 0;
@@ -245,7 +963,7 @@ impl LogFormatter {
 
             let total = completed_count + pending_count + in_progress_count;
 
-            match self.tool_display_mode {
+            match mode {
                 crate::ToolDisplayMode::Simple => {
                     let mut parts = Vec::new();
                     if pending_count > 0 {
@@ -288,17 +1006,16 @@ impl LogFormatter {
                                 _ => "[ ]",
                             };
 
-                            let priority_icon = match priority {
-                                "high" => "🔴",
-                                "low" => "🟢",
-                                _ => "🟡",
+                            let item = match priority {
+                                "high" => self.decorate("🔴", "(high)", content),
+                                "low" => self.decorate("🟢", "(low)", content),
+                                _ => self.decorate("🟡", "(medium)", content),
                             };
 
                             lines.push(format!(
-                                "  {} {} {} {}",
+                                "  {} {} {}",
                                 checkbox,
-                                priority_icon,
-                                content,
+                                item,
                                 if status == "in_progress" {
                                     "(in progress)"
                                 } else {
@@ -308,7 +1025,11 @@ impl LogFormatter {
                         }
                     }
 
-                    format!("\n{}", lines.join("\n"))
+                    let progress = match self.todo_progress {
+                        Some(width) => self.progress_bar(completed_count, total, width),
+                        None => String::new(),
+                    };
+                    format!("{progress}\n{}", lines.join("\n"))
                 }
                 _ => format!("{total} tasks"),
             }
@@ -317,6 +1038,20 @@ impl LogFormatter {
         }
     }
 
+    /// `[██████░░░░] 3/5 done`, with `#` and `-` outside the emoji theme
+    fn progress_bar(&self, done: usize, total: usize, width: usize) -> String {
+        let filled = (done * width + total / 2).checked_div(total).unwrap_or(0);
+        let (full, empty) = match self.theme {
+            Theme::Emoji => ("█", "░"),
+            Theme::Ascii | Theme::None => ("#", "-"),
+        };
+        format!(
+            "[{}{}] {done}/{total} done",
+            full.repeat(filled),
+            empty.repeat(width - filled)
+        )
+    }
+
     /// Format todos for Slack mrkdwn
     pub fn format_todos_for_slack(&self, todos: &Value) -> String {
         if let Value::Array(todo_array) = todos {
@@ -375,6 +1110,10 @@ impl LogFormatter {
                                 .and_then(|p| p.as_str())
                                 .unwrap_or("medium");
 
+                            if self.hide_completed_todos && status == "completed" {
+                                continue;
+                            }
+
                             let status_emoji = match status {
                                 "completed" => "✅",
                                 "in_progress" => "⚠️",
@@ -398,7 +1137,12 @@ impl LogFormatter {
                         }
                     }
 
-                    format!("\n{}", lines.join("\n"))
+                    let header = if self.hide_completed_todos {
+                        format!("{completed_count}/{total} completed")
+                    } else {
+                        String::new()
+                    };
+                    format!("{header}\n{}", lines.join("\n"))
                 }
                 _ => format!("{total} tasks"),
             }
@@ -409,23 +1153,42 @@ impl LogFormatter {
 
     /// Format tool result for detailed display
     fn format_tool_result(&self, content: &Value) -> String {
-        match content {
-            Value::String(s) => {
-                let first_line = s.lines().next().unwrap_or("");
-                let truncated = first_line.chars().take(50).collect::<String>();
-                truncated + if first_line.len() > 50 { "..." } else { "" }
-            }
-            _ => "Result".to_string(),
+        let text = match content {
+            Value::String(s) => s.clone(),
+            Value::Array(blocks) => blocks
+                .iter()
+                .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => return "Result".to_string(),
+        };
+
+        // With a line cap the whole result is shown and capped per message
+        if self.max_result_lines.is_some() {
+            return text;
         }
+        let first_line = text.lines().next().unwrap_or("");
+        truncate_chars(first_line, 50)
     }
 
     /// Format content
     fn format_content(&self, content: &str) -> String {
-        if content.contains('\n') {
-            // Add indentation for multi-line content
+        // Indentation would only pad the separators of flattened output
+        if self.indent && !self.flatten_newlines && content.contains('\n') {
+            // Add indentation for multi-line content, leaving the interior of
+            // fenced code blocks untouched so they can be copied verbatim
+            let mut in_fence = false;
             content
                 .lines()
                 .map(|line| {
+                    let is_fence = line.trim_start().starts_with("```");
+                    if in_fence && !is_fence {
+                        return line.to_string();
+                    }
+                    if is_fence {
+                        in_fence = !in_fence;
+                    }
+
                     if line.trim().is_empty() {
                         String::new()
                     } else {
@@ -440,30 +1203,64 @@ impl LogFormatter {
     }
 
     /// Display conversation separator
-    #[allow(dead_code)]
     pub fn format_separator(&self) -> String {
         "─".repeat(80)
     }
 
+    /// Header shown when output switches to a different session
+    pub fn format_session_header(&self, session_id: &str, project_name: &str) -> String {
+        let rule = self.label("──", "--", "--");
+        format!(
+            "{rule} session {} ({project_name}) {rule}",
+            short_session_id(session_id)
+        )
+    }
+
     /// Display session start
-    #[allow(dead_code)]
     pub fn format_session_start(&self, session_id: &str) -> String {
-        format!("🚀 New session started: {}", &session_id[..8])
+        let text = format!("New session started: {}", short_session_id(session_id));
+        self.decorate("🚀", "[start]", &text)
     }
 
     /// Display session end
-    #[allow(dead_code)]
     pub fn format_session_end(&self, session_id: &str) -> String {
-        format!("🔚 Session ended: {}", &session_id[..8])
+        let text = format!("Session ended: {}", short_session_id(session_id));
+        self.decorate("🔚", "[end]", &text)
     }
 
     /// Display statistics
-    #[allow(dead_code)]
     pub fn format_stats(&self, user_messages: usize, assistant_messages: usize) -> String {
-        format!(
-            "📊 Statistics: {user_messages} user messages, {assistant_messages} Claude messages"
-        )
+        let text = format!(
+            "Statistics: {user_messages} user messages, {assistant_messages} Claude messages"
+        );
+        self.decorate("📊", "[stats]", &text)
+    }
+
+    /// Display token usage totals
+    pub fn format_usage(&self, usage: &crate::usage::Usage) -> String {
+        let text = format!(
+            "Tokens: {} input ({} uncached, {} cache write, {} cache read), {} output; billable input {}",
+            usage.total_input(),
+            usage.input_tokens,
+            usage.cache_creation_input_tokens,
+            usage.cache_read_input_tokens,
+            usage.output_tokens,
+            usage.billable_input()
+        );
+        self.decorate("🪙", "[tokens]", &text)
+    }
+}
+
+/// Keep the first `max` lines of `s`, noting how many were dropped
+pub fn truncate_lines(s: &str, max: usize) -> String {
+    let total = s.lines().count();
+    if total <= max {
+        return s.to_string();
     }
+    let mut kept: Vec<&str> = s.lines().take(max).collect();
+    let marker = format!("… (+{} more lines)", total - max);
+    kept.push(&marker);
+    kept.join("\n")
 }
 
 #[cfg(test)]
@@ -480,6 +1277,13 @@ mod tests {
             uuid: "test-uuid".to_string(),
             project_name: "test-project".to_string(),
             raw_content: None,
+            tool_use_result: None,
+            is_meta: false,
+            is_sidechain: false,
+            parent_uuid: None,
+            task: None,
+            raw_entry: None,
+            raw_line: None,
         }
     }
 
@@ -493,6 +1297,345 @@ mod tests {
         assert!(result.contains("Test message."));
     }
 
+    #[test]
+    fn test_timestamp_prefix_can_be_disabled() {
+        let message = create_test_message();
+
+        let with = LogFormatter::new().format_message(&message).unwrap();
+        assert!(with.starts_with('[') && with[9..].starts_with("] "));
+
+        let without = LogFormatter::new()
+            .with_timestamp(false)
+            .format_message(&message)
+            .unwrap();
+        assert_eq!(without, with[11..]);
+    }
+
+    #[test]
+    fn test_no_indent_keeps_lines_flush() {
+        let message = create_test_message();
+        let result = LogFormatter::new()
+            .with_timestamp(false)
+            .with_indent(false)
+            .format_message(&message)
+            .unwrap();
+
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(": Test message."));
+        assert_eq!(lines[1], "Spanning multiple lines.");
+    }
+
+    #[test]
+    fn test_timestamps_across_dst_change() {
+        // New York moved to daylight time at 2025-03-09T07:00:00Z
+        let before: DateTime<Utc> = "2025-03-09T06:30:00Z".parse().unwrap();
+        let after: DateTime<Utc> = "2025-03-09T07:30:00Z".parse().unwrap();
+        let formatter = LogFormatter::new();
+        let new_york = chrono_tz::America::New_York;
+
+        assert_eq!(formatter.clock_time_in(before, &new_york), "01:30:00");
+        assert_eq!(formatter.clock_time_in(after, &new_york), "03:30:00");
+
+        let mut message = create_test_message();
+        message.timestamp = before;
+        let utc = LogFormatter::new().with_utc(true).format_message(&message);
+        assert!(utc.unwrap().starts_with("[06:30:00]"));
+    }
+
+    #[test]
+    fn test_validate_rejects_compact_detail() {
+        assert!(LogFormatter::default().validate().is_ok());
+
+        let detailed = LogFormatter::new()
+            .with_compact_mode(true)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed);
+        assert!(detailed.validate().is_err());
+
+        let capped = LogFormatter::new()
+            .with_compact_mode(true)
+            .with_max_result_lines(Some(5));
+        let error = capped.validate().unwrap_err();
+        assert!(error.to_string().contains("line limit"), "{error}");
+    }
+
+    #[test]
+    fn test_millisecond_time_format() {
+        let formatter = LogFormatter::new().with_time_format("%H:%M:%S%.3f");
+        let mut first = create_test_message();
+        first.timestamp = "2025-01-01T12:00:00.120Z".parse().unwrap();
+        let mut second = create_test_message();
+        second.timestamp = "2025-01-01T12:00:00.875Z".parse().unwrap();
+
+        let first = formatter.format_message(&first).unwrap();
+        let second = formatter.format_message(&second).unwrap();
+        assert!(first.starts_with('[') && first[9..].starts_with(".120] "));
+        assert!(second[9..].starts_with(".875] "));
+
+        assert!(validate_time_format("%H:%M:%S%.3f").is_ok());
+        assert!(validate_time_format("%Q").is_err());
+    }
+
+    #[test]
+    fn test_show_thinking_excerpt() {
+        let mut message = create_test_message();
+        message.role = MessageRole::Assistant;
+        message.raw_content = Some(serde_json::json!([
+            {"type": "thinking", "thinking": "The user wants\na fix.", "signature": "x"}
+        ]));
+        let detailed = LogFormatter::new()
+            .with_timestamp(false)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed);
+
+        let hidden = detailed.clone().format_message(&message).unwrap();
+        assert!(hidden.ends_with("💭 Thinking..."));
+
+        let shown = detailed
+            .with_show_thinking(true)
+            .format_message(&message)
+            .unwrap();
+        assert!(shown.ends_with("💭 Thinking: The user wants a fix."));
+    }
+
+    #[test]
+    fn test_every_block_of_a_message_is_shown() {
+        let mut message = tool_use_message("Bash", serde_json::json!({"command": "ls"}));
+        message.raw_content = Some(serde_json::json!([
+            {"type": "thinking", "thinking": "Look around first."},
+            {"type": "tool_use", "name": "Bash", "input": {"command": "ls"}},
+            {"type": "text", "text": "Listing the files."}
+        ]));
+        let formatter = LogFormatter::new()
+            .with_timestamp(false)
+            .with_indent(false)
+            .with_show_thinking(true)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed);
+
+        let output = formatter.format_message(&message).unwrap();
+        assert_eq!(output.matches("🤖 Claude").count(), 1);
+        let thinking = output.find("Thinking: Look around first.").unwrap();
+        let tool = output.find("Bash: ls").unwrap();
+        let text = output.find("Listing the files.").unwrap();
+        assert!(thinking < tool && tool < text, "{output}");
+
+        // Tool blocks can be hidden while the prose stays
+        let output = formatter
+            .with_tool_display_mode(crate::ToolDisplayMode::None)
+            .format_message(&message)
+            .unwrap();
+        assert_eq!(output, "🤖 Claude: Listing the files.");
+    }
+
+    #[test]
+    fn test_show_citations() {
+        let mut message = create_test_message();
+        message.role = MessageRole::Assistant;
+        message.content = "Rust 1.0 shipped in 2015.".to_string();
+        message.raw_content = Some(serde_json::json!([{
+            "type": "text",
+            "text": "Rust 1.0 shipped in 2015.",
+            "citations": [
+                {"type": "web_search_result_location", "title": "Rust Blog", "url": "https://blog.rust-lang.org/"},
+                {"type": "char_location", "document_title": "Release notes"},
+                {"type": "web_search_result_location", "title": "Rust Blog", "url": "https://blog.rust-lang.org/"}
+            ],
+            "annotations": [{"type": "url_citation", "url": "https://www.rust-lang.org/"}]
+        }]));
+        let detailed = LogFormatter::new()
+            .with_timestamp(false)
+            .with_indent(false)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed);
+
+        assert_eq!(
+            detailed.clone().format_message(&message).unwrap(),
+            "🤖 Claude: Rust 1.0 shipped in 2015."
+        );
+        assert_eq!(
+            detailed
+                .with_show_citations(true)
+                .format_message(&message)
+                .unwrap(),
+            "🤖 Claude: Rust 1.0 shipped in 2015.\n📚 Sources:\n\
+             [1] Rust Blog <https://blog.rust-lang.org/>\n\
+             [2] Release notes\n\
+             [3] https://www.rust-lang.org/"
+        );
+    }
+
+    #[test]
+    fn test_role_color_leaves_content_plain() {
+        let mut message = create_test_message();
+        message.content = "grep me".to_string();
+        let result = LogFormatter::new()
+            .with_timestamp(false)
+            .with_role_color(true)
+            .format_message(&message)
+            .unwrap();
+
+        let (role, content) = result.split_once(": ").unwrap();
+        assert_eq!(role, "\x1b[1;36m👤 User\x1b[0m");
+        assert_eq!(content, "grep me");
+        assert_eq!(strip_ansi(&result), "👤 User: grep me");
+    }
+
+    #[test]
+    fn test_result_color_by_outcome() {
+        let formatter = LogFormatter::new()
+            .with_timestamp(false)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed)
+            .with_result_color(true);
+        let result = |is_error: bool| {
+            let mut message = create_test_message();
+            message.raw_content = Some(serde_json::json!([
+                {"type": "tool_result", "tool_use_id": "t1", "content": "done", "is_error": is_error}
+            ]));
+            formatter.format_message(&message).unwrap()
+        };
+
+        assert!(result(false).ends_with("\x1b[32m✅ done\x1b[0m"));
+        assert!(result(true).ends_with("\x1b[31m❌ done\x1b[0m"));
+        assert_eq!(strip_ansi(&result(true)), "👤 User: ❌ done");
+    }
+
+    #[test]
+    fn test_result_color_survives_truncation() {
+        let formatter = LogFormatter::new()
+            .with_timestamp(false)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed)
+            .with_max_result_lines(Some(2))
+            .with_result_color(true);
+        let mut message = create_test_message();
+        message.raw_content = Some(serde_json::json!([
+            {"type": "tool_result", "tool_use_id": "t1", "content": "one\ntwo\nthree\nfour", "is_error": true}
+        ]));
+        let result = formatter.format_message(&message).unwrap();
+
+        assert!(result.contains("\x1b[31m❌ one\x1b[0m"), "{result:?}");
+        for line in result.lines() {
+            // Any line that turns red also turns it off again
+            if let Some(start) = line.find("\x1b[31m") {
+                assert!(line[start..].contains("\x1b[0m"), "{line:?}");
+            }
+        }
+        assert!(result.ends_with("more lines)"), "{result:?}");
+    }
+
+    #[test]
+    fn test_meta_and_sidechain_filtering() {
+        let mut message = create_test_message();
+        message.content = "sub-agent step".to_string();
+        message.is_sidechain = true;
+
+        let formatter = LogFormatter::new().with_timestamp(false);
+        assert_eq!(formatter.format_visible(&message).unwrap(), None);
+        assert_eq!(
+            formatter
+                .clone()
+                .with_hide_sidechains(false)
+                .format_message(&message)
+                .unwrap(),
+            "👤 User [sidechain]: sub-agent step"
+        );
+
+        message.is_sidechain = false;
+        message.is_meta = true;
+        assert!(formatter.format_visible(&message).unwrap().is_some());
+        let hide_meta = formatter.with_hide_meta(true);
+        assert_eq!(hide_meta.format_visible(&message).unwrap(), None);
+    }
+
+    #[test]
+    fn test_min_content_len() {
+        let formatter = LogFormatter::new().with_min_content_len(Some(5));
+        let mut message = create_test_message();
+        message.content = "ok".to_string();
+        assert_eq!(formatter.format_visible(&message).unwrap(), None);
+        assert!(formatter
+            .format_visible_any_length(&message)
+            .unwrap()
+            .is_some());
+
+        message.content = "done!".to_string();
+        assert!(formatter.format_visible(&message).unwrap().is_some());
+
+        // The simple line is short, but the call itself is not
+        let message = tool_use_message("Bash", serde_json::json!({"command": "cargo test"}));
+        let simple = formatter
+            .with_tool_display_mode(crate::ToolDisplayMode::Simple)
+            .with_min_content_len(Some(15));
+        assert!(simple.format_visible(&message).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_sidechain_reply_nested_under_task() {
+        let task = tool_use_message(
+            "Task",
+            serde_json::json!({"description": "Find callers", "prompt": "Search the repo"}),
+        );
+        let mut reply = create_test_message();
+        reply.role = MessageRole::Assistant;
+        reply.content = "Found two callers:\nmain.rs\nlib.rs".to_string();
+        reply.is_sidechain = true;
+        reply.task = Some("Find callers".to_string());
+
+        let formatter = LogFormatter::new()
+            .with_timestamp(false)
+            .with_hide_sidechains(false);
+        let task = formatter.format_message(&task).unwrap();
+        let reply = formatter.format_message(&reply).unwrap();
+
+        assert!(task.starts_with("🤖 Claude: "), "{task}");
+        // The gutter is added for the terminal only
+        assert!(
+            reply.starts_with("🤖 Claude [sidechain: Find callers]:"),
+            "{reply}"
+        );
+        let nested = nest_sidechain(&reply);
+        let lines: Vec<_> = nested.lines().collect();
+        assert!(lines[0].starts_with("  │ 🤖 Claude [sidechain: Find callers]:"));
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1..].iter().all(|line| line.starts_with("  │   ")));
+    }
+
+    #[test]
+    fn test_redaction() {
+        let mut message = create_test_message();
+        message.content = "token sk-abcdefghijklmnopqrstu for bob@example.com".to_string();
+
+        let result = LogFormatter::new()
+            .with_timestamp(false)
+            .with_redactor(Some(Redactor::builtin()))
+            .format_message(&message)
+            .unwrap();
+        assert_eq!(result, "👤 User: token [REDACTED] for [EMAIL]");
+    }
+
+    #[test]
+    fn test_flatten_newlines() {
+        let mut message = create_test_message();
+        message.content = "first\nsecond\r\nthird".to_string();
+
+        let result = LogFormatter::new()
+            .with_timestamp(false)
+            .with_flatten_newlines(true)
+            .format_message(&message)
+            .unwrap();
+        assert_eq!(result, "👤 User: first ⏎ second ⏎ third");
+    }
+
+    #[test]
+    fn test_summary_rendering() {
+        let mut message = create_test_message();
+        message.role = MessageRole::Summary;
+        message.content = "Fix login bug".to_string();
+
+        let result = LogFormatter::new()
+            .with_timestamp(false)
+            .format_message(&message)
+            .unwrap();
+        assert_eq!(result, "📋 Summary: Fix login bug");
+    }
+
     #[test]
     fn test_compact_mode() {
         let formatter = LogFormatter::new().with_compact_mode(true);
@@ -502,6 +1645,31 @@ mod tests {
         assert!(!result.contains('\n'));
     }
 
+    #[test]
+    fn test_compact_mode_collapses_tool_lines() {
+        let formatter = LogFormatter::new()
+            .with_compact_mode(true)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed);
+        let message = tool_use_message(
+            "TodoWrite",
+            serde_json::json!({"todos": [
+                {"content": "First", "status": "pending", "priority": "high"},
+                {"content": "Second", "status": "completed", "priority": "low"}
+            ]}),
+        );
+
+        let result = formatter.format_message(&message).unwrap();
+        assert!(!result.contains('\n'));
+        assert!(result.contains("First"));
+
+        // Multi-byte content is truncated on character boundaries
+        let mut long = create_test_message();
+        long.content = "日本語\n".repeat(50);
+        let result = formatter.format_message(&long).unwrap();
+        assert!(!result.contains('\n'));
+        assert!(result.ends_with("..."));
+    }
+
     #[test]
     fn test_session_id_display() {
         let formatter = LogFormatter::new().with_session_id(true);
@@ -511,6 +1679,317 @@ mod tests {
         assert!(result.contains("test-ses"));
     }
 
+    #[test]
+    fn test_session_id_end_to_end() {
+        let line = r#"{"type":"user","message":{"role":"user","content":"Hi"},"timestamp":"2025-01-01T12:00:00Z","sessionId":"0123456789abcdef","uuid":"1"}"#;
+        let message = crate::parser::LogParser::new()
+            .parse_line(line)
+            .unwrap()
+            .unwrap();
+
+        let formatter = LogFormatter::new().with_session_id(true);
+        let result = formatter.format_message(&message).unwrap();
+        assert!(result.contains("👤 User (01234567): Hi"));
+
+        let disabled = LogFormatter::new().format_message(&message).unwrap();
+        assert!(!disabled.contains("01234567"));
+    }
+
+    #[test]
+    fn test_short_session_id_does_not_panic() {
+        let formatter = LogFormatter::new().with_session_id(true);
+        let mut message = create_test_message();
+        message.session_id = "abc".to_string();
+
+        let result = formatter.format_message(&message).unwrap();
+        assert!(result.contains("(abc)"));
+        assert_eq!(short_session_id("セッションIDです長い"), "セッションIDで");
+    }
+
+    #[test]
+    fn test_code_fence_contents_preserved() {
+        let formatter = LogFormatter::new();
+        let code = "fn main() {\n    println!(\"hi\");\n\n}";
+        let content = format!("Here is the code:\n```rust\n{code}\n```\nDone.");
+
+        let result = formatter.format_content(&content);
+        assert!(result.contains(&format!("  ```rust\n{code}\n  ```")));
+        assert!(result.starts_with("  Here is the code:"));
+        assert!(result.ends_with("  Done."));
+    }
+
+    #[test]
+    fn test_session_header() {
+        let formatter = LogFormatter::new();
+        assert_eq!(
+            formatter.format_session_header("abc12345-6789", "claude-logger"),
+            "── session abc12345 (claude-logger) ──"
+        );
+
+        let ascii = LogFormatter::new().with_theme(Theme::Ascii);
+        assert!(ascii.format_session_header("abc", "p").is_ascii());
+    }
+
+    #[test]
+    fn test_tool_icons() {
+        let formatter = LogFormatter::new();
+        let mut message = create_test_message();
+        message.role = MessageRole::Assistant;
+        message.raw_content = Some(serde_json::json!([
+            {"type": "tool_use", "name": "Bash", "input": {"command": "ls"}}
+        ]));
+
+        let result = formatter.format_message(&message).unwrap();
+        assert!(result.contains("💻 Bash"));
+        assert_eq!(tool_icon("SomethingNew"), "🔧");
+    }
+
+    #[test]
+    fn test_mcp_tool_name() {
+        let formatter = LogFormatter::new().with_timestamp(false);
+        let message = tool_use_message("mcp__github__create_issue", serde_json::json!({}));
+        let result = formatter.format_message(&message).unwrap();
+        assert!(result.ends_with("🔌 github/create_issue"));
+
+        assert_eq!(split_mcp_tool("mcp__github"), None);
+        assert_eq!(tool_display_name("Bash"), "Bash");
+    }
+
+    fn tool_use_message(name: &str, input: Value) -> LogMessage {
+        let mut message = create_test_message();
+        message.role = MessageRole::Assistant;
+        message.raw_content = Some(serde_json::json!([
+            {"type": "tool_use", "name": name, "input": input}
+        ]));
+        message
+    }
+
+    #[test]
+    fn test_text_only_keeps_assistant_prose() {
+        let formatter = LogFormatter::new()
+            .with_timestamp(false)
+            .with_indent(false)
+            .with_tool_display_mode(crate::ToolDisplayMode::None)
+            .with_role_filter(Some(MessageRole::Assistant))
+            .with_text_only(true);
+
+        let mut message = create_test_message();
+        message.role = MessageRole::Assistant;
+        message.content = "Let me check.\n[Tool Use: Bash]\n[Thinking...]\nAll good.".to_string();
+        message.raw_content = Some(serde_json::json!([
+            {"type": "thinking", "thinking": "hmm"},
+            {"type": "text", "text": "Let me check."},
+            {"type": "tool_use", "name": "Bash", "input": {"command": "ls"}},
+            {"type": "text", "text": "All good."}
+        ]));
+        assert_eq!(
+            formatter.format_message(&message).unwrap(),
+            "🤖 Claude: Let me check.\nAll good."
+        );
+
+        let tool_only = tool_use_message("Bash", serde_json::json!({"command": "ls"}));
+        assert_eq!(formatter.format_visible(&tool_only).unwrap(), None);
+
+        let user = create_test_message();
+        assert_eq!(formatter.format_visible(&user).unwrap(), None);
+    }
+
+    #[test]
+    fn test_detailed_webfetch_url() {
+        let formatter =
+            LogFormatter::new().with_tool_display_mode(crate::ToolDisplayMode::Detailed);
+        let message = tool_use_message(
+            "WebFetch",
+            serde_json::json!({"url": "https://example.com", "prompt": "Summarize"}),
+        );
+
+        let result = formatter.format_message(&message).unwrap();
+        assert!(result.ends_with("🌐 WebFetch: https://example.com"));
+    }
+
+    #[test]
+    fn test_detailed_unrecognized_input_as_json() {
+        let formatter =
+            LogFormatter::new().with_tool_display_mode(crate::ToolDisplayMode::Detailed);
+        let message = tool_use_message("mcp__demo__run", serde_json::json!({"foo": 1}));
+
+        let result = formatter.format_message(&message).unwrap();
+        assert!(result.ends_with(r#": {"foo":1}"#), "{result}");
+
+        // Long arguments are cut to the configured length
+        let message = tool_use_message(
+            "mcp__demo__run",
+            serde_json::json!({"foo": "x".repeat(100)}),
+        );
+        let result = formatter
+            .with_tool_input_len(20)
+            .format_message(&message)
+            .unwrap();
+        assert!(result.ends_with(r#": {"foo":"xxxxxxxxxxxx..."#), "{result}");
+    }
+
+    #[test]
+    fn test_detailed_grep_pattern() {
+        let formatter =
+            LogFormatter::new().with_tool_display_mode(crate::ToolDisplayMode::Detailed);
+        let message = tool_use_message("Grep", serde_json::json!({"pattern": "fn main"}));
+
+        let result = formatter.format_message(&message).unwrap();
+        assert!(result.ends_with("🔍 Grep: fn main"));
+    }
+
+    #[test]
+    fn test_detailed_write_previews_content() {
+        let formatter = LogFormatter::new()
+            .with_timestamp(false)
+            .with_indent(false)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed)
+            .with_max_result_lines(Some(6));
+        let content: Vec<String> = (1..=10).map(|i| format!("line {i}")).collect();
+        let message = tool_use_message(
+            "Write",
+            serde_json::json!({"file_path": "src/lib.rs", "content": content.join("\n")}),
+        );
+
+        let result = formatter.format_message(&message).unwrap();
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "🤖 Claude: ✍️ Write: src/lib.rs",
+                "```",
+                "line 1",
+                "line 2",
+                "… (+8 more lines)",
+                "```"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detailed_read_file_path_middle_truncated() {
+        let formatter =
+            LogFormatter::new().with_tool_display_mode(crate::ToolDisplayMode::Detailed);
+        let path = "/home/suzuki/repos/claude-logger/some/deeply/nested/module/path/formatter.rs";
+        let message = tool_use_message("Read", serde_json::json!({"file_path": path}));
+
+        let result = formatter.format_message(&message).unwrap();
+        let shown = result.split("📖 Read: ").nth(1).unwrap();
+        assert_eq!(shown.chars().count(), 50);
+        assert!(shown.starts_with("/home/suzuki/"));
+        assert!(shown.ends_with(".../formatter.rs"));
+
+        let short = tool_use_message("Edit", serde_json::json!({"file_path": "src/main.rs"}));
+        let result = formatter.format_message(&short).unwrap();
+        assert!(result.ends_with("✏️ Edit: src/main.rs"));
+    }
+
+    #[test]
+    fn test_detailed_result_prefers_tool_use_result() {
+        let mut message = create_test_message();
+        message.raw_content = Some(serde_json::json!([
+            {"type": "tool_result", "tool_use_id": "t1", "content": "The file has been updated."}
+        ]));
+        message.tool_use_result = Some(serde_json::json!({
+            "filePath": "/src/main.rs",
+            "structuredPatch": [{"oldStart": 1, "lines": ["-let x = 1;", "+let x = 2;"]}]
+        }));
+        let formatter = LogFormatter::new()
+            .with_timestamp(false)
+            .with_indent(false)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed)
+            .with_max_result_lines(Some(10));
+
+        assert_eq!(
+            formatter.format_message(&message).unwrap(),
+            "👤 User: ✅ -let x = 1;\n+let x = 2;"
+        );
+
+        // Command output keeps stderr after stdout
+        message.tool_use_result = Some(serde_json::json!({"stdout": "ok", "stderr": "warning"}));
+        assert_eq!(
+            formatter.format_message(&message).unwrap(),
+            "👤 User: ✅ ok\nwarning"
+        );
+
+        // Unrecognised shapes fall back to the content block
+        message.tool_use_result = Some(serde_json::json!({"unknown": true}));
+        assert_eq!(
+            formatter.format_message(&message).unwrap(),
+            "👤 User: ✅ The file has been updated."
+        );
+    }
+
+    #[test]
+    fn test_max_result_lines_caps_tool_result() {
+        let formatter = LogFormatter::new()
+            .with_timestamp(false)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed)
+            .with_max_result_lines(Some(5));
+
+        let output: Vec<String> = (1..=50).map(|i| format!("line {i}")).collect();
+        let mut message = create_test_message();
+        message.raw_content = Some(serde_json::json!([
+            {"type": "tool_result", "tool_use_id": "t1", "content": output.join("\n")}
+        ]));
+
+        let result = formatter.format_message(&message).unwrap();
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].ends_with("✅ line 1"));
+        assert!(lines[4].ends_with("line 5"));
+        assert!(lines[5].ends_with("… (+45 more lines)"));
+    }
+
+    #[test]
+    fn test_tool_verbosity_override() {
+        let formatter = LogFormatter::new()
+            .with_tool_display_mode(crate::ToolDisplayMode::Simple)
+            .with_tool_verbosity([
+                ("Bash".to_string(), crate::ToolDisplayMode::Detailed),
+                ("NoSuchTool".to_string(), crate::ToolDisplayMode::None),
+            ]);
+
+        let bash = tool_use_message("Bash", serde_json::json!({"command": "cargo build"}));
+        let result = formatter.format_message(&bash).unwrap();
+        assert!(result.ends_with("💻 Bash: cargo build"));
+
+        let grep = tool_use_message("Grep", serde_json::json!({"pattern": "fn main"}));
+        let result = formatter.format_message(&grep).unwrap();
+        assert!(result.ends_with("🔍 Grep"));
+
+        assert!(!formatter.tool_overrides.contains_key("NoSuchTool"));
+    }
+
+    #[test]
+    fn test_ascii_theme_is_pure_ascii() {
+        let formatter = LogFormatter::new()
+            .with_theme(Theme::Ascii)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed);
+
+        let mut tool_use = create_test_message();
+        tool_use.role = MessageRole::Assistant;
+        tool_use.raw_content = Some(serde_json::json!([
+            {"type": "tool_use", "name": "Bash", "input": {"command": "ls"}}
+        ]));
+        let mut todo = create_test_message();
+        todo.raw_content = Some(serde_json::json!([
+            {"type": "tool_use", "name": "TodoWrite", "input": {"todos": [
+                {"content": "Task", "status": "pending", "priority": "high"}
+            ]}}
+        ]));
+        let mut thinking = create_test_message();
+        thinking.raw_content = Some(serde_json::json!([{"type": "thinking"}]));
+
+        for message in [create_test_message(), tool_use, todo, thinking] {
+            let result = formatter.format_message(&message).unwrap();
+            assert!(result.is_ascii(), "non-ASCII output: {result}");
+        }
+
+        let user = formatter.format_message(&create_test_message()).unwrap();
+        assert!(user.contains("[user]: "));
+    }
+
     #[test]
     fn test_todowrite_simple_format() {
         let formatter = LogFormatter::new().with_tool_display_mode(crate::ToolDisplayMode::Simple);
@@ -536,7 +2015,7 @@ mod tests {
             }
         ]);
 
-        let result = formatter.format_todos_input(&todos_json);
+        let result = formatter.format_todos_for_terminal(&todos_json, &formatter.tool_display_mode);
         assert!(result.contains("3 tasks"));
         assert!(result.contains("1 pending"));
         assert!(result.contains("1 in progress"));
@@ -569,9 +2048,63 @@ mod tests {
             }
         ]);
 
-        let result = formatter.format_todos_input(&todos_json);
+        let result = formatter.format_todos_for_terminal(&todos_json, &formatter.tool_display_mode);
         assert!(result.contains("\n  [x] 🔴 Complete task 1"));
         assert!(result.contains("\n  [~] 🟡 Work on task 2 (in progress)"));
         assert!(result.contains("\n  [ ] 🟢 Start task 3"));
     }
+
+    #[test]
+    fn test_slack_todos_hide_completed() {
+        let todos = serde_json::json!([
+            {"content": "Done one", "status": "completed"},
+            {"content": "Done two", "status": "completed"},
+            {"content": "Doing", "status": "in_progress"},
+            {"content": "Later", "status": "pending"}
+        ]);
+        let formatter = LogFormatter::new()
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed)
+            .with_hide_completed_todos(true);
+
+        let result = formatter.format_todos_for_slack(&todos);
+        let lines: Vec<_> = result.lines().collect();
+        assert_eq!(lines[0], "2/4 completed");
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].contains("*Doing* (in progress)"));
+        assert!(lines[2].contains("*Later*"));
+        assert!(!result.contains("Done"));
+    }
+
+    #[test]
+    fn test_todowrite_progress_bar() {
+        let statuses = [
+            "completed",
+            "completed",
+            "completed",
+            "pending",
+            "in_progress",
+        ];
+        let todos: Vec<Value> = statuses
+            .iter()
+            .map(|status| serde_json::json!({"content": "task", "status": status}))
+            .collect();
+        let message = tool_use_message("TodoWrite", serde_json::json!({ "todos": todos }));
+        let formatter = LogFormatter::new()
+            .with_timestamp(false)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed);
+
+        let result = formatter
+            .clone()
+            .with_todo_progress(Some(10))
+            .format_message(&message)
+            .unwrap();
+        let header = result.lines().next().unwrap();
+        assert!(
+            header.ends_with("📝 TodoWrite: [██████░░░░] 3/5 done"),
+            "{header}"
+        );
+
+        let result = formatter.format_message(&message).unwrap();
+        assert!(!result.contains("done"));
+    }
 }
@@ -0,0 +1,169 @@
+//! Parsing and formatting of Claude Code JSONL conversation logs.
+//!
+//! The `claude-logger` binary is a thin CLI over this library; the same
+//! parser and formatter can be embedded in other tools.
+//!
+//! ```
+//! use claude_logger::{LogFormatter, LogParser, MessageRole, ToolDisplayMode};
+//!
+//! let line = r#"{"type":"user","message":{"role":"user","content":"Hello"},"timestamp":"2025-01-01T12:00:00Z","sessionId":"abcdef123456","uuid":"1"}"#;
+//!
+//! let message = LogParser::new().parse_line(line)?.expect("a user message");
+//! assert!(matches!(message.role, MessageRole::User));
+//!
+//! let formatter = LogFormatter::new()
+//!     .with_timestamp(false)
+//!     .with_tool_display_mode(ToolDisplayMode::Simple);
+//! assert_eq!(formatter.format_message(&message)?, "👤 User: Hello");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! To follow a project live, [`watcher::LogWatcher::stream`] yields messages
+//! as they are written:
+//!
+//! ```no_run
+//! use claude_logger::watcher::LogWatcher;
+//! use tokio_stream::StreamExt;
+//!
+//! # async fn run() {
+//! let mut messages = Box::pin(LogWatcher::new().stream("/path/to/project"));
+//! while let Some(message) = messages.next().await {
+//!     println!("{:?}: {}", message.role, message.content);
+//! }
+//! # }
+//! ```
+
+use clap::ValueEnum;
+
+pub mod dedup;
+pub mod doctor;
+#[cfg(unix)]
+pub mod events;
+pub mod exec;
+pub mod formatter;
+pub mod metrics;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod parser;
+pub mod project;
+pub mod queue;
+pub mod redact;
+pub mod subagents;
+pub mod usage;
+pub mod watcher;
+pub mod webhook;
+
+pub use formatter::LogFormatter;
+pub use parser::{BlockHandler, LogMessage, LogParser, MessageRole};
+
+/// Where printed output goes: stdout or stderr unless replaced, e.g. by a
+/// buffer in tests. Shared between clones of a watcher or sender.
+pub(crate) type SharedWriter = std::sync::Arc<std::sync::Mutex<dyn std::io::Write + Send>>;
+
+/// Write `text` to `writer`, failing like `print!` does when it cannot
+pub(crate) fn write_output(writer: &SharedWriter, text: &str) {
+    let mut writer = writer.lock().unwrap();
+    if let Err(e) = writer
+        .write_all(text.as_bytes())
+        .and_then(|()| writer.flush())
+    {
+        panic!("failed printing output: {e}");
+    }
+}
+
+/// Verbosity of diagnostic output on stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for tracing::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => tracing::Level::ERROR,
+            LogLevel::Warn => tracing::Level::WARN,
+            LogLevel::Info => tracing::Level::INFO,
+            LogLevel::Debug => tracing::Level::DEBUG,
+            LogLevel::Trace => tracing::Level::TRACE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ToolDisplayMode {
+    /// Hide all tool information
+    None,
+    /// Show simple tool indicators (🔧 Bash)
+    Simple,
+    /// Show detailed tool information including parameters
+    Detailed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Theme {
+    /// Emoji role and tool indicators (👤 User, 🔧 Bash)
+    Emoji,
+    /// Plain ASCII indicators ([user], [tool] Bash)
+    Ascii,
+    /// No indicators, just role names and text
+    None,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum WebhookFormat {
+    /// Generic JSON webhook format
+    Generic,
+    /// Slack webhook format
+    Slack,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SlackStyle {
+    /// Top-level section blocks
+    #[default]
+    Blocks,
+    /// Blocks inside an attachment with a role-colored bar
+    Attachments,
+}
+
+/// Where reading starts in session files that exist when watching begins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StartPosition {
+    /// Read every existing line
+    Beginning,
+    /// Skip existing lines and show only what is appended afterwards
+    End,
+}
+
+/// Ready-made choices of which messages are posted to webhooks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum WebhookPreset {
+    /// Every message, including bare tool calls and results
+    All,
+    /// Only Claude's messages that contain prose
+    AssistantText,
+    /// Only tool results that report an error
+    Errors,
+}
+
+/// What to do when the webhook queue is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum WebhookOverflow {
+    /// Wait for the webhook to catch up, pausing output
+    #[default]
+    Block,
+    /// Discard the oldest queued message
+    Drop,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ProjectSort {
+    /// Sort alphabetically by directory name
+    Name,
+    /// Sort by most recent session activity
+    Recent,
+}
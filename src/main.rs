@@ -1,46 +1,56 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand, ValueEnum};
+use chrono::{DateTime, Utc};
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::engine::{ArgValueCompleter, CompletionCandidate};
+use clap_complete::{CompleteEnv, Shell};
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use url::Url;
 
-mod formatter;
-mod parser;
-mod watcher;
-mod webhook;
-
-use watcher::LogWatcher;
-
-#[derive(Debug, Clone, ValueEnum)]
-pub enum ToolDisplayMode {
-    /// Hide all tool information
-    None,
-    /// Show simple tool indicators (🔧 Bash)
-    Simple,
-    /// Show detailed tool information including parameters
-    Detailed,
-}
-
-#[derive(Debug, Clone, ValueEnum)]
-pub enum WebhookFormat {
-    /// Generic JSON webhook format
-    Generic,
-    /// Slack webhook format
-    Slack,
-}
+#[cfg(unix)]
+use claude_logger::events;
+use claude_logger::exec::ExecFormatter;
+use claude_logger::project::decode_project_path;
+use claude_logger::redact::{RedactPattern, Redactor};
+use claude_logger::watcher::{parse_time_anchor, LogWatcher};
+use claude_logger::webhook::{self, WebhookRoute, WebhookTemplate};
+use claude_logger::{doctor, formatter, metrics, usage, LogFormatter, MessageRole};
+use claude_logger::{
+    LogLevel, ProjectSort, SlackStyle, StartPosition, Theme, ToolDisplayMode, WebhookFormat,
+    WebhookOverflow, WebhookPreset,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// Claude projects directory (default: ~/.claude/projects); repeat to
+    /// aggregate several, e.g. one per user
+    #[arg(long, global = true, env = "CLAUDE_LOGGER_DIR")]
+    claude_dir: Vec<PathBuf>,
+
+    /// Verbosity of diagnostics written to stderr
+    #[arg(long, global = true, default_value = "info")]
+    log_level: LogLevel,
+
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
+// Parsed once at startup, so the size of the Watch variant does not matter
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Watch Claude Code log files and stream to stdout
+    #[command(group(ArgGroup::new("webhook").args(["webhook_url", "webhook_route"]).multiple(true)))]
     Watch {
-        /// Path to the project to monitor (e.g. /home/suzuki/.claude/projects/-home-suzuki-repos)
-        #[arg(short, long)]
+        /// Project to monitor: a Claude project directory (e.g.
+        /// /home/suzuki/.claude/projects/-home-suzuki-repos) or the repository path itself
+        #[arg(short, long, add = ArgValueCompleter::new(complete_project_path))]
         project_path: Option<PathBuf>,
 
         /// Automatically select the latest project
@@ -51,64 +61,869 @@ enum Commands {
         #[arg(short, long)]
         all: bool,
 
+        /// With --all, only watch projects whose path matches this substring or regex
+        #[arg(long, value_name = "PATTERN", requires = "all")]
+        project_name_filter: Option<String>,
+
+        /// With --all, watch at most N projects, most recently active first
+        #[arg(long, value_name = "N", requires = "all")]
+        max_sessions: Option<usize>,
+
+        /// With --all, skip projects without a session modified within DURATION, e.g. 24h
+        #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, requires = "all")]
+        only_sessions_active_within: Option<Duration>,
+
         /// Tool display mode: none, simple, or detailed
         #[arg(long, default_value = "simple")]
         tool_display: ToolDisplayMode,
 
-        /// Webhook URL to post messages
+        /// Show only Claude's prose: no user messages, tool calls or thinking
+        #[arg(long, conflicts_with = "tool_display")]
+        assistant_only_text: bool,
+
+        /// Print at most N messages per second, e.g. when replaying a large backlog
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        max_rate: Option<u32>,
+
+        /// Format stdout lines with a program that reads JSON messages and prints one line each
+        #[arg(long, value_name = "CMD")]
+        exec_formatter: Option<String>,
+
+        /// Print how many messages would be emitted, e.g. with --include-existing, and exit
+        #[arg(long)]
+        count: bool,
+
+        /// Prefix each emitted message with an incrementing sequence number
+        #[arg(long)]
+        number: bool,
+
+        /// Omit the [HH:MM:SS] prefix from each message
+        #[arg(long)]
+        no_timestamp: bool,
+
+        /// strftime format of the timestamp prefix (e.g. %H:%M:%S%.3f for milliseconds)
+        #[arg(long, default_value = formatter::DEFAULT_TIME_FORMAT, value_parser = formatter::validate_time_format)]
+        time_format: String,
+
+        /// Show timestamps in UTC instead of local time
+        #[arg(long)]
+        utc: bool,
+
+        /// Prefix each message with its short session id
+        #[arg(long)]
+        show_session_id: bool,
+
+        /// Print a header whenever output switches to a different session
+        #[arg(long)]
+        group_sessions: bool,
+
+        /// Keep a line of running totals below the output (terminals only)
+        #[arg(long)]
+        status_line: bool,
+
+        /// Collapse consecutive identical lines into one with an (xN) suffix
+        #[arg(long)]
+        collapse_repeats: bool,
+
+        /// Print each message on a single line, capped at 100 characters
+        #[arg(long)]
+        compact: bool,
+
+        /// Mask emails, API keys, bearer tokens and home directories in output and webhooks
+        #[arg(long)]
+        redact: bool,
+
+        /// Extra redaction rule as REGEX=REPLACEMENT (repeatable)
+        #[arg(long, value_name = "REGEX=REPLACEMENT")]
+        redact_pattern: Vec<RedactPattern>,
+
+        /// Print each message on one line, replacing line breaks with " ⏎ "
+        #[arg(long)]
+        flatten_newlines: bool,
+
+        /// Do not indent continuation lines of multi-line messages
+        #[arg(long)]
+        no_indent: bool,
+
+        /// Override the tool display mode per tool: NAME=MODE (repeatable)
+        #[arg(long, value_name = "NAME=MODE", value_parser = parse_tool_verbosity)]
+        tool_verbosity: Vec<(String, ToolDisplayMode)>,
+
+        /// Hide meta entries Claude Code injects, such as command caveats
+        #[arg(long)]
+        hide_meta: bool,
+
+        /// Hide sub-agent (Task) conversations (the default)
+        #[arg(long, overrides_with = "show_sidechains")]
+        hide_sidechains: bool,
+
+        /// Show sub-agent (Task) conversations, nested under the Task call that started them
+        #[arg(long, overrides_with = "hide_sidechains")]
+        show_sidechains: bool,
+
+        /// Show session summaries written by Claude Code
+        #[arg(long)]
+        show_summaries: bool,
+
+        /// Hide messages shorter than N characters, e.g. a bare "ok", on stdout
+        #[arg(long, value_name = "N")]
+        min_content_len: Option<usize>,
+
+        /// Also keep messages shorter than --min-content-len from webhooks
+        #[arg(long, requires = "min_content_len")]
+        min_content_len_webhooks: bool,
+
+        /// Join text blocks split mid-sentence with a space instead of a line break
+        #[arg(long)]
+        merge_adjacent_text: bool,
+
+        /// In detailed mode, show an excerpt of Claude's thinking (hidden by default)
+        #[arg(long)]
+        show_thinking: bool,
+
+        /// In detailed mode, list the sources cited by Claude's answers
         #[arg(long)]
+        show_citations: bool,
+
+        /// In detailed mode, head TodoWrite checklists with a progress bar WIDTH cells wide
+        #[arg(
+            long,
+            value_name = "WIDTH",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "10"
+        )]
+        todo_progress: Option<usize>,
+
+        /// Show at most N lines per message and tool result
+        #[arg(long, value_name = "N")]
+        max_result_lines: Option<usize>,
+
+        /// Cut tool inputs in detailed mode to N characters
+        #[arg(long, value_name = "N", default_value_t = formatter::DEFAULT_TOOL_INPUT_LEN)]
+        tool_input_len: usize,
+
+        /// Indicator theme: emoji, ascii, or none
+        #[arg(long, default_value = "emoji")]
+        theme: Theme,
+
+        /// Color just the role indicator, keeping content plain for grep (terminals only)
+        #[arg(long)]
+        color_role_only: bool,
+
+        /// Color tool results green on success and red on error (terminals only)
+        #[arg(long)]
+        color_results: bool,
+
+        /// Webhook URL to post messages
+        #[arg(long, value_parser = webhook::parse_webhook_url)]
         webhook_url: Option<Url>,
 
+        /// Route messages to a different webhook: ROLE=URL or project=NAME:URL (repeatable)
+        #[arg(long, value_name = "ROUTE")]
+        webhook_route: Vec<WebhookRoute>,
+
+        /// Only send messages to the webhook, printing nothing to stdout
+        #[arg(long, requires = "webhook")]
+        no_stdout: bool,
+
+        /// Print the original JSONL line of each new message instead of formatting it
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "webhook",
+                "tail",
+                "exec_formatter",
+                "count",
+                "redact",
+                "redact_pattern",
+                "number"
+            ]
+        )]
+        jsonl_passthrough: bool,
+
         /// Webhook format: generic or slack
         #[arg(long, default_value = "generic")]
         webhook_format: WebhookFormat,
 
+        /// Send generic webhook messages in batches as newline-delimited JSON
+        #[arg(long)]
+        webhook_batch: bool,
+
+        /// In Slack TodoWrite checklists, list only open items under a completed count
+        #[arg(long)]
+        todo_hide_completed: bool,
+
+        /// Webhook preset: all, assistant-text or errors (default: skip bare tool calls)
+        #[arg(long, value_name = "PRESET")]
+        webhook_on: Option<WebhookPreset>,
+
+        /// Only forward messages with this role to webhooks (repeatable; default: all)
+        #[arg(long, value_name = "ROLE")]
+        webhook_roles: Vec<MessageRole>,
+
+        /// Include the original log entry under `raw` in generic webhook payloads
+        #[arg(long)]
+        webhook_include_raw: bool,
+
+        /// Print webhook payloads to stderr instead of sending them
+        #[arg(long)]
+        webhook_dry_run: bool,
+
+        /// JSON shape of generic webhook payloads, with {{timestamp}}, {{role}},
+        /// {{content}}, {{session_id}}, {{project}} and {{uuid}} placeholders
+        #[arg(long, value_name = "JSON")]
+        format_template: Option<WebhookTemplate>,
+
+        /// Layout of Slack webhook messages
+        #[arg(long, value_enum, default_value_t = SlackStyle::Blocks)]
+        slack_style: SlackStyle,
+
+        /// Slack bot name, with {{project}}, {{session}} and {{role}} placeholders
+        #[arg(long, value_name = "TEMPLATE", value_parser = webhook::validate_slack_username)]
+        slack_username: Option<String>,
+
+        /// Slack bot icon, e.g. :robot:
+        #[arg(long, value_name = "EMOJI")]
+        slack_icon_emoji: Option<String>,
+
+        /// Text put before every webhook message, e.g. [prod] or a Slack mention
+        #[arg(long, value_name = "STR")]
+        webhook_prefix: Option<String>,
+
+        /// Text put on its own line after every webhook message
+        #[arg(long, value_name = "STR")]
+        webhook_suffix: Option<String>,
+
+        /// When the webhook falls 1000 messages behind: block output or drop the oldest
+        #[arg(long, value_enum, default_value_t = WebhookOverflow::Block)]
+        webhook_overflow: WebhookOverflow,
+
+        /// Give up on a webhook request after SECONDS
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            default_value_t = webhook::DEFAULT_TIMEOUT.as_secs(),
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        webhook_timeout: u64,
+
+        /// Post a heartbeat to the webhook after SECONDS without messages
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            requires = "webhook_url",
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        webhook_heartbeat: Option<u64>,
+
         /// Include existing messages from log files
         #[arg(long)]
         include_existing: bool,
+
+        /// Include existing messages from an RFC 3339 timestamp or a duration ago (e.g. 1h)
+        #[arg(
+            long,
+            value_name = "TIMESTAMP|DURATION",
+            value_parser = parse_time_anchor,
+            conflicts_with = "include_existing"
+        )]
+        include_existing_from: Option<DateTime<Utc>>,
+
+        /// Show only the last N existing messages before streaming new ones
+        #[arg(
+            long,
+            value_name = "N",
+            conflicts_with_all = ["include_existing", "include_existing_from"]
+        )]
+        tail: Option<usize>,
+
+        /// Read existing session files from the beginning, or only what is appended (end)
+        #[arg(
+            long,
+            value_name = "POSITION",
+            conflicts_with_all = ["include_existing", "include_existing_from", "tail"]
+        )]
+        start_position: Option<StartPosition>,
+
+        /// Resume from the message with this uuid, emitting only what follows it
+        #[arg(
+            long,
+            value_name = "UUID",
+            conflicts_with_all = ["include_existing", "include_existing_from", "tail", "start_position", "all"]
+        )]
+        after_uuid: Option<String>,
+
+        /// With --after-uuid, also emit the message with that uuid
+        #[arg(long, requires = "after_uuid")]
+        after_uuid_inclusive: bool,
+
+        /// Keep following a session file after it is renamed
+        #[arg(long)]
+        follow_rename: bool,
+
+        /// Tail only the newest session, switching when a newer one appears
+        #[arg(long)]
+        follow_latest: bool,
+
+        /// Exit after SECONDS without a new message (starts after any replay)
+        #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+        idle_timeout: Option<u64>,
+
+        /// Number of recent message uuids remembered to suppress duplicates (0 disables)
+        #[arg(long, value_name = "N", default_value_t = 10000)]
+        dedup_cache: usize,
+
+        /// Abort on the first malformed log line instead of skipping it
+        #[arg(long)]
+        strict: bool,
+
+        /// Recover lines with trailing commas or comments instead of skipping them
+        #[arg(long)]
+        lenient: bool,
+
+        /// Serve Prometheus metrics on this address (e.g. 127.0.0.1:9100)
+        #[arg(long)]
+        metrics_addr: Option<SocketAddr>,
+
+        /// Publish each message as a JSON line to clients of this Unix socket
+        #[cfg(unix)]
+        #[arg(long, value_name = "PATH")]
+        events_socket: Option<PathBuf>,
+
+        /// Export a span per message to this OTLP/HTTP collector (e.g. http://localhost:4318)
+        #[cfg(feature = "otel")]
+        #[arg(long, value_name = "URL")]
+        otlp_endpoint: Option<Url>,
+    },
+    /// Diagnose setup problems (projects directory, sessions, webhook)
+    Doctor {
+        /// Webhook URL to check for reachability
+        #[arg(long, value_parser = webhook::parse_webhook_url)]
+        webhook_url: Option<Url>,
+
+        /// Tool display mode that would be used by watch
+        #[arg(long, default_value = "simple")]
+        tool_display: ToolDisplayMode,
+
+        /// Indicator theme that would be used by watch
+        #[arg(long, default_value = "emoji")]
+        theme: Theme,
+    },
+    /// Re-stream a finished session at its original pace
+    Replay {
+        /// Session file to replay
+        session_file: PathBuf,
+
+        /// Playback speed multiplier (2 = twice as fast, 0 = no delays)
+        #[arg(long, default_value_t = 1.0, value_parser = parse_speed)]
+        speed: f64,
+
+        /// Tool display mode: none, simple, or detailed
+        #[arg(long, default_value = "simple")]
+        tool_display: ToolDisplayMode,
+
+        /// Indicator theme: emoji, ascii, or none
+        #[arg(long, default_value = "emoji")]
+        theme: Theme,
+
+        /// Webhook URL to post messages
+        #[arg(long, value_parser = webhook::parse_webhook_url)]
+        webhook_url: Option<Url>,
+
+        /// Webhook format: generic or slack
+        #[arg(long, default_value = "generic")]
+        webhook_format: WebhookFormat,
+    },
+    /// Show message and token totals for a session file or project directory
+    Stats {
+        /// Session file, or project directory to total across its sessions
+        path: PathBuf,
+
+        /// Print the totals as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// List available projects
-    List,
+    List {
+        /// Sort order: name or recent
+        #[arg(long, default_value = "name")]
+        sort: ProjectSort,
+
+        /// Show last-modified time and message count per project
+        #[arg(short, long)]
+        details: bool,
+
+        /// Print the project list as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Show the decoded filesystem path next to each project directory name
+        #[arg(long)]
+        decode_paths: bool,
+
+        /// Skip projects without a session modified within DURATION, e.g. 24h
+        #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+        only_sessions_active_within: Option<Duration>,
+    },
+    /// Print a shell completion script
+    Completions {
+        /// Shell to generate the script for
+        shell: Shell,
+    },
+}
+
+/// Complete `--project-path` with the paths of known projects. Only
+/// `CLAUDE_LOGGER_DIR` is honored, as other arguments are not parsed yet.
+fn complete_project_path(current: &OsStr) -> Vec<CompletionCandidate> {
+    let claude_dirs = std::env::var_os("CLAUDE_LOGGER_DIR")
+        .map(|dir| vec![PathBuf::from(dir)])
+        .unwrap_or_default();
+    let watcher = LogWatcher::new().with_claude_dirs(claude_dirs);
+    let Ok(claude_dirs) = watcher.claude_dirs() else {
+        return Vec::new();
+    };
+    project_paths(claude_dirs, &current.to_string_lossy())
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Decoded paths of the projects in `claude_dirs` that start with `prefix`
+fn project_paths(claude_dirs: &[PathBuf], prefix: &str) -> Vec<String> {
+    let mut paths: Vec<String> = claude_dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| decode_project_path(&entry.file_name().to_string_lossy()))
+        .filter(|path| path.starts_with(prefix))
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Redaction rules from `--redact` and `--redact-pattern`, if any were asked for
+fn redactor(builtin: bool, patterns: &[RedactPattern]) -> Option<Redactor> {
+    if !builtin && patterns.is_empty() {
+        return None;
+    }
+    let redactor = if builtin {
+        Redactor::builtin()
+    } else {
+        Redactor::default()
+    };
+    Some(redactor.with_patterns(patterns.to_vec()))
+}
+
+/// Parse a non-negative `--speed` multiplier
+fn parse_speed(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(speed) if speed >= 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(format!("expected a non-negative number, got {s:?}")),
+    }
+}
+
+/// Parse a `--tool-verbosity NAME=MODE` argument
+fn parse_tool_verbosity(s: &str) -> Result<(String, ToolDisplayMode), String> {
+    let (name, mode) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=MODE, got {s:?}"))?;
+    let mode = ToolDisplayMode::from_str(mode, true)?;
+    Ok((name.to_string(), mode))
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    CompleteEnv::with_factory(Cli::command).complete();
     let cli = Cli::parse();
 
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::from(cli.log_level))
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .without_time()
+        .init();
+
     match &cli.command {
         Commands::Watch {
             project_path,
             latest,
             all,
+            project_name_filter,
+            max_sessions,
+            only_sessions_active_within,
             tool_display,
+            assistant_only_text,
+            tool_verbosity,
+            max_result_lines,
+            tool_input_len,
+            todo_progress,
+            show_thinking,
+            show_citations,
+            show_summaries,
+            merge_adjacent_text,
+            hide_meta,
+            hide_sidechains,
+            show_sidechains,
+            min_content_len,
+            min_content_len_webhooks,
+            compact,
+            flatten_newlines,
+            redact,
+            redact_pattern,
+            no_indent,
+            no_timestamp,
+            time_format,
+            utc,
+            number,
+            max_rate,
+            exec_formatter,
+            count,
+            show_session_id,
+            group_sessions,
+            collapse_repeats,
+            status_line,
+            theme,
+            color_role_only,
+            color_results,
             webhook_url,
+            webhook_route,
+            no_stdout,
+            jsonl_passthrough,
             webhook_format,
+            webhook_batch,
+            webhook_roles,
+            webhook_on,
+            todo_hide_completed,
+            webhook_include_raw,
+            webhook_dry_run,
+            webhook_heartbeat,
+            webhook_timeout,
+            webhook_overflow,
+            slack_style,
+            slack_username,
+            slack_icon_emoji,
+            webhook_prefix,
+            webhook_suffix,
+            format_template,
             include_existing,
+            include_existing_from,
+            tail,
+            start_position,
+            after_uuid,
+            after_uuid_inclusive,
+            follow_rename,
+            follow_latest,
+            idle_timeout,
+            dedup_cache,
+            strict,
+            lenient,
+            metrics_addr,
+            #[cfg(unix)]
+            events_socket,
+            #[cfg(feature = "otel")]
+            otlp_endpoint,
         } => {
+            // --assistant-only-text is a preset over the display options
+            let tool_display = if *assistant_only_text {
+                ToolDisplayMode::None
+            } else {
+                tool_display.clone()
+            };
             let mut watcher = LogWatcher::new()
-                .with_tool_display_mode(tool_display.clone())
+                .with_claude_dirs(cli.claude_dir.clone())
+                .with_tool_display_mode(tool_display)
+                .with_role_filter(assistant_only_text.then_some(MessageRole::Assistant))
+                .with_text_only(*assistant_only_text)
+                .with_tool_verbosity(tool_verbosity.clone())
+                .with_max_result_lines(*max_result_lines)
+                .with_tool_input_len(*tool_input_len)
+                .with_todo_progress(*todo_progress)
+                .with_show_thinking(*show_thinking)
+                .with_show_citations(*show_citations)
+                .with_show_summaries(*show_summaries)
+                .with_merge_adjacent_text(*merge_adjacent_text)
+                .with_hide_meta(*hide_meta)
+                .with_hide_sidechains(*hide_sidechains || !*show_sidechains)
+                .with_min_content_len(*min_content_len)
+                .with_min_content_len_webhooks(*min_content_len_webhooks)
+                .with_compact_mode(*compact)
+                .with_flatten_newlines(*flatten_newlines)
+                .with_indent(!*no_indent)
+                .with_timestamp(!*no_timestamp)
+                .with_time_format(time_format.clone())
+                .with_utc(*utc)
+                .with_session_id(*show_session_id)
+                .with_group_sessions(*group_sessions)
+                .with_collapse_repeats(*collapse_repeats)
+                .with_status_line(*status_line && std::io::stdout().is_terminal())
+                .with_theme(*theme)
+                .with_role_color(*color_role_only && std::io::stdout().is_terminal())
+                .with_result_color(*color_results && std::io::stdout().is_terminal())
+                .with_webhook_timeout(Some(Duration::from_secs(*webhook_timeout)))
                 .with_webhook(webhook_url.clone(), webhook_format.clone())
-                .with_include_existing(*include_existing);
+                .with_webhook_routes(webhook_route.clone(), webhook_format.clone())
+                .with_webhook_batch(*webhook_batch)
+                .with_webhook_overflow(*webhook_overflow)
+                .with_slack_style(*slack_style)
+                .with_slack_identity(slack_username.clone(), slack_icon_emoji.clone())
+                .with_webhook_text_wrapping(webhook_prefix.clone(), webhook_suffix.clone())
+                .with_webhook_template(format_template.clone())
+                .with_webhook_preset(*webhook_on)
+                .with_webhook_roles((!webhook_roles.is_empty()).then(|| webhook_roles.clone()))
+                .with_todo_hide_completed(*todo_hide_completed)
+                .with_webhook_include_raw(*webhook_include_raw)
+                .with_webhook_dry_run(*webhook_dry_run)
+                .with_webhook_heartbeat(webhook_heartbeat.map(Duration::from_secs))
+                .with_include_existing(*include_existing)
+                .with_include_existing_from(*include_existing_from)
+                .with_start_position(*start_position)
+                .with_after_uuid(after_uuid.clone())
+                .with_after_uuid_inclusive(*after_uuid_inclusive)
+                .with_tail(*tail)
+                .with_follow_rename(*follow_rename)
+                .with_follow_latest(*follow_latest)
+                .with_idle_timeout(idle_timeout.map(Duration::from_secs))
+                .with_dedup_cache(*dedup_cache)
+                .with_numbering(*number)
+                .with_max_rate(*max_rate)
+                .with_exec_formatter(exec_formatter.as_deref().map(ExecFormatter::new))
+                .with_stdout(!*no_stdout)
+                .with_jsonl_passthrough(*jsonl_passthrough)
+                .with_project_name_filter(project_name_filter.as_deref())
+                .with_max_sessions(*max_sessions)
+                .with_active_within(*only_sessions_active_within)
+                .with_strict(*strict)
+                .with_lenient(*lenient)
+                .with_redactor(redactor(*redact, redact_pattern));
+            watcher.validate()?;
+
+            if *count {
+                let projects = if *all {
+                    watcher.projects_to_watch()?
+                } else if *latest {
+                    vec![watcher.get_latest_project().await?]
+                } else if let Some(path) = project_path {
+                    vec![watcher.resolve_project_path(path)]
+                } else {
+                    eprintln!("Please specify project path, --latest, or --all option");
+                    std::process::exit(1);
+                };
+                let mut total = 0;
+                for project in &projects {
+                    total += watcher.count_messages(project)?;
+                }
+                println!("{total}");
+                return Ok(());
+            }
+
+            if let Some(addr) = metrics_addr {
+                let metrics = Arc::new(metrics::Metrics::default());
+                let listener = metrics::bind(*addr).await?;
+                tracing::info!("Serving metrics on http://{addr}/metrics");
+                tokio::spawn(metrics::serve(listener, metrics.clone()));
+                watcher = watcher.with_metrics(metrics);
+            }
+
+            #[cfg(unix)]
+            if let Some(path) = events_socket {
+                let publisher = events::EventPublisher::new();
+                let listener = events::bind(path)?;
+                tracing::info!("Publishing messages as JSON on {path:?}");
+                tokio::spawn(events::serve(listener, publisher.clone()));
+                watcher = watcher.with_events(publisher);
+            }
+
+            #[cfg(feature = "otel")]
+            let telemetry = match otlp_endpoint {
+                Some(endpoint) => {
+                    let telemetry = Arc::new(claude_logger::otel::Telemetry::otlp(endpoint)?);
+                    tracing::info!("Exporting spans to {endpoint}");
+                    watcher = watcher.with_telemetry(telemetry.clone());
+                    Some(telemetry)
+                }
+                None => None,
+            };
 
             if *all {
-                println!("Monitoring all projects...");
+                tracing::info!("Monitoring all projects...");
                 watcher.watch_all().await?;
             } else if *latest {
-                println!("Monitoring latest project...");
+                tracing::info!("Monitoring latest project...");
                 watcher.watch_latest().await?;
             } else if let Some(path) = project_path {
-                println!("Monitoring project {path:?}...");
-                watcher.watch_project(path).await?;
+                let path = watcher.resolve_project_path(path);
+                tracing::info!("Monitoring project {path:?}...");
+                watcher.watch_project(&path).await?;
             } else {
                 eprintln!("Please specify project path, --latest, or --all option");
                 std::process::exit(1);
             }
+            // Nothing printed before an idle timeout or shutdown may be lost
+            let _ = std::io::stdout().flush();
+
+            #[cfg(feature = "otel")]
+            if let Some(telemetry) = telemetry {
+                telemetry.shutdown();
+            }
         }
-        Commands::List => {
-            let watcher = LogWatcher::new();
-            watcher.list_projects().await?;
+        Commands::Doctor {
+            webhook_url,
+            tool_display,
+            theme,
+        } => {
+            let watcher = LogWatcher::new().with_claude_dirs(cli.claude_dir.clone());
+            let checks = doctor::run_checks(watcher.claude_dirs(), webhook_url.as_ref()).await;
+            doctor::print_report(&checks);
+            println!("ℹ️  Tool display: {tool_display:?}, theme: {theme:?}");
+
+            if checks.iter().any(|check| !check.passed) {
+                std::process::exit(1);
+            }
+        }
+        Commands::Replay {
+            session_file,
+            speed,
+            tool_display,
+            theme,
+            webhook_url,
+            webhook_format,
+        } => {
+            let mut watcher = LogWatcher::new()
+                .with_tool_display_mode(tool_display.clone())
+                .with_theme(*theme)
+                .with_webhook(webhook_url.clone(), webhook_format.clone());
+            let emitted = watcher.replay(session_file, *speed).await?;
+            tracing::info!("Replayed {emitted} messages from {session_file:?}");
+        }
+        Commands::Stats { path, json } => {
+            let stats = usage::collect_stats(path)?;
+            if *json {
+                let output = serde_json::json!({
+                    "sessions": stats.sessions,
+                    "user_messages": stats.user_messages,
+                    "assistant_messages": stats.assistant_messages,
+                    "usage": stats.usage,
+                    "total_input_tokens": stats.usage.total_input(),
+                    "billable_input_tokens": stats.usage.billable_input(),
+                    "parse": stats.parse,
+                    "files": stats.files,
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else {
+                let formatter = LogFormatter::new();
+                println!(
+                    "{}",
+                    formatter.format_stats(stats.user_messages, stats.assistant_messages)
+                );
+                println!("{}", formatter.format_usage(&stats.usage));
+                if let Some(ref error) = stats.parse.last_error {
+                    println!(
+                        "⚠️  {} malformed lines skipped; last at {error}",
+                        stats.parse.malformed
+                    );
+                }
+            }
+        }
+        Commands::List {
+            sort,
+            details,
+            json,
+            decode_paths,
+            only_sessions_active_within,
+        } => {
+            let watcher = LogWatcher::new()
+                .with_claude_dirs(cli.claude_dir.clone())
+                .with_active_within(*only_sessions_active_within);
+            watcher
+                .list_projects(*sort, *details, *json, *decode_paths)
+                .await?;
+        }
+        Commands::Completions { shell } => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(*shell, &mut command, name, &mut std::io::stdout());
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bash_completions_list_subcommands() {
+        let mut script = Vec::new();
+        clap_complete::generate(
+            Shell::Bash,
+            &mut Cli::command(),
+            "claude-logger",
+            &mut script,
+        );
+        let script = String::from_utf8(script).unwrap();
+        for subcommand in ["watch", "list", "stats", "replay", "doctor", "completions"] {
+            assert!(script.contains(subcommand), "missing {subcommand}");
+        }
+    }
+
+    #[test]
+    fn test_last_sidechain_flag_wins() {
+        let show_sidechains = |args: &[&str]| {
+            let cli = Cli::try_parse_from(["claude-logger", "watch"].iter().chain(args)).unwrap();
+            match cli.command {
+                Commands::Watch {
+                    hide_sidechains,
+                    show_sidechains,
+                    ..
+                } => show_sidechains && !hide_sidechains,
+                _ => unreachable!(),
+            }
+        };
+        assert!(!show_sidechains(&[]));
+        assert!(!show_sidechains(&["--hide-sidechains"]));
+        assert!(show_sidechains(&["--show-sidechains"]));
+        assert!(show_sidechains(&["--hide-sidechains", "--show-sidechains"]));
+        assert!(!show_sidechains(&[
+            "--show-sidechains",
+            "--hide-sidechains"
+        ]));
+    }
+
+    #[test]
+    fn test_jsonl_passthrough_conflicts_with_redaction() {
+        // Raw lines stay byte-identical, so nothing may rewrite them
+        for flag in ["--redact", "--redact-pattern=x=y", "--number"] {
+            let args = ["claude-logger", "watch", "--jsonl-passthrough", flag];
+            assert!(Cli::try_parse_from(args).is_err(), "{flag}");
+        }
+    }
+
+    #[test]
+    fn test_zero_second_intervals_rejected() {
+        let url = "--webhook-url=https://example.com/hook";
+        for args in [vec![url, "--webhook-heartbeat=0"], vec!["--idle-timeout=0"]] {
+            let args = ["claude-logger", "watch"].into_iter().chain(args.clone());
+            assert!(Cli::try_parse_from(args).is_err());
+        }
+        let args = ["claude-logger", "watch", url, "--webhook-heartbeat=1"];
+        assert!(Cli::try_parse_from(args).is_ok());
+    }
+
+    #[test]
+    fn test_project_path_candidates() {
+        let root = TempDir::new().unwrap();
+        for name in ["-home-user-app", "-home-user-api", "-srv-web"] {
+            fs::create_dir(root.path().join(name)).unwrap();
+        }
+        fs::write(root.path().join("-home-user-notes"), "").unwrap();
+
+        let paths = project_paths(&[root.path().to_path_buf()], "/home/user/ap");
+        assert_eq!(paths, ["/home/user/api", "/home/user/app"]);
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::{Context, Result};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::parser::MessageRole;
+use crate::webhook::{RateLimited, WebhookResult};
+
+/// Counters exposed on the Prometheus metrics endpoint
+#[derive(Debug, Default)]
+pub struct Metrics {
+    user_messages: AtomicU64,
+    assistant_messages: AtomicU64,
+    system_messages: AtomicU64,
+    summary_messages: AtomicU64,
+    webhook_sent: AtomicU64,
+    webhook_skipped: AtomicU64,
+    webhook_failed: AtomicU64,
+    webhook_rate_limited: AtomicU64,
+    parse_errors: AtomicU64,
+}
+
+impl Metrics {
+    pub fn record_message(&self, role: &MessageRole) {
+        let counter = match role {
+            MessageRole::User => &self.user_messages,
+            MessageRole::Assistant => &self.assistant_messages,
+            MessageRole::System => &self.system_messages,
+            MessageRole::Summary => &self.summary_messages,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_webhook(&self, result: &Result<WebhookResult>) {
+        let counter = match result {
+            Ok(WebhookResult::Sent) => &self.webhook_sent,
+            Ok(WebhookResult::Skipped) => &self.webhook_skipped,
+            // Counted when the batch is flushed
+            Ok(WebhookResult::Queued) => return,
+            Ok(WebhookResult::DryRun) => return,
+            Err(e) => {
+                if e.is::<RateLimited>() {
+                    self.webhook_rate_limited.fetch_add(1, Ordering::Relaxed);
+                }
+                &self.webhook_failed
+            }
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_parse_errors(&self, count: u64) {
+        self.parse_errors.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Render all counters in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+        let mut output = String::new();
+        output.push_str("# HELP claude_logger_messages_total Messages processed, by role.\n");
+        output.push_str("# TYPE claude_logger_messages_total counter\n");
+        for (role, counter) in [
+            ("user", &self.user_messages),
+            ("assistant", &self.assistant_messages),
+            ("system", &self.system_messages),
+            ("summary", &self.summary_messages),
+        ] {
+            output.push_str(&format!(
+                "claude_logger_messages_total{{role=\"{role}\"}} {}\n",
+                load(counter)
+            ));
+        }
+
+        output.push_str(
+            "# HELP claude_logger_webhook_requests_total Webhook deliveries, by result.\n",
+        );
+        output.push_str("# TYPE claude_logger_webhook_requests_total counter\n");
+        for (result, counter) in [
+            ("sent", &self.webhook_sent),
+            ("skipped", &self.webhook_skipped),
+            ("failed", &self.webhook_failed),
+        ] {
+            output.push_str(&format!(
+                "claude_logger_webhook_requests_total{{result=\"{result}\"}} {}\n",
+                load(counter)
+            ));
+        }
+
+        output.push_str(
+            "# HELP claude_logger_webhook_rate_limited_total Webhook deliveries rejected with 429.\n",
+        );
+        output.push_str("# TYPE claude_logger_webhook_rate_limited_total counter\n");
+        output.push_str(&format!(
+            "claude_logger_webhook_rate_limited_total {}\n",
+            load(&self.webhook_rate_limited)
+        ));
+
+        output
+            .push_str("# HELP claude_logger_parse_errors_total Log lines that failed to parse.\n");
+        output.push_str("# TYPE claude_logger_parse_errors_total counter\n");
+        output.push_str(&format!(
+            "claude_logger_parse_errors_total {}\n",
+            load(&self.parse_errors)
+        ));
+
+        output
+    }
+}
+
+/// Bind the metrics listener
+pub async fn bind(addr: SocketAddr) -> Result<TcpListener> {
+    TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Cannot bind metrics endpoint on {addr}"))
+}
+
+/// Serve `/metrics` on the given listener until the process exits
+pub async fn serve(listener: TcpListener, metrics: Arc<Metrics>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let metrics = metrics.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, &metrics).await {
+                        tracing::warn!("Metrics request error: {e}");
+                    }
+                });
+            }
+            Err(e) => tracing::error!("Metrics accept error: {e}"),
+        }
+    }
+}
+
+/// Answer a single HTTP request with the metrics page or a 404
+async fn handle_connection(mut stream: TcpStream, metrics: &Metrics) -> Result<()> {
+    let mut buffer = [0u8; 1024];
+    let read = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", metrics.render())
+    } else {
+        ("404 Not Found", "Not Found\n".to_string())
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let metrics = Arc::new(Metrics::default());
+        metrics.record_message(&MessageRole::Assistant);
+        metrics.record_webhook(&Ok(WebhookResult::Skipped));
+        metrics.record_parse_errors(2);
+
+        let listener = bind("127.0.0.1:0".parse().unwrap()).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, metrics));
+
+        let body = reqwest::get(format!("http://{addr}/metrics"))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        assert!(body.contains("claude_logger_messages_total{role=\"assistant\"} 1"));
+        assert!(body.contains("claude_logger_webhook_requests_total{result=\"skipped\"} 1"));
+        assert!(body.contains("claude_logger_webhook_requests_total{result=\"failed\"} 0"));
+        assert!(body.contains("claude_logger_parse_errors_total 2"));
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::{Context, Result};
+use opentelemetry::trace::{Span, Tracer, TracerProvider as _};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use std::path::Path;
+use url::Url;
+
+use crate::parser::{LogMessage, MessageRole};
+use crate::webhook::{message_kind, RateLimited, WebhookResult};
+
+const SERVICE_NAME: &str = "claude-logger";
+
+/// Exports a span per processed message to an OTLP collector
+#[derive(Debug)]
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+    tracer: SdkTracer,
+}
+
+impl Telemetry {
+    /// Export over OTLP/HTTP to the collector at `endpoint`, e.g.
+    /// `http://localhost:4318`; spans are posted to `/v1/traces`
+    pub fn otlp(endpoint: &Url) -> Result<Self> {
+        let traces_url = format!("{}/v1/traces", endpoint.as_str().trim_end_matches('/'));
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(traces_url)
+            .build()
+            .context("Cannot create OTLP exporter")?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+            .build();
+        Ok(Self::from_provider(provider))
+    }
+
+    pub fn from_provider(provider: SdkTracerProvider) -> Self {
+        let tracer = provider.tracer(SERVICE_NAME);
+        Self { provider, tracer }
+    }
+
+    /// Record a processed message. `webhook` is the delivery result, or
+    /// `None` when no webhook was configured for it.
+    pub fn record_message(&self, message: &LogMessage, webhook: Option<&Result<WebhookResult>>) {
+        let mut span = self.tracer.start("process_message");
+        let (kind, tool_name) = message_kind(message);
+        span.set_attribute(KeyValue::new("role", role_name(&message.role)));
+        span.set_attribute(KeyValue::new("project", message.project_name.clone()));
+        span.set_attribute(KeyValue::new("session_id", message.session_id.clone()));
+        span.set_attribute(KeyValue::new("message_type", kind));
+        if let Some(tool_name) = tool_name {
+            span.set_attribute(KeyValue::new("tool_name", tool_name.to_string()));
+        }
+        span.set_attribute(KeyValue::new("webhook.result", webhook_outcome(webhook)));
+        span.end();
+    }
+
+    /// Record lines of `path` that could not be parsed as a span event
+    pub fn record_parse_errors(&self, path: &Path, count: u64, error: Option<&str>) {
+        let mut span = self.tracer.start("parse_file");
+        span.set_attribute(KeyValue::new("file", path.display().to_string()));
+        let mut attributes = vec![KeyValue::new("count", count as i64)];
+        if let Some(error) = error {
+            attributes.push(KeyValue::new("error", error.to_string()));
+        }
+        span.add_event("parse_error", attributes);
+        span.end();
+    }
+
+    /// Export spans that are still buffered
+    pub fn shutdown(&self) {
+        if let Err(e) = self.provider.shutdown() {
+            tracing::warn!("Failed to flush OpenTelemetry spans: {e}");
+        }
+    }
+}
+
+fn role_name(role: &MessageRole) -> &'static str {
+    match role {
+        MessageRole::User => "user",
+        MessageRole::Assistant => "assistant",
+        MessageRole::System => "system",
+        MessageRole::Summary => "summary",
+    }
+}
+
+fn webhook_outcome(result: Option<&Result<WebhookResult>>) -> &'static str {
+    match result {
+        None => "none",
+        Some(Ok(WebhookResult::Sent)) => "sent",
+        Some(Ok(WebhookResult::Skipped)) => "skipped",
+        Some(Ok(WebhookResult::Queued)) => "batched",
+        Some(Ok(WebhookResult::DryRun)) => "dry_run",
+        Some(Err(e)) if e.is::<RateLimited>() => "rate_limited",
+        Some(Err(_)) => "failed",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::LogParser;
+    use opentelemetry::Value;
+    use opentelemetry_sdk::trace::InMemorySpanExporter;
+
+    #[test]
+    fn test_message_span_attributes() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let telemetry = Telemetry::from_provider(provider);
+
+        let line = r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","name":"Bash","input":{"command":"ls"}}]},"timestamp":"2025-01-01T00:00:00Z","sessionId":"s","uuid":"u1"}"#;
+        let mut message = LogParser::new().parse_line(line).unwrap().unwrap();
+        message.project_name = "demo".to_string();
+        telemetry.record_message(&message, Some(&Ok(WebhookResult::Sent)));
+        telemetry.record_parse_errors(Path::new("session.jsonl"), 2, None);
+
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 2);
+        let attribute = |key: &str| {
+            spans[0]
+                .attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(spans[0].name, "process_message");
+        assert_eq!(attribute("role"), Some(Value::from("assistant")));
+        assert_eq!(attribute("project"), Some(Value::from("demo")));
+        assert_eq!(attribute("tool_name"), Some(Value::from("Bash")));
+        assert_eq!(attribute("webhook.result"), Some(Value::from("sent")));
+
+        assert_eq!(spans[1].events.events[0].name, "parse_error");
+    }
+}
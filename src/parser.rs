@@ -1,10 +1,13 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::fs::File;
+use std::collections::HashMap;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogMessage {
@@ -15,14 +18,38 @@ pub struct LogMessage {
     pub uuid: String,
     pub project_name: String,
     pub raw_content: Option<Value>, // Store raw content for detailed tool parsing
+    /// Structured tool output from the entry's top-level `toolUseResult`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_use_result: Option<Value>,
+    /// Entry marked `isMeta`, e.g. injected command caveats
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_meta: bool,
+    /// Entry from a sub-agent conversation (`isSidechain`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_sidechain: bool,
+    /// The entry this one follows in its conversation (`parentUuid`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_uuid: Option<String>,
+    /// Description of the `Task` call a sidechain message belongs to, once
+    /// traced back to it
+    #[serde(skip)]
+    pub task: Option<String>,
+    /// The whole log entry, kept only when the parser is asked to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_entry: Option<Value>,
+    /// The line as read from the file, kept only when the parser is asked to
+    #[serde(skip)]
+    pub raw_line: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
 #[serde(rename_all = "lowercase")]
 pub enum MessageRole {
     User,
     Assistant,
     System,
+    /// Session summary written by Claude Code (`type: "summary"`)
+    Summary,
 }
 
 #[derive(Debug, Deserialize)]
@@ -30,10 +57,21 @@ struct RawLogEntry {
     #[serde(rename = "type")]
     entry_type: String,
     message: Option<Value>,
-    timestamp: String,
+    timestamp: Option<String>,
     #[serde(rename = "sessionId")]
     session_id: Option<String>,
-    uuid: String,
+    uuid: Option<String>,
+    summary: Option<String>,
+    #[serde(rename = "leafUuid")]
+    leaf_uuid: Option<String>,
+    #[serde(rename = "toolUseResult")]
+    tool_use_result: Option<Value>,
+    #[serde(rename = "isMeta", default)]
+    is_meta: bool,
+    #[serde(rename = "isSidechain", default)]
+    is_sidechain: bool,
+    #[serde(rename = "parentUuid")]
+    parent_uuid: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,13 +85,141 @@ struct CwdEntry {
     cwd: Option<String>,
 }
 
+/// Line counts gathered by the most recent `parse_file` call
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ParseStats {
+    /// Non-blank lines read
+    pub lines: usize,
+    /// Lines that produced a message
+    pub parsed: usize,
+    /// Valid entries that are not conversation messages (summaries, snapshots, ...)
+    pub filtered: usize,
+    /// Lines that could not be parsed at all
+    pub malformed: usize,
+    /// Why the last malformed line was rejected, with its byte offset
+    pub last_error: Option<String>,
+}
+
+impl std::ops::AddAssign<&ParseStats> for ParseStats {
+    fn add_assign(&mut self, other: &ParseStats) {
+        self.lines += other.lines;
+        self.parsed += other.parsed;
+        self.filtered += other.filtered;
+        self.malformed += other.malformed;
+        if other.last_error.is_some() {
+            self.last_error.clone_from(&other.last_error);
+        }
+    }
+}
+
+/// Renders one content block of a given `type` into summary text.
+/// Returning `None` drops the block from the message content.
+pub type BlockHandler =
+    Arc<dyn Fn(&serde_json::Map<String, Value>) -> Option<String> + Send + Sync>;
+
+/// Fixed summary labels for content block types without their own text
+const BLOCK_LABELS: &[(&str, &str)] = &[
+    ("tool_result", "[Tool Result]"),
+    ("thinking", "[Thinking...]"),
+    ("redacted_thinking", "[Thinking...]"),
+    ("image", "[Image]"),
+    ("document", "[Document]"),
+    ("web_search_tool_result", "[Web Search Result]"),
+];
+
+#[derive(Clone)]
 pub struct LogParser {
-    last_position: u64,
+    /// Read offset per file, so several sessions can share one parser
+    last_positions: HashMap<PathBuf, u64>,
+    /// Timestamp of the last timestamped entry read from each file
+    last_timestamps: HashMap<PathBuf, DateTime<Utc>>,
+    strict: bool,
+    /// Retry lines that are not valid JSON with a JSON5 parser
+    lenient: bool,
+    keep_raw_entries: bool,
+    keep_raw_lines: bool,
+    show_summaries: bool,
+    /// Join consecutive text blocks that split a sentence with a space
+    merge_adjacent_text: bool,
+    last_stats: ParseStats,
+    /// Consumer-registered renderers, consulted before the built-in ones
+    block_handlers: HashMap<String, BlockHandler>,
+}
+
+impl Default for LogParser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl LogParser {
     pub fn new() -> Self {
-        Self { last_position: 0 }
+        Self {
+            last_positions: HashMap::new(),
+            last_timestamps: HashMap::new(),
+            strict: false,
+            lenient: false,
+            keep_raw_entries: false,
+            keep_raw_lines: false,
+            show_summaries: false,
+            merge_adjacent_text: false,
+            last_stats: ParseStats::default(),
+            block_handlers: HashMap::new(),
+        }
+    }
+
+    /// Render content blocks of `block_type` with `handler`, overriding
+    /// the built-in rendering for that type
+    pub fn with_block_handler<F>(mut self, block_type: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&serde_json::Map<String, Value>) -> Option<String> + Send + Sync + 'static,
+    {
+        self.block_handlers
+            .insert(block_type.into(), Arc::new(handler));
+        self
+    }
+
+    /// Fail `parse_file` on the first malformed line instead of skipping it
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Salvage lines with trailing commas, comments and other JSON5 syntax
+    /// instead of counting them as malformed
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Keep each message's full log entry in `LogMessage::raw_entry`
+    pub fn with_raw_entries(mut self, keep: bool) -> Self {
+        self.keep_raw_entries = keep;
+        self
+    }
+
+    /// Keep each message's line, byte for byte, in `LogMessage::raw_line`
+    pub fn with_raw_lines(mut self, keep: bool) -> Self {
+        self.keep_raw_lines = keep;
+        self
+    }
+
+    /// Join consecutive `text` blocks with a space instead of a newline
+    /// when the break falls mid-sentence
+    pub fn with_merge_adjacent_text(mut self, merge: bool) -> Self {
+        self.merge_adjacent_text = merge;
+        self
+    }
+
+    /// Return `summary` entries as `MessageRole::Summary` messages
+    pub fn with_summaries(mut self, show: bool) -> Self {
+        self.show_summaries = show;
+        self
+    }
+
+    /// Statistics for the most recent `parse_file` call
+    pub fn last_stats(&self) -> &ParseStats {
+        &self.last_stats
     }
 
     /// Parse entire file
@@ -62,39 +228,131 @@ impl LogParser {
         let mut file = File::open(path).with_context(|| format!("Cannot open file {path:?}"))?;
 
         // Read from the last position we read from
-        file.seek(SeekFrom::Start(self.last_position))?;
-        let reader = BufReader::new(file);
+        let last_position = self.last_positions.get(path).copied().unwrap_or(0);
+        file.seek(SeekFrom::Start(last_position))?;
+        let mut reader = BufReader::new(file);
+
+        let mut messages: Vec<LogMessage> = Vec::new();
+        let mut stats = ParseStats::default();
+        let mut current_position = last_position;
+        let mut buf = String::new();
+        // Summaries carry no timestamp; they take the one of the entry
+        // before them, or after them at the top of the file
+        let mut last_timestamp = self.last_timestamps.get(path).copied();
+        let mut untimed: Vec<usize> = Vec::new();
 
-        let mut messages = Vec::new();
-        let mut current_position = self.last_position;
+        loop {
+            buf.clear();
+            let read = reader.read_line(&mut buf)? as u64;
+            if read == 0 {
+                break;
+            }
+            let at_start = current_position == 0;
+            let complete = buf.ends_with('\n');
+            let line = buf.trim_end_matches(['\n', '\r']);
+
+            // A UTF-8 BOM can only appear before the first line
+            let line = match line.strip_prefix('\u{feff}') {
+                Some(rest) if at_start => rest,
+                _ => line,
+            };
+            if line.trim().is_empty() {
+                current_position += read;
+                continue;
+            }
 
-        for line in reader.lines() {
-            let line = line?;
-            current_position += line.len() as u64 + 1; // +1 for newline
+            let parsed = self.parse_entry(line);
+            // A last line without a newline may still be being written;
+            // leave it to be read again once it is complete
+            if !complete && parsed.is_err() {
+                break;
+            }
+            let offset = current_position;
+            current_position += read;
+            stats.lines += 1;
+
+            match parsed {
+                Ok(Some((mut message, timed))) => {
+                    message.project_name = project_name.clone();
+                    if self.keep_raw_lines {
+                        message.raw_line = Some(line.to_string());
+                    }
+                    if timed {
+                        last_timestamp = Some(message.timestamp);
+                        for index in untimed.drain(..) {
+                            messages[index].timestamp = message.timestamp;
+                        }
+                    } else if let Some(timestamp) = last_timestamp {
+                        message.timestamp = timestamp;
+                    } else {
+                        untimed.push(messages.len());
+                    }
+                    messages.push(message);
+                    stats.parsed += 1;
+                }
+                Ok(None) => stats.filtered += 1,
+                Err(e) if self.strict => {
+                    return Err(e.context(format!("Malformed line in {path:?}")));
+                }
+                Err(e) => {
+                    stats.malformed += 1;
+                    stats.last_error = Some(format!("byte {offset}: {e:#}"));
+                }
+            }
+        }
 
-            if let Ok(mut message) = self.parse_line(&line) {
-                message.project_name = project_name.clone();
-                messages.push(message);
+        if !untimed.is_empty() {
+            // Nothing else to go by: the file's modification time
+            let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+            if let Ok(modified) = modified {
+                for index in untimed {
+                    messages[index].timestamp = modified.into();
+                }
             }
         }
 
-        self.last_position = current_position;
+        self.last_positions
+            .insert(path.to_path_buf(), current_position);
+        if let Some(timestamp) = last_timestamp {
+            self.last_timestamps.insert(path.to_path_buf(), timestamp);
+        }
+        self.last_stats = stats;
         Ok(messages)
     }
 
     /// Get only new messages
-    #[allow(dead_code)]
     pub fn parse_new_messages(&mut self, path: &Path) -> Result<Vec<LogMessage>> {
         self.parse_file(path)
     }
 
     /// Parse a single JSONL entry
-    fn parse_line(&self, line: &str) -> Result<LogMessage> {
-        let raw: RawLogEntry = serde_json::from_str(line).context("Failed to parse JSON")?;
+    ///
+    /// Returns `Ok(None)` for well-formed entries that are not conversation
+    /// messages, and `Err` only when the line itself is malformed.
+    pub fn parse_line(&self, line: &str) -> Result<Option<LogMessage>> {
+        Ok(self.parse_entry(line)?.map(|(message, _)| message))
+    }
+
+    /// Like `parse_line`, also telling whether the timestamp came from the
+    /// entry rather than the time it was read
+    fn parse_entry(&self, line: &str) -> Result<Option<(LogMessage, bool)>> {
+        let raw = match serde_json::from_str::<RawLogEntry>(line) {
+            Ok(raw) => raw,
+            Err(e) if self.lenient && e.is_syntax() => {
+                let value: Value = json5::from_str(line).context("Failed to parse JSON")?;
+                // Re-encoded so raw entries are plain JSON
+                return self.parse_entry(&value.to_string());
+            }
+            Err(e) => return Err(e).context("Failed to parse JSON"),
+        };
+
+        if raw.entry_type == "summary" && self.show_summaries {
+            return self.parse_summary(raw, line);
+        }
 
         // Process only user or assistant messages
         if raw.entry_type != "user" && raw.entry_type != "assistant" {
-            return Err(anyhow::anyhow!("Not a message type"));
+            return Ok(None);
         }
 
         let message = raw
@@ -108,27 +366,87 @@ impl LogParser {
             "user" => MessageRole::User,
             "assistant" => MessageRole::Assistant,
             "system" => MessageRole::System,
-            _ => return Err(anyhow::anyhow!("Unknown role: {}", content_msg.role)),
+            _ => return Ok(None),
         };
 
-        let content = self.extract_content(&content_msg.content)?;
-        let raw_content = Some(content_msg.content.clone());
+        let content_value = match content_msg.content {
+            Value::String(ref s) => decode_stringified_blocks(s).unwrap_or(content_msg.content),
+            other => other,
+        };
+        let content = self.extract_content(&content_value)?;
+        let raw_content = Some(content_value);
 
-        let timestamp = DateTime::parse_from_rfc3339(&raw.timestamp)
+        let timestamp = raw.timestamp.context("Timestamp field not found")?;
+        let timestamp = DateTime::parse_from_rfc3339(&timestamp)
             .context("Failed to parse timestamp")?
             .with_timezone(&Utc);
 
-        let session_id = raw.session_id.unwrap_or_else(|| "unknown".to_string());
+        Ok(Some((
+            LogMessage {
+                role,
+                content,
+                timestamp,
+                session_id: raw.session_id.unwrap_or_else(|| "unknown".to_string()),
+                uuid: raw.uuid.context("uuid field not found")?,
+                project_name: String::new(), // Will be set by parse_file
+                raw_content,
+                tool_use_result: raw.tool_use_result,
+                is_meta: raw.is_meta,
+                is_sidechain: raw.is_sidechain,
+                parent_uuid: raw.parent_uuid,
+                task: None,
+                raw_entry: self.raw_entry(line)?,
+                raw_line: None,
+            },
+            true,
+        )))
+    }
 
-        Ok(LogMessage {
-            role,
-            content,
-            timestamp,
-            session_id,
-            uuid: raw.uuid,
-            project_name: String::new(), // Will be set by parse_file
-            raw_content,
-        })
+    /// Build a summary message. Summary entries usually carry no timestamp;
+    /// such ones are stamped with the time they were read, which
+    /// `parse_file` replaces with that of a neighboring entry.
+    fn parse_summary(&self, raw: RawLogEntry, line: &str) -> Result<Option<(LogMessage, bool)>> {
+        let Some(summary) = raw.summary else {
+            return Ok(None);
+        };
+        let timed = raw.timestamp.is_some();
+        let timestamp = match raw.timestamp {
+            Some(timestamp) => DateTime::parse_from_rfc3339(&timestamp)
+                .context("Failed to parse timestamp")?
+                .with_timezone(&Utc),
+            None => Utc::now(),
+        };
+        // Distinct from the leaf message's own uuid so dedup keeps both
+        let leaf = raw.leaf_uuid.or(raw.uuid).unwrap_or_default();
+
+        Ok(Some((
+            LogMessage {
+                role: MessageRole::Summary,
+                content: summary,
+                timestamp,
+                session_id: raw.session_id.unwrap_or_else(|| "unknown".to_string()),
+                uuid: format!("summary-{leaf}"),
+                project_name: String::new(),
+                raw_content: None,
+                tool_use_result: None,
+                is_meta: raw.is_meta,
+                is_sidechain: raw.is_sidechain,
+                parent_uuid: raw.parent_uuid,
+                task: None,
+                raw_entry: self.raw_entry(line)?,
+                raw_line: None,
+            },
+            timed,
+        )))
+    }
+
+    /// The whole entry, if raw entries are being kept
+    fn raw_entry(&self, line: &str) -> Result<Option<Value>> {
+        if self.keep_raw_entries {
+            Ok(Some(serde_json::from_str(line)?))
+        } else {
+            Ok(None)
+        }
     }
 
     /// Extract message content
@@ -137,43 +455,28 @@ impl LogParser {
             Value::String(s) => Ok(s.clone()),
             Value::Array(arr) => {
                 let mut result = String::new();
+                let mut previous_text: Option<String> = None;
                 for item in arr {
-                    if let Some(obj) = item.as_object() {
-                        if let Some(content_type) = obj.get("type") {
-                            match content_type.as_str().unwrap_or("") {
-                                "text" => {
-                                    if let Some(text) = obj.get("text") {
-                                        if let Some(text_str) = text.as_str() {
-                                            result.push_str(text_str);
-                                            result.push('\n');
-                                        }
-                                    }
-                                }
-                                "tool_use" => {
-                                    if let Some(name) = obj.get("name") {
-                                        if let Some(name_str) = name.as_str() {
-                                            result.push_str(&format!("[Tool Use: {name_str}]"));
-                                            result.push('\n');
-                                        }
-                                    }
-                                }
-                                "tool_result" => {
-                                    result.push_str("[Tool Result]");
-                                    result.push('\n');
-                                }
-                                "thinking" => {
-                                    result.push_str("[Thinking...]");
-                                    result.push('\n');
-                                }
-                                _ => {
-                                    result.push_str(&format!(
-                                        "[{}]",
-                                        content_type.as_str().unwrap_or("unknown")
-                                    ));
-                                    result.push('\n');
-                                }
-                            }
+                    let Some(obj) = item.as_object() else {
+                        continue;
+                    };
+                    let Some(content_type) = obj.get("type").and_then(|t| t.as_str()) else {
+                        continue;
+                    };
+                    if let Some(text) = self.render_block(content_type, obj) {
+                        let is_text = content_type == "text";
+                        if self.merge_adjacent_text
+                            && is_text
+                            && previous_text
+                                .as_deref()
+                                .is_some_and(|previous| breaks_mid_sentence(previous, &text))
+                        {
+                            result.pop();
+                            result.push(' ');
                         }
+                        result.push_str(&text);
+                        result.push('\n');
+                        previous_text = is_text.then_some(text);
                     }
                 }
                 Ok(result.trim_end().to_string())
@@ -182,6 +485,31 @@ impl LogParser {
         }
     }
 
+    /// Summary text for a single content block
+    fn render_block(
+        &self,
+        content_type: &str,
+        obj: &serde_json::Map<String, Value>,
+    ) -> Option<String> {
+        if let Some(handler) = self.block_handlers.get(content_type) {
+            return handler(obj);
+        }
+        match content_type {
+            "text" => obj.get("text")?.as_str().map(str::to_string),
+            "tool_use" => {
+                let name = obj.get("name")?.as_str()?;
+                Some(format!("[Tool Use: {name}]"))
+            }
+            _ => Some(
+                BLOCK_LABELS
+                    .iter()
+                    .find(|(block_type, _)| *block_type == content_type)
+                    .map(|(_, label)| label.to_string())
+                    .unwrap_or_else(|| format!("[{content_type}]")),
+            ),
+        }
+    }
+
     /// Extract project name from file path
     fn extract_project_name(&self, path: &Path) -> String {
         // Try to extract project name from cwd field in JSONL file
@@ -193,7 +521,9 @@ impl LogParser {
                     .lines()
                     .take(10)
                     .filter_map(|line| line.ok())
-                    .filter_map(|line| serde_json::from_str::<CwdEntry>(&line).ok())
+                    .filter_map(|line| {
+                        serde_json::from_str::<CwdEntry>(line.trim_start_matches('\u{feff}')).ok()
+                    })
                     .filter_map(|entry| entry.cwd)
                     .filter_map(|cwd| {
                         Path::new(&cwd)
@@ -213,9 +543,411 @@ impl LogParser {
         })
     }
 
-    /// Reset position (reload entire file)
-    #[allow(dead_code)]
+    /// Whether `path` has been read by this parser before
+    pub fn is_tracking(&self, path: &Path) -> bool {
+        self.last_positions.contains_key(path)
+    }
+
+    /// Start reading `path` from its current end, skipping existing lines
+    pub fn skip_to_end(&mut self, path: &Path) -> Result<()> {
+        let len = fs::metadata(path)
+            .with_context(|| format!("Cannot read metadata of {path:?}"))?
+            .len();
+        self.last_positions.insert(path.to_path_buf(), len);
+        Ok(())
+    }
+
+    /// Start reading `path` right after the entry with `uuid`, or at it
+    /// when `inclusive`. Returns false, leaving the position unchanged, if
+    /// no entry has that uuid.
+    pub fn seek_to_uuid(&mut self, path: &Path, uuid: &str, inclusive: bool) -> Result<bool> {
+        let file = File::open(path).with_context(|| format!("Cannot open file {path:?}"))?;
+        let mut reader = BufReader::new(file);
+        let mut position = 0;
+        let mut buf = String::new();
+        loop {
+            buf.clear();
+            // Count the bytes actually read, so CRLF endings and a last
+            // line without a newline land on the right offset
+            let read = reader.read_line(&mut buf)? as u64;
+            if read == 0 {
+                break;
+            }
+            let start = position;
+            position += read;
+
+            let line = buf.trim_end_matches(['\n', '\r']);
+            let line = match line.strip_prefix('\u{feff}') {
+                Some(rest) if start == 0 => rest,
+                _ => line,
+            };
+            let entry = serde_json::from_str::<Value>(line);
+            if entry.is_ok_and(|entry| entry.get("uuid").and_then(Value::as_str) == Some(uuid)) {
+                let resume = if inclusive { start } else { position };
+                self.last_positions.insert(path.to_path_buf(), resume);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Carry the read position of a renamed file over to its new path.
+    /// Returns false if `from` was never read.
+    pub fn rename(&mut self, from: &Path, to: &Path) -> bool {
+        if let Some(timestamp) = self.last_timestamps.remove(from) {
+            self.last_timestamps.insert(to.to_path_buf(), timestamp);
+        }
+        match self.last_positions.remove(from) {
+            Some(position) => {
+                self.last_positions.insert(to.to_path_buf(), position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reset positions (reload every file from the start)
     pub fn reset(&mut self) {
-        self.last_position = 0;
+        self.last_positions.clear();
+        self.last_timestamps.clear();
+    }
+}
+
+/// Decode content stored as a JSON-encoded array of content blocks.
+/// Only arrays whose every element is a typed block qualify, so ordinary
+/// text that merely looks like JSON is left alone.
+fn decode_stringified_blocks(s: &str) -> Option<Value> {
+    let trimmed = s.trim();
+    if !(trimmed.starts_with('[') && trimmed.ends_with(']')) {
+        return None;
+    }
+    let blocks: Vec<Value> = serde_json::from_str(trimmed).ok()?;
+    let all_typed = blocks
+        .iter()
+        .all(|block| block.get("type").is_some_and(Value::is_string));
+    (!blocks.is_empty() && all_typed).then_some(Value::Array(blocks))
+}
+
+/// Characters after which a text block is taken to end a sentence
+const SENTENCE_ENDS: &[char] = &['.', '!', '?', ':', ';', '。', '！', '？', '：'];
+
+/// Whether the boundary between two adjacent text blocks falls inside a
+/// sentence: neither side has sentence punctuation, a line break or a
+/// code fence at the join
+fn breaks_mid_sentence(previous: &str, next: &str) -> bool {
+    let ends_sentence = previous.ends_with(SENTENCE_ENDS)
+        || previous.ends_with(char::is_whitespace)
+        || previous
+            .lines()
+            .last()
+            .is_none_or(|line| line.starts_with("```"));
+    let starts_sentence = next.starts_with(SENTENCE_ENDS)
+        || next.starts_with(char::is_whitespace)
+        || next.starts_with("```");
+    !ends_sentence && !starts_sentence
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    const USER_LINE: &str = r#"{"type":"user","message":{"role":"user","content":"Hello"},"timestamp":"2025-01-01T12:00:00Z","sessionId":"abcdef123456","uuid":"1"}"#;
+    const SUMMARY_LINE: &str = r#"{"type":"summary","summary":"Greeting","leafUuid":"1","timestamp":"2025-01-01T12:00:01Z","uuid":"2"}"#;
+
+    fn write_fixture(lines: &[&str]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        for line in lines {
+            writeln!(file, "{line}").unwrap();
+        }
+        file
+    }
+
+    #[test]
+    fn test_seek_to_uuid_counts_bytes_read() {
+        let second = USER_LINE.replace(r#""uuid":"1""#, r#""uuid":"2""#);
+        for (contents, after_first, after_second) in [
+            (
+                format!("{USER_LINE}\n{second}\n"),
+                USER_LINE.len() + 1,
+                USER_LINE.len() + second.len() + 2,
+            ),
+            (
+                format!("{USER_LINE}\r\n{second}\r\n"),
+                USER_LINE.len() + 2,
+                USER_LINE.len() + second.len() + 4,
+            ),
+            (
+                format!("{USER_LINE}\n{second}"),
+                USER_LINE.len() + 1,
+                USER_LINE.len() + second.len() + 1,
+            ),
+        ] {
+            let mut file = NamedTempFile::new().unwrap();
+            file.write_all(contents.as_bytes()).unwrap();
+            let mut parser = LogParser::new();
+            let position = |parser: &LogParser| parser.last_positions[file.path()] as usize;
+
+            assert!(parser.seek_to_uuid(file.path(), "2", true).unwrap());
+            assert_eq!(position(&parser), after_first, "{contents:?}");
+            assert!(parser.seek_to_uuid(file.path(), "1", false).unwrap());
+            assert_eq!(position(&parser), after_first, "{contents:?}");
+            assert!(parser.seek_to_uuid(file.path(), "2", false).unwrap());
+            assert_eq!(position(&parser), after_second, "{contents:?}");
+            assert_eq!(after_second, contents.len());
+        }
+    }
+
+    #[test]
+    fn test_parse_stats_for_mixed_lines() {
+        let file = write_fixture(&[USER_LINE, SUMMARY_LINE, "{not json", USER_LINE, "garbage"]);
+        let mut parser = LogParser::new();
+
+        let messages = parser.parse_file(file.path()).unwrap();
+
+        assert_eq!(messages.len(), 2);
+        let stats = parser.last_stats();
+        assert_eq!(
+            (stats.lines, stats.parsed, stats.filtered, stats.malformed),
+            (5, 2, 1, 2)
+        );
+        // "garbage" starts after the first four lines and their newlines
+        let offset = [USER_LINE, SUMMARY_LINE, "{not json", USER_LINE]
+            .iter()
+            .map(|line| line.len() + 1)
+            .sum::<usize>();
+        let last_error = stats.last_error.as_deref().unwrap();
+        assert!(
+            last_error.starts_with(&format!("byte {offset}: ")),
+            "{last_error}"
+        );
+    }
+
+    #[test]
+    fn test_raw_entries_kept_on_request() {
+        let message = LogParser::new().parse_line(USER_LINE).unwrap().unwrap();
+        assert!(message.raw_entry.is_none());
+
+        let parser = LogParser::new().with_raw_entries(true);
+        let message = parser.parse_line(USER_LINE).unwrap().unwrap();
+        assert_eq!(message.raw_entry.unwrap()["sessionId"], "abcdef123456");
+    }
+
+    #[test]
+    fn test_raw_lines_kept_on_request() {
+        let spaced = r#"{ "type": "user", "uuid": "3", "message": {"role": "user", "content": "caf\u00e9"}, "timestamp": "2025-01-01T12:00:00Z" }"#;
+        let file = write_fixture(&[USER_LINE, SUMMARY_LINE, spaced]);
+        assert!(LogParser::new().parse_file(file.path()).unwrap()[0]
+            .raw_line
+            .is_none());
+
+        let mut parser = LogParser::new().with_raw_lines(true);
+        let lines: Vec<_> = parser
+            .parse_file(file.path())
+            .unwrap()
+            .into_iter()
+            .map(|message| message.raw_line.unwrap())
+            .collect();
+        assert_eq!(lines, [USER_LINE, spaced]);
+    }
+
+    #[test]
+    fn test_stringified_content_array_is_decoded() {
+        let blocks =
+            r#"[{"type":"text","text":"Running"},{"type":"tool_use","name":"Bash","input":{}}]"#;
+        let entry = serde_json::json!({
+            "type": "assistant",
+            "message": {"role": "assistant", "content": blocks},
+            "timestamp": "2025-01-01T12:00:00Z",
+            "uuid": "1"
+        });
+        let message = LogParser::new()
+            .parse_line(&entry.to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.content, "Running\n[Tool Use: Bash]");
+        assert!(message.raw_content.unwrap().is_array());
+
+        // Plain text that happens to look like JSON stays verbatim
+        for text in ["[1, 2, 3]", "[]", r#"[{"name":"x"}]"#, "[see above]"] {
+            assert_eq!(decode_stringified_blocks(text), None);
+        }
+    }
+
+    #[test]
+    fn test_tool_use_result_captured() {
+        let entry = serde_json::json!({
+            "type": "user",
+            "message": {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "t1", "content": "ok"}
+            ]},
+            "toolUseResult": {"stdout": "ok", "stderr": "", "interrupted": false},
+            "timestamp": "2025-01-01T12:00:00Z",
+            "uuid": "1"
+        });
+        let message = LogParser::new()
+            .parse_line(&entry.to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.tool_use_result.unwrap()["stdout"], "ok");
+
+        let message = LogParser::new().parse_line(USER_LINE).unwrap().unwrap();
+        assert!(message.tool_use_result.is_none());
+    }
+
+    #[test]
+    fn test_meta_and_sidechain_flags() {
+        let message = LogParser::new().parse_line(USER_LINE).unwrap().unwrap();
+        assert!(!message.is_meta && !message.is_sidechain);
+
+        assert_eq!(message.parent_uuid, None);
+
+        let line = USER_LINE.replacen(
+            '{',
+            r#"{"isMeta":true,"isSidechain":true,"parentUuid":"0","#,
+            1,
+        );
+        let message = LogParser::new().parse_line(&line).unwrap().unwrap();
+        assert!(message.is_meta);
+        assert!(message.is_sidechain);
+        assert_eq!(message.parent_uuid.as_deref(), Some("0"));
+    }
+
+    #[test]
+    fn test_bom_and_blank_lines_skipped() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "\u{feff}{USER_LINE}\n\n   \n{USER_LINE}\n\n").unwrap();
+
+        let mut parser = LogParser::new();
+        let messages = parser.parse_file(file.path()).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(parser.last_stats().malformed, 0);
+
+        // The read position ends exactly at the end of the file
+        writeln!(file, "{USER_LINE}").unwrap();
+        assert_eq!(parser.parse_file(file.path()).unwrap().len(), 1);
+        assert_eq!(parser.last_stats().malformed, 0);
+    }
+
+    #[test]
+    fn test_partial_last_line_read_once_complete() {
+        let mut file = NamedTempFile::new().unwrap();
+        let (head, tail) = USER_LINE.split_at(40);
+        write!(file, "{head}").unwrap();
+
+        let mut parser = LogParser::new();
+        assert!(parser.parse_file(file.path()).unwrap().is_empty());
+        assert_eq!(parser.last_stats().malformed, 0);
+
+        writeln!(file, "{tail}").unwrap();
+        assert_eq!(parser.parse_file(file.path()).unwrap().len(), 1);
+        assert!(parser.parse_file(file.path()).unwrap().is_empty());
+
+        // A complete entry is read even before its newline arrives
+        write!(file, "{USER_LINE}").unwrap();
+        assert_eq!(parser.parse_file(file.path()).unwrap().len(), 1);
+        writeln!(file).unwrap();
+        assert!(parser.parse_file(file.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_merge_adjacent_text_blocks() {
+        let line = |texts: &[&str]| {
+            let blocks: Vec<Value> = texts
+                .iter()
+                .map(|text| serde_json::json!({"type": "text", "text": text}))
+                .collect();
+            serde_json::json!({
+                "type": "assistant",
+                "message": {"role": "assistant", "content": blocks},
+                "timestamp": "2025-01-01T12:00:00Z",
+                "sessionId": "s",
+                "uuid": "1"
+            })
+            .to_string()
+        };
+        let content = |parser: &LogParser, texts: &[&str]| {
+            parser.parse_line(&line(texts)).unwrap().unwrap().content
+        };
+
+        let split = ["The build finished", "without errors."];
+        assert_eq!(
+            content(&LogParser::new(), &split),
+            "The build finished\nwithout errors."
+        );
+
+        let parser = LogParser::new().with_merge_adjacent_text(true);
+        assert_eq!(
+            content(&parser, &split),
+            "The build finished without errors."
+        );
+        assert_eq!(
+            content(&parser, &["Done.", "Next step"]),
+            "Done.\nNext step"
+        );
+        assert_eq!(
+            content(&parser, &["Output:", "\nok", "```\nls\n```", "after"]),
+            "Output:\n\nok\n```\nls\n```\nafter"
+        );
+    }
+
+    #[test]
+    fn test_lenient_recovers_trailing_comma() {
+        let line = USER_LINE.replace(r#""uuid":"1"}"#, r#""uuid":"1",}"#);
+        assert!(LogParser::new().parse_line(&line).is_err());
+
+        let parser = LogParser::new().with_lenient(true);
+        let message = parser.parse_line(&line).unwrap().unwrap();
+        assert_eq!(message.content, "Hello");
+
+        // Lines that are not JSON5 either are still rejected
+        assert!(parser.parse_line("{not json").is_err());
+    }
+
+    #[test]
+    fn test_timestamp_keeps_subsecond_precision() {
+        let line = USER_LINE.replace("12:00:00Z", "12:00:00.123456Z");
+        let message = LogParser::new().parse_line(&line).unwrap().unwrap();
+        assert_eq!(message.timestamp.timestamp_subsec_micros(), 123456);
+    }
+
+    #[test]
+    fn test_summary_entries() {
+        let line = r#"{"type":"summary","summary":"Fix login bug","leafUuid":"abc"}"#;
+        assert!(LogParser::new().parse_line(line).unwrap().is_none());
+
+        let parser = LogParser::new().with_summaries(true);
+        let message = parser.parse_line(line).unwrap().unwrap();
+        assert_eq!(message.role, MessageRole::Summary);
+        assert_eq!(message.content, "Fix login bug");
+        assert_eq!(message.uuid, "summary-abc");
+    }
+
+    #[test]
+    fn test_strict_mode_aborts_on_malformed_line() {
+        let file = write_fixture(&[USER_LINE, "{not json"]);
+        let mut parser = LogParser::new().with_strict(true);
+
+        assert!(parser.parse_file(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_custom_block_handler() {
+        let parser = LogParser::new().with_block_handler("server_tool_use", |block| {
+            let name = block.get("name")?.as_str()?;
+            Some(format!("[Server Tool: {name}]"))
+        });
+        let content = serde_json::json!([
+            {"type": "text", "text": "Searching"},
+            {"type": "server_tool_use", "name": "web_search"},
+            {"type": "image"},
+            {"type": "brand_new"}
+        ]);
+
+        assert_eq!(
+            parser.extract_content(&content).unwrap(),
+            "Searching\n[Server Tool: web_search]\n[Image]\n[brand_new]"
+        );
     }
 }
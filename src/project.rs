@@ -0,0 +1,77 @@
+use std::path::Path;
+
+/// Decode a Claude Code project directory name back into a filesystem path.
+///
+/// Claude Code stores each project under a directory named after its working
+/// directory with every `/` replaced by `-` (e.g. `/home/suzuki/repos` becomes
+/// `-home-suzuki-repos`). Dots are mangled the same way, so a doubled dash is
+/// decoded as the start of a hidden directory (`/.`). Dashes that were part of
+/// the original directory names cannot be told apart and decode to `/`.
+pub fn decode_project_path(dir_name: &str) -> String {
+    let mut decoded = String::with_capacity(dir_name.len() + 1);
+    let mut chars = dir_name.chars().peekable();
+
+    if !dir_name.starts_with('-') {
+        decoded.push('/');
+    }
+
+    while let Some(c) = chars.next() {
+        if c == '-' {
+            decoded.push('/');
+            if chars.peek() == Some(&'-') {
+                chars.next();
+                decoded.push('.');
+            }
+        } else {
+            decoded.push(c);
+        }
+    }
+
+    decoded
+}
+
+/// Mangle a working directory into the project directory name Claude Code
+/// uses for it: every character other than an ASCII letter or digit becomes
+/// `-`. This is the inverse of [`decode_project_path`].
+pub fn encode_project_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_project_path() {
+        assert_eq!(
+            decode_project_path("-home-suzuki-repos"),
+            "/home/suzuki/repos"
+        );
+        assert_eq!(decode_project_path("-tmp"), "/tmp");
+        assert_eq!(
+            decode_project_path("-home-suzuki--config-nvim"),
+            "/home/suzuki/.config/nvim"
+        );
+        assert_eq!(decode_project_path("home-user"), "/home/user");
+    }
+
+    #[test]
+    fn test_encode_project_path() {
+        assert_eq!(
+            encode_project_path(Path::new("/home/suzuki/repos")),
+            "-home-suzuki-repos"
+        );
+        assert_eq!(
+            encode_project_path(Path::new("/home/suzuki/.config/nvim")),
+            "-home-suzuki--config-nvim"
+        );
+        let dir = "-home-suzuki--config-nvim";
+        assert_eq!(
+            encode_project_path(Path::new(&decode_project_path(dir))),
+            dir
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+use crate::parser::LogMessage;
+use crate::webhook::WebhookSender;
+use crate::WebhookOverflow;
+
+/// A message waiting to be sent through `sender`
+pub struct QueuedMessage {
+    pub sender: WebhookSender,
+    pub message: LogMessage,
+    pub formatted: String,
+}
+
+/// Work for the webhook task, processed in order
+pub enum WebhookJob {
+    Send(Box<QueuedMessage>),
+    /// Send whatever these senders have batched so far
+    Flush(Vec<WebhookSender>),
+    /// Tell `sender` that `project` is still being watched
+    Heartbeat {
+        sender: Box<WebhookSender>,
+        project: String,
+    },
+}
+
+/// Bounded queue between the watcher, which pushes jobs, and a task that
+/// delivers them, so a slow webhook does not hold up stdout
+#[derive(Clone)]
+pub struct WebhookQueue {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    jobs: Mutex<VecDeque<WebhookJob>>,
+    capacity: usize,
+    overflow: WebhookOverflow,
+    job_ready: Notify,
+    space_ready: Notify,
+    closed: AtomicBool,
+}
+
+impl WebhookQueue {
+    pub fn new(capacity: usize, overflow: WebhookOverflow) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                jobs: Mutex::new(VecDeque::new()),
+                capacity: capacity.max(1),
+                overflow,
+                job_ready: Notify::new(),
+                space_ready: Notify::new(),
+                closed: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Queue a job. When the queue is full this waits for space, or with
+    /// `WebhookOverflow::Drop` discards the oldest queued message instead,
+    /// or the oldest flush if no message is waiting.
+    pub async fn push(&self, job: WebhookJob) {
+        let shared = &self.shared;
+        let mut job = Some(job);
+        loop {
+            {
+                let mut jobs = shared.jobs.lock().unwrap();
+                let full = jobs.len() >= shared.capacity;
+                if full && shared.overflow == WebhookOverflow::Drop {
+                    // Flushes are kept over messages so batched messages are
+                    // still sent
+                    let oldest = jobs
+                        .iter()
+                        .position(|job| matches!(job, WebhookJob::Send(_)));
+                    match oldest {
+                        Some(index) => {
+                            jobs.remove(index);
+                            tracing::warn!("Webhook queue full; dropped the oldest message");
+                        }
+                        // Only flushes are waiting; any later one sends what
+                        // the oldest would have
+                        None => {
+                            jobs.pop_front();
+                        }
+                    }
+                }
+                if !full || shared.overflow == WebhookOverflow::Drop {
+                    jobs.extend(job.take());
+                    drop(jobs);
+                    shared.job_ready.notify_one();
+                    return;
+                }
+            }
+            shared.space_ready.notified().await;
+        }
+    }
+
+    /// Next job, or `None` once the queue is closed and empty
+    pub async fn pop(&self) -> Option<WebhookJob> {
+        let shared = &self.shared;
+        loop {
+            {
+                let mut jobs = shared.jobs.lock().unwrap();
+                if let Some(job) = jobs.pop_front() {
+                    drop(jobs);
+                    shared.space_ready.notify_one();
+                    return Some(job);
+                }
+                if shared.closed.load(Ordering::Acquire) {
+                    return None;
+                }
+            }
+            shared.job_ready.notified().await;
+        }
+    }
+
+    /// Let the consumer finish the remaining jobs and stop
+    pub fn close(&self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.job_ready.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::LogParser;
+    use crate::WebhookFormat;
+    use std::time::Duration;
+    use url::Url;
+
+    fn send_job(uuid: &str) -> WebhookJob {
+        let line = format!(
+            r#"{{"type":"user","message":{{"role":"user","content":"{uuid}"}},"timestamp":"2025-01-01T00:00:00Z","sessionId":"s","uuid":"{uuid}"}}"#
+        );
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        WebhookJob::Send(Box::new(QueuedMessage {
+            sender: WebhookSender::new(url, WebhookFormat::Generic).unwrap(),
+            message: LogParser::new().parse_line(&line).unwrap().unwrap(),
+            formatted: uuid.to_string(),
+        }))
+    }
+
+    fn uuid_of(job: WebhookJob) -> String {
+        match job {
+            WebhookJob::Send(queued) => queued.message.uuid,
+            WebhookJob::Flush(_) => "flush".to_string(),
+            WebhookJob::Heartbeat { .. } => "heartbeat".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drop_policy_discards_oldest() {
+        let queue = WebhookQueue::new(2, WebhookOverflow::Drop);
+        for uuid in ["u1", "u2", "u3"] {
+            queue.push(send_job(uuid)).await;
+        }
+        queue.close();
+
+        let mut uuids = Vec::new();
+        while let Some(job) = queue.pop().await {
+            uuids.push(uuid_of(job));
+        }
+        assert_eq!(uuids, vec!["u2", "u3"]);
+    }
+
+    #[tokio::test]
+    async fn test_drop_policy_stays_within_capacity() {
+        let queue = WebhookQueue::new(2, WebhookOverflow::Drop);
+        for _ in 0..3 {
+            queue.push(WebhookJob::Flush(Vec::new())).await;
+        }
+        queue.push(send_job("u1")).await;
+        queue.close();
+
+        let mut uuids = Vec::new();
+        while let Some(job) = queue.pop().await {
+            uuids.push(uuid_of(job));
+        }
+        assert_eq!(uuids, vec!["flush", "u1"]);
+    }
+
+    #[tokio::test]
+    async fn test_block_policy_waits_for_space() {
+        let queue = WebhookQueue::new(1, WebhookOverflow::Block);
+        queue.push(send_job("u1")).await;
+
+        let producer = queue.clone();
+        let push = tokio::spawn(async move { producer.push(send_job("u2")).await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!push.is_finished());
+
+        assert_eq!(uuid_of(queue.pop().await.unwrap()), "u1");
+        push.await.unwrap();
+        assert_eq!(uuid_of(queue.pop().await.unwrap()), "u2");
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::str::FromStr;
+
+/// Patterns applied by `--redact`, in order
+const BUILTIN_PATTERNS: &[(&str, &str)] = &[
+    // `Authorization: Bearer ...` and similar
+    (r"(?i)\bbearer\s+[A-Za-z0-9._~+/=-]+", "Bearer [REDACTED]"),
+    // OpenAI/Anthropic, GitHub, Slack and AWS style keys
+    (
+        r"\b(?:sk-[A-Za-z0-9_-]{16,}|gh[pousr]_[A-Za-z0-9]{20,}|xox[abprs]-[A-Za-z0-9-]{10,}|AKIA[0-9A-Z]{16})\b",
+        "[REDACTED]",
+    ),
+    (r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}", "[EMAIL]"),
+    (r"(?:/home|/Users)/[^/\s]+", "~"),
+];
+
+/// A `REGEX=REPLACEMENT` rule; the last `=` separates the two
+#[derive(Debug, Clone)]
+pub struct RedactPattern {
+    regex: Regex,
+    replacement: String,
+}
+
+impl FromStr for RedactPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (pattern, replacement) = s.rsplit_once('=').context("Expected REGEX=REPLACEMENT")?;
+        Ok(Self {
+            regex: Regex::new(pattern).with_context(|| format!("Invalid regex {pattern:?}"))?,
+            replacement: replacement.to_string(),
+        })
+    }
+}
+
+/// Masks sensitive text before it is printed or sent
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    patterns: Vec<RedactPattern>,
+}
+
+impl Redactor {
+    /// Emails, common API key formats, bearer tokens and home directories
+    pub fn builtin() -> Self {
+        let patterns = BUILTIN_PATTERNS
+            .iter()
+            .map(|(pattern, replacement)| RedactPattern {
+                regex: Regex::new(pattern).expect("built-in redaction pattern"),
+                replacement: replacement.to_string(),
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    /// Also apply user-supplied patterns, after any built-in ones
+    pub fn with_patterns(mut self, patterns: Vec<RedactPattern>) -> Self {
+        self.patterns.extend(patterns);
+        self
+    }
+
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for pattern in &self.patterns {
+            if let Cow::Owned(replaced) = pattern
+                .regex
+                .replace_all(&text, pattern.replacement.as_str())
+            {
+                text = Cow::Owned(replaced);
+            }
+        }
+        text
+    }
+
+    /// Redact every string inside a JSON value
+    pub fn redact_value(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => {
+                if let Cow::Owned(redacted) = self.redact(text) {
+                    *text = redacted;
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_value(item)),
+            Value::Object(map) => map.values_mut().for_each(|item| self.redact_value(item)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_builtin_patterns() {
+        let redactor = Redactor::builtin();
+        let text = "key sk-ant-REDACTED from jane.doe@example.com \
+                    in /home/jane/project with Authorization: Bearer abc.def";
+        assert_eq!(
+            redactor.redact(text),
+            "key [REDACTED] from [EMAIL] in ~/project with Authorization: Bearer [REDACTED]"
+        );
+        assert!(matches!(redactor.redact("nothing here"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_custom_pattern() {
+        let pattern: RedactPattern = r"ticket-\d+=ticket-#".parse().unwrap();
+        let redactor = Redactor::default().with_patterns(vec![pattern]);
+        assert_eq!(redactor.redact("see ticket-1234"), "see ticket-#");
+
+        assert!("no-separator".parse::<RedactPattern>().is_err());
+        assert!("a(b=x".parse::<RedactPattern>().is_err());
+    }
+
+    #[test]
+    fn test_redact_value() {
+        let mut value =
+            json!({"text": "mail me@example.com", "nested": ["sk-abcdefghijklmnopqrst"], "n": 1});
+        Redactor::builtin().redact_value(&mut value);
+        assert_eq!(
+            value,
+            json!({"text": "mail [EMAIL]", "nested": ["[REDACTED]"], "n": 1})
+        );
+    }
+}
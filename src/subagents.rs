@@ -0,0 +1,236 @@
+use crate::parser::LogMessage;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+
+/// Sidechain messages remembered as parents for the ones that follow
+const TRACKED_MESSAGES: usize = 10_000;
+
+/// Task calls remembered while waiting for their sub-agent to start
+const PENDING_TASKS: usize = 100;
+
+/// Traces sub-agent (sidechain) messages back to the `Task` call that
+/// spawned them.
+///
+/// A sub-agent's first entry has no `parentUuid`; its content is the
+/// prompt of the `Task` call, which is how it is matched. Later entries
+/// follow their `parentUuid` to an already traced one, so replies of
+/// parallel tasks stay apart however they interleave.
+///
+/// Both kinds of state are bounded: past their capacity the oldest Task
+/// calls and traced messages are forgotten.
+#[derive(Debug, Clone)]
+pub struct TaskTracker {
+    /// `(prompt, description)` of Task calls whose sub-agent has not
+    /// written anything yet, oldest first
+    pending: VecDeque<(String, String)>,
+    /// Task description of recently traced sidechain messages, by uuid
+    threads: HashMap<String, String>,
+    /// Uuids in `threads`, oldest first
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl Default for TaskTracker {
+    fn default() -> Self {
+        Self::new(TRACKED_MESSAGES)
+    }
+}
+
+impl TaskTracker {
+    /// Remember up to `capacity` traced sidechain messages
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            threads: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Note the Task calls `message` makes, or fill in `message.task` for
+    /// a sidechain message whose Task call is known
+    pub fn resolve(&mut self, message: &mut LogMessage) {
+        if !message.is_sidechain {
+            for call in task_calls(message) {
+                if self.pending.len() == PENDING_TASKS {
+                    self.pending.pop_front();
+                }
+                self.pending.push_back(call);
+            }
+            return;
+        }
+
+        let task = match message.parent_uuid {
+            Some(ref parent) => self.threads.get(parent).cloned(),
+            None => self.start_thread(&message.content),
+        };
+        if let Some(ref task) = task {
+            self.remember(&message.uuid, task);
+        }
+        message.task = task;
+    }
+
+    fn remember(&mut self, uuid: &str, task: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.threads.remove(&oldest);
+            }
+        }
+        if self
+            .threads
+            .insert(uuid.to_string(), task.to_string())
+            .is_none()
+        {
+            self.order.push_back(uuid.to_string());
+        }
+    }
+
+    /// Description of the pending Task call started with `prompt`
+    fn start_thread(&mut self, prompt: &str) -> Option<String> {
+        let index = self
+            .pending
+            .iter()
+            .position(|(pending, _)| pending.trim() == prompt.trim())?;
+        self.pending
+            .remove(index)
+            .map(|(_, description)| description)
+    }
+}
+
+/// `(prompt, description)` of each Task call in `message`
+fn task_calls(message: &LogMessage) -> Vec<(String, String)> {
+    let Some(Value::Array(blocks)) = message.raw_content.as_ref() else {
+        return Vec::new();
+    };
+    blocks
+        .iter()
+        .filter(|block| block["type"] == "tool_use" && block["name"] == "Task")
+        .filter_map(|block| {
+            let input = &block["input"];
+            let prompt = input["prompt"].as_str()?;
+            let description = input["description"]
+                .as_str()
+                .or_else(|| input["subagent_type"].as_str())
+                .unwrap_or("Task");
+            Some((prompt.to_string(), description.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::LogParser;
+    use serde_json::json;
+
+    fn entry(uuid: &str, parent: Option<&str>, sidechain: bool, content: Value) -> LogMessage {
+        let line = json!({
+            "type": if content.is_string() { "user" } else { "assistant" },
+            "message": {
+                "role": if content.is_string() { "user" } else { "assistant" },
+                "content": content,
+            },
+            "timestamp": "2025-01-01T00:00:00Z",
+            "sessionId": "s",
+            "uuid": uuid,
+            "parentUuid": parent,
+            "isSidechain": sidechain,
+        });
+        LogParser::new()
+            .parse_line(&line.to_string())
+            .unwrap()
+            .unwrap()
+    }
+
+    fn task(description: &str, prompt: &str) -> Value {
+        json!({
+            "type": "tool_use",
+            "id": format!("toolu_{description}"),
+            "name": "Task",
+            "input": {"description": description, "prompt": prompt},
+        })
+    }
+
+    fn text(text: &str) -> Value {
+        json!([{"type": "text", "text": text}])
+    }
+
+    #[test]
+    fn test_parallel_tasks_keep_their_replies() {
+        let mut tracker = TaskTracker::default();
+        let mut messages = vec![
+            entry(
+                "a1",
+                None,
+                false,
+                json!([
+                    task("Find callers", "Search for callers"),
+                    task("Read docs", "Read the docs")
+                ]),
+            ),
+            // The second task's sub-agent starts first, and the two interleave
+            entry("d1", None, true, json!("Read the docs")),
+            entry("c1", None, true, json!("Search for callers")),
+            entry("c2", Some("c1"), true, text("Found main.rs")),
+            entry("d2", Some("d1"), true, text("The docs say")),
+            entry("c3", Some("c2"), true, text("Done")),
+        ];
+        for message in &mut messages {
+            tracker.resolve(message);
+        }
+
+        let tasks: Vec<_> = messages
+            .iter()
+            .map(|message| (message.uuid.as_str(), message.task.as_deref()))
+            .collect();
+        assert_eq!(
+            tasks,
+            [
+                ("a1", None),
+                ("d1", Some("Read docs")),
+                ("c1", Some("Find callers")),
+                ("c2", Some("Find callers")),
+                ("d2", Some("Read docs")),
+                ("c3", Some("Find callers")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_state_is_bounded() {
+        let mut tracker = TaskTracker::new(2);
+        let calls: Vec<Value> = (0..=PENDING_TASKS)
+            .map(|i| task(&format!("t{i}"), &format!("prompt {i}")))
+            .collect();
+        tracker.resolve(&mut entry("a1", None, false, Value::Array(calls)));
+        assert_eq!(tracker.pending.len(), PENDING_TASKS);
+
+        // The oldest call was forgotten; the newest is still matched
+        let mut first = entry("x1", None, true, json!("prompt 0"));
+        tracker.resolve(&mut first);
+        assert_eq!(first.task, None);
+        let mut root = entry("c1", None, true, json!(format!("prompt {PENDING_TASKS}")));
+        tracker.resolve(&mut root);
+        assert!(root.task.is_some());
+
+        for (uuid, parent) in [("c2", "c1"), ("c3", "c2"), ("c4", "c3")] {
+            tracker.resolve(&mut entry(uuid, Some(parent), true, text("step")));
+        }
+        assert_eq!(tracker.threads.len(), 2);
+        assert_eq!(tracker.order, ["c3", "c4"]);
+    }
+
+    #[test]
+    fn test_unknown_task_left_unresolved() {
+        let mut tracker = TaskTracker::default();
+        let mut root = entry("x1", None, true, json!("Started before watching"));
+        let mut reply = entry("x2", Some("x1"), true, text("Working"));
+        tracker.resolve(&mut root);
+        tracker.resolve(&mut reply);
+        assert_eq!(root.task, None);
+        assert_eq!(reply.task, None);
+    }
+}
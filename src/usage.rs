@@ -0,0 +1,219 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+
+use crate::parser::{LogParser, MessageRole, ParseStats};
+use crate::watcher::session_files;
+
+/// Token counts from an assistant message's `usage` object.
+///
+/// `input_tokens` only covers the uncached part of the prompt; tokens written
+/// to or read from the prompt cache are reported separately, so the three
+/// input fields are disjoint and can be summed without double counting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+}
+
+impl Usage {
+    /// Every prompt token the model processed, cached or not
+    pub fn total_input(&self) -> u64 {
+        self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+
+    /// Prompt tokens expressed at the base input rate: cache writes cost
+    /// 1.25x (5-minute cache) and cache reads 0.1x
+    pub fn billable_input(&self) -> u64 {
+        (self.input_tokens * 100
+            + self.cache_creation_input_tokens * 125
+            + self.cache_read_input_tokens * 10)
+            / 100
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+}
+
+/// Message and token totals for one or more sessions
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SessionStats {
+    pub sessions: usize,
+    pub user_messages: usize,
+    pub assistant_messages: usize,
+    pub usage: Usage,
+    /// Line counts summed over all files
+    pub parse: ParseStats,
+    pub files: Vec<FileParseStats>,
+}
+
+/// Line counts for one session file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileParseStats {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub stats: ParseStats,
+}
+
+/// Totals for a session file, or for every session in a project directory,
+/// including its subdirectories as the watcher does
+pub fn collect_stats(path: &Path) -> Result<SessionStats> {
+    let files = if path.is_dir() {
+        fs::read_dir(path).with_context(|| format!("Cannot read directory {path:?}"))?;
+        session_files(path)
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut parser = LogParser::new();
+    let mut stats = SessionStats {
+        sessions: files.len(),
+        ..SessionStats::default()
+    };
+    // Claude Code writes one entry per content block and repeats the API
+    // message's usage on each, so usage is counted once per message id
+    let mut usage_by_message: HashMap<String, Usage> = HashMap::new();
+
+    for file in &files {
+        for message in parser.parse_file(file)? {
+            match message.role {
+                MessageRole::User => stats.user_messages += 1,
+                MessageRole::Assistant => stats.assistant_messages += 1,
+                MessageRole::System | MessageRole::Summary => {}
+            }
+        }
+        stats.parse += parser.last_stats();
+        stats.files.push(FileParseStats {
+            path: file.clone(),
+            stats: parser.last_stats().clone(),
+        });
+
+        let content =
+            fs::read_to_string(file).with_context(|| format!("Cannot read file {file:?}"))?;
+        let content = content.strip_prefix('\u{feff}').unwrap_or(&content);
+        for line in content.lines() {
+            if let Some((id, usage)) = entry_usage(line) {
+                usage_by_message.insert(id, usage);
+            }
+        }
+    }
+
+    for usage in usage_by_message.into_values() {
+        stats.usage += usage;
+    }
+    Ok(stats)
+}
+
+/// Message id and usage of an assistant entry, if it reports any
+fn entry_usage(line: &str) -> Option<(String, Usage)> {
+    let entry: Value = serde_json::from_str(line).ok()?;
+    let message = entry.get("message")?;
+    let usage = serde_json::from_value(message.get("usage")?.clone()).ok()?;
+    let id = message
+        .get("id")
+        .or_else(|| entry.get("uuid"))?
+        .as_str()?
+        .to_string();
+    Some((id, usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn assistant_line(uuid: &str, message_id: &str, usage: &str) -> String {
+        format!(
+            r#"{{"type":"assistant","message":{{"id":"{message_id}","role":"assistant","content":"ok","usage":{usage}}},"timestamp":"2025-01-01T12:00:00Z","sessionId":"s","uuid":"{uuid}"}}"#
+        )
+    }
+
+    #[test]
+    fn test_billable_input_weights_cache_tokens() {
+        let usage = Usage {
+            input_tokens: 1000,
+            output_tokens: 500,
+            cache_creation_input_tokens: 2000,
+            cache_read_input_tokens: 10000,
+        };
+        assert_eq!(usage.total_input(), 13000);
+        assert_eq!(usage.billable_input(), 1000 + 2500 + 1000);
+    }
+
+    #[test]
+    fn test_nested_sessions_counted() {
+        let project = tempfile::TempDir::new().unwrap();
+        let nested = project.path().join("subagents");
+        fs::create_dir(&nested).unwrap();
+        let line = assistant_line("u1", "msg_1", r#"{"output_tokens":1}"#) + "\n";
+        fs::write(project.path().join("main.jsonl"), &line).unwrap();
+        fs::write(nested.join("agent.jsonl"), &line).unwrap();
+
+        let stats = collect_stats(project.path()).unwrap();
+        assert_eq!(stats.sessions, 2);
+        assert_eq!(stats.files[1].path, nested.join("agent.jsonl"));
+    }
+
+    #[test]
+    fn test_usage_counted_once_per_message_id() {
+        let first = r#"{"input_tokens":10,"output_tokens":5,"cache_creation_input_tokens":400,"cache_read_input_tokens":2000}"#;
+        let second = r#"{"input_tokens":20,"output_tokens":7,"cache_read_input_tokens":2400}"#;
+        let user = r#"{"type":"user","message":{"role":"user","content":"hi"},"timestamp":"2025-01-01T12:00:00Z","sessionId":"s","uuid":"u0"}"#;
+
+        let mut file = NamedTempFile::new().unwrap();
+        for line in [
+            user.to_string(),
+            // Two content blocks of the same API message share its usage
+            assistant_line("u1", "msg_1", first),
+            assistant_line("u2", "msg_1", first),
+            assistant_line("u3", "msg_2", second),
+            r#"{"type":"file-history-snapshot","messageId":"m"}"#.to_string(),
+            "{broken".to_string(),
+        ] {
+            writeln!(file, "{line}").unwrap();
+        }
+
+        let stats = collect_stats(file.path()).unwrap();
+        assert_eq!(stats.sessions, 1);
+        assert_eq!(stats.user_messages, 1);
+        assert_eq!(stats.assistant_messages, 3);
+        assert_eq!(
+            stats.usage,
+            Usage {
+                input_tokens: 30,
+                output_tokens: 12,
+                cache_creation_input_tokens: 400,
+                cache_read_input_tokens: 4400,
+            }
+        );
+        assert_eq!(stats.usage.total_input(), 4830);
+        assert_eq!(stats.usage.billable_input(), 30 + 500 + 440);
+
+        assert_eq!(
+            (
+                stats.parse.lines,
+                stats.parse.parsed,
+                stats.parse.filtered,
+                stats.parse.malformed
+            ),
+            (6, 4, 1, 1)
+        );
+        assert!(stats.parse.last_error.is_some());
+        assert_eq!(stats.files.len(), 1);
+        assert_eq!(stats.files[0].stats, stats.parse);
+    }
+}
@@ -1,260 +1,3427 @@
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
-use notify::{event::CreateKind, Event, EventKind, RecursiveMode, Watcher};
+use chrono::{DateTime, Local, Utc};
+use notify::event::{CreateKind, ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use regex::Regex;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::fs;
+use std::io::IsTerminal;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc;
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
 use tokio::sync::mpsc as tokio_mpsc;
-use tokio::time::{sleep, Duration};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout, Duration};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tracing::{debug, error, info, warn};
 
-use crate::formatter::LogFormatter;
-use crate::parser::LogParser;
-use crate::webhook::{WebhookResult, WebhookSender};
-use crate::WebhookFormat;
+use crate::dedup::SeenUuids;
+use crate::exec::ExecFormatter;
+use crate::formatter::{nest_sidechain, strip_ansi, LogFormatter};
+use crate::metrics::Metrics;
+use crate::parser::{LogMessage, LogParser, MessageRole, ParseStats};
+use crate::project::{decode_project_path, encode_project_path};
+use crate::queue::{QueuedMessage, WebhookJob, WebhookQueue};
+use crate::redact::Redactor;
+use crate::subagents::TaskTracker;
+use crate::webhook::{
+    DroppedPayloads, RateLimited, WebhookResult, WebhookRoute, WebhookSender, WebhookTemplate,
+};
+use crate::{write_output, SharedWriter};
+use crate::{
+    ProjectSort, SlackStyle, StartPosition, WebhookFormat, WebhookOverflow, WebhookPreset,
+};
 use url::Url;
 
+type WatchReceiver = tokio_mpsc::UnboundedReceiver<notify::Result<Event>>;
+
+/// Delay before the first attempt to re-create a failed watcher
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+/// Upper bound for the exponential reconnect backoff
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+/// Attempts before giving up on a project whose watch cannot be restored
+const RECONNECT_ATTEMPTS: u32 = 8;
+/// Messages waiting for the webhook before `WebhookOverflow` applies
+const WEBHOOK_QUEUE_CAPACITY: usize = 1000;
+/// Messages `stream` buffers before the watcher waits for the consumer
+const STREAM_BUFFER: usize = 256;
+/// How long a collapsed line waits for another repeat before it is printed
+const REPEAT_QUIET_PERIOD: Duration = Duration::from_secs(2);
+
+/// Summary of a single project directory, as shown by the `list` command
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectInfo {
+    pub name: String,
+    /// Claude projects directory the project was found in
+    pub root: PathBuf,
+    pub decoded_path: String,
+    pub path: PathBuf,
+    pub sessions: usize,
+    pub messages: usize,
+    pub last_modified: Option<DateTime<Utc>>,
+    /// Line counts over all sessions, for `doctor`
+    #[serde(skip)]
+    pub parse: ParseStats,
+}
+
+/// Running totals shown by the status line
+#[derive(Debug, Default)]
+struct StatusCounts {
+    sessions: HashSet<String>,
+    messages: usize,
+    webhook_sent: usize,
+    webhook_skipped: usize,
+    webhook_failed: usize,
+    /// Batched payloads waiting for a successful flush
+    webhook_queued: usize,
+    webhook_dry_run: usize,
+}
+
+impl StatusCounts {
+    fn record(&mut self, message: &LogMessage) {
+        if !self.sessions.contains(&message.session_id) {
+            self.sessions.insert(message.session_id.clone());
+        }
+        self.messages += 1;
+    }
+
+    fn record_webhook(&mut self, result: &Result<WebhookResult>) {
+        match result {
+            Ok(WebhookResult::Sent) => self.webhook_sent += 1,
+            Ok(WebhookResult::Queued) => self.webhook_queued += 1,
+            Ok(WebhookResult::DryRun) => self.webhook_dry_run += 1,
+            Ok(WebhookResult::Skipped) => self.webhook_skipped += 1,
+            Err(_) => self.webhook_failed += 1,
+        }
+    }
+
+    /// Batched payloads count as sent once flushed, or as failed once a
+    /// failing endpoint makes the sender drop them
+    fn record_flush(&mut self, result: &Result<usize>) {
+        let (done, counter) = match result {
+            Ok(sent) => (*sent, &mut self.webhook_sent),
+            Err(e) => match e.downcast_ref::<DroppedPayloads>() {
+                Some(dropped) => (dropped.0, &mut self.webhook_failed),
+                None => return,
+            },
+        };
+        *counter += done;
+        self.webhook_queued = self.webhook_queued.saturating_sub(done);
+    }
+
+    /// `sessions: 3 | msgs: 152`, plus webhook totals when one is configured
+    fn render(&self, webhook: bool) -> String {
+        let mut line = format!(
+            "sessions: {} | msgs: {}",
+            self.sessions.len(),
+            self.messages
+        );
+        if webhook {
+            line.push_str(&format!(
+                " | webhook sent: {} skipped: {}",
+                self.webhook_sent, self.webhook_skipped
+            ));
+            for (label, count) in [
+                ("queued", self.webhook_queued),
+                ("failed", self.webhook_failed),
+                ("dry run", self.webhook_dry_run),
+            ] {
+                if count > 0 {
+                    line.push_str(&format!(" {label}: {count}"));
+                }
+            }
+        }
+        line
+    }
+}
+
+/// Token bucket pacing stdout to `rate` lines per second, with bursts of
+/// up to one second's worth
+#[derive(Debug)]
+struct Pacer {
+    rate: f64,
+    tokens: f64,
+    last: tokio::time::Instant,
+}
+
+impl Pacer {
+    fn new(rate: u32) -> Self {
+        Self {
+            rate: f64::from(rate),
+            tokens: f64::from(rate),
+            last: tokio::time::Instant::now(),
+        }
+    }
+
+    /// Take a token, returning how long to wait before using it
+    fn reserve(&mut self) -> Duration {
+        let now = tokio::time::Instant::now();
+        let refill = now.duration_since(self.last).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + refill).min(self.rate) - 1.0;
+        self.last = now;
+        Duration::from_secs_f64((-self.tokens).max(0.0) / self.rate)
+    }
+}
+
+/// A formatted line held back while identical lines keep arriving
+#[derive(Clone)]
+struct PendingRepeat {
+    message: LogMessage,
+    formatted: String,
+    /// Formatted line without the timestamp, used for comparison
+    body: String,
+    count: usize,
+    /// When the last repeat arrived
+    last_seen: Instant,
+}
+
+impl PendingRepeat {
+    fn text(&self) -> String {
+        if self.count > 1 {
+            format!("{} (x{})", self.formatted, self.count)
+        } else {
+            self.formatted.clone()
+        }
+    }
+}
+
+/// Webhook settings, kept so senders added after a setting get it too
+#[derive(Clone, Default)]
+struct WebhookConfig {
+    timeout: Option<Duration>,
+    batch: bool,
+    dry_run: bool,
+    template: Option<WebhookTemplate>,
+    slack_style: SlackStyle,
+    slack_username: Option<String>,
+    slack_icon_emoji: Option<String>,
+    prefix: Option<String>,
+    suffix: Option<String>,
+    include_raw: bool,
+    preset: Option<WebhookPreset>,
+    roles: Option<Vec<MessageRole>>,
+    hide_completed_todos: bool,
+}
+
+#[derive(Clone)]
 pub struct LogWatcher {
-    claude_dir: PathBuf,
+    /// Claude projects directories to scan, in priority order
+    claude_dirs: Vec<PathBuf>,
     parser: LogParser,
     formatter: LogFormatter,
     webhook_sender: Option<WebhookSender>,
+    /// Route-specific senders, checked in order before the default sender
+    webhook_routes: Vec<(WebhookRoute, WebhookSender)>,
     include_existing: bool,
+    /// Include existing messages newer than this instant
+    existing_from: Option<DateTime<Utc>>,
     startup_time: DateTime<Utc>,
+    /// Explicit start offset for existing files; disables the startup time cutoff
+    start_position: Option<StartPosition>,
+    /// Resume after the message with this uuid; disables the startup time cutoff
+    after_uuid: Option<String>,
+    after_uuid_inclusive: bool,
+    metrics: Option<Arc<Metrics>>,
+    strict: bool,
+    tail: Option<usize>,
+    follow_rename: bool,
+    follow_latest: bool,
+    /// Stop watching once no message has been emitted for this long
+    idle_timeout: Option<Duration>,
+    /// Applied to every webhook sender, whenever it is added
+    webhook_config: WebhookConfig,
+    /// Ping the webhook after this long without sending anything
+    heartbeat_interval: Option<Duration>,
+    /// When the webhook last got a message or heartbeat, shared across
+    /// projects so `watch_all` sends one heartbeat per interval
+    webhook_sent: Arc<Mutex<Instant>>,
+    /// Named in heartbeats instead of the project being watched
+    heartbeat_project: Option<String>,
+    /// Session being tailed when following only the latest one
+    active_session: Option<PathBuf>,
+    watch_root: Option<PathBuf>,
+    /// Shared between the per-project watchers spawned by `watch_all`
+    seen_uuids: Option<Arc<Mutex<SeenUuids>>>,
+    /// Session of the last emitted message, when grouping output by session
+    last_session: Option<Arc<Mutex<Option<String>>>>,
+    /// Keep messages under the minimum length from webhooks as well
+    min_content_len_webhooks: bool,
+    collapse_repeats: bool,
+    pending_repeat: Option<PendingRepeat>,
+    /// Links sub-agent messages to the Task call that spawned them, while
+    /// sidechains are shown
+    tasks: Option<TaskTracker>,
+    /// Number of the last emitted message, shared across projects
+    sequence: Option<Arc<AtomicU64>>,
+    /// Limits how fast lines reach stdout, shared across projects
+    pacer: Option<Arc<Mutex<Pacer>>>,
+    /// Print messages; off when only webhooks should receive them
+    stdout: bool,
+    /// Where messages are printed, stdout unless `with_writer` replaces it
+    writer: SharedWriter,
+    /// Print each message's original log line instead of formatting it
+    jsonl_passthrough: bool,
+    /// External program that formats stdout lines, shared across projects
+    exec_formatter: Option<Arc<tokio::sync::Mutex<ExecFormatter>>>,
+    /// Restricts `watch_all` to projects whose decoded path matches
+    project_filter: Option<Regex>,
+    /// Most projects `watch_all` watches at once, newest first
+    max_sessions: Option<usize>,
+    /// Ignore projects with no session modified within this long
+    active_within: Option<Duration>,
+    /// Totals redrawn below the output, shared across projects
+    status: Option<Arc<Mutex<StatusCounts>>>,
+    #[cfg(unix)]
+    events: Option<crate::events::EventPublisher>,
+    /// Masks content passed on as is, e.g. to events socket clients
+    redactor: Option<Redactor>,
+    #[cfg(feature = "otel")]
+    telemetry: Option<Arc<crate::otel::Telemetry>>,
+    /// Receives emitted messages in place of stdout, see `stream`
+    sink: Option<tokio_mpsc::Sender<LogMessage>>,
+    webhook_overflow: WebhookOverflow,
+    /// Feeds the webhook task while a project is being watched
+    webhook_queue: Option<WebhookQueue>,
+}
+
+impl Default for LogWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl LogWatcher {
     pub fn new() -> Self {
-        let home = std::env::var("HOME").expect("HOME environment variable not set");
-        let claude_dir = PathBuf::from(home).join(".claude").join("projects");
+        let claude_dirs = std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".claude").join("projects"))
+            .into_iter()
+            .collect();
 
         Self {
-            claude_dir,
+            claude_dirs,
             parser: LogParser::new(),
             formatter: LogFormatter::new(),
             webhook_sender: None,
+            webhook_routes: Vec::new(),
             include_existing: false,
+            existing_from: None,
             startup_time: Utc::now(),
+            start_position: None,
+            after_uuid: None,
+            after_uuid_inclusive: false,
+            metrics: None,
+            strict: false,
+            tail: None,
+            follow_rename: false,
+            follow_latest: false,
+            idle_timeout: None,
+            webhook_config: WebhookConfig::default(),
+            heartbeat_interval: None,
+            webhook_sent: Arc::new(Mutex::new(Instant::now())),
+            heartbeat_project: None,
+            active_session: None,
+            watch_root: None,
+            seen_uuids: None,
+            last_session: None,
+            min_content_len_webhooks: false,
+            collapse_repeats: false,
+            pending_repeat: None,
+            tasks: None,
+            sequence: None,
+            pacer: None,
+            stdout: true,
+            writer: Arc::new(Mutex::new(std::io::stdout())),
+            jsonl_passthrough: false,
+            exec_formatter: None,
+            project_filter: None,
+            max_sessions: None,
+            active_within: None,
+            status: None,
+            #[cfg(unix)]
+            events: None,
+            redactor: None,
+            #[cfg(feature = "otel")]
+            telemetry: None,
+            sink: None,
+            webhook_overflow: WebhookOverflow::default(),
+            webhook_queue: None,
         }
     }
 
+    /// Use a custom Claude projects directory instead of `$HOME/.claude/projects`
+    pub fn with_claude_dir(self, claude_dir: Option<PathBuf>) -> Self {
+        self.with_claude_dirs(claude_dir.into_iter().collect())
+    }
+
+    /// Scan several Claude projects directories, e.g. one per user, instead
+    /// of `$HOME/.claude/projects`. An empty list keeps the default.
+    pub fn with_claude_dirs(mut self, claude_dirs: Vec<PathBuf>) -> Self {
+        if !claude_dirs.is_empty() {
+            self.claude_dirs = claude_dirs;
+        }
+        self
+    }
+
     pub fn with_tool_display_mode(mut self, mode: crate::ToolDisplayMode) -> Self {
         self.formatter = self.formatter.with_tool_display_mode(mode);
         self
     }
 
+    pub fn with_tool_verbosity(
+        mut self,
+        overrides: impl IntoIterator<Item = (String, crate::ToolDisplayMode)>,
+    ) -> Self {
+        self.formatter = self.formatter.with_tool_verbosity(overrides);
+        self
+    }
+
+    /// Only show messages with this role
+    pub fn with_role_filter(mut self, role: Option<MessageRole>) -> Self {
+        self.formatter = self.formatter.with_role_filter(role);
+        self
+    }
+
+    /// Show only the prose of each message, without tool or thinking markers
+    pub fn with_text_only(mut self, text_only: bool) -> Self {
+        self.formatter = self.formatter.with_text_only(text_only);
+        self
+    }
+
+    /// Hide messages shorter than `min` characters, such as a bare "ok",
+    /// on stdout. Webhooks still receive them unless
+    /// `with_min_content_len_webhooks` is set.
+    pub fn with_min_content_len(mut self, min: Option<usize>) -> Self {
+        self.formatter = self.formatter.with_min_content_len(min);
+        self
+    }
+
+    /// Apply the minimum content length to webhooks as well
+    pub fn with_min_content_len_webhooks(mut self, apply: bool) -> Self {
+        self.min_content_len_webhooks = apply;
+        self
+    }
+
+    pub fn with_hide_meta(mut self, hide: bool) -> Self {
+        self.formatter = self.formatter.with_hide_meta(hide);
+        self
+    }
+
+    /// Hide sub-agent conversations; on by default
+    pub fn with_hide_sidechains(mut self, hide: bool) -> Self {
+        self.formatter = self.formatter.with_hide_sidechains(hide);
+        self.tasks = (!hide).then(TaskTracker::default);
+        self
+    }
+
+    /// Color the role indicator of each message
+    pub fn with_role_color(mut self, color: bool) -> Self {
+        self.formatter = self.formatter.with_role_color(color);
+        self
+    }
+
+    pub fn with_result_color(mut self, color: bool) -> Self {
+        self.formatter = self.formatter.with_result_color(color);
+        self
+    }
+
+    /// Join assistant text blocks that split a sentence with a space
+    pub fn with_merge_adjacent_text(mut self, merge: bool) -> Self {
+        self.parser = self.parser.with_merge_adjacent_text(merge);
+        self
+    }
+
+    /// Show `summary` entries as `📋 Summary:` lines
+    pub fn with_show_summaries(mut self, show: bool) -> Self {
+        self.parser = self.parser.with_summaries(show);
+        self
+    }
+
+    pub fn with_show_thinking(mut self, show: bool) -> Self {
+        self.formatter = self.formatter.with_show_thinking(show);
+        self
+    }
+
+    pub fn with_show_citations(mut self, show: bool) -> Self {
+        self.formatter = self.formatter.with_show_citations(show);
+        self
+    }
+
+    /// Characters of tool input shown in detailed mode
+    pub fn with_tool_input_len(mut self, len: usize) -> Self {
+        self.formatter = self.formatter.with_tool_input_len(len);
+        self
+    }
+
+    pub fn with_todo_progress(mut self, width: Option<usize>) -> Self {
+        self.formatter = self.formatter.with_todo_progress(width);
+        self
+    }
+
+    pub fn with_max_result_lines(mut self, max: Option<usize>) -> Self {
+        self.formatter = self.formatter.with_max_result_lines(max);
+        self
+    }
+
+    pub fn with_timestamp(mut self, show: bool) -> Self {
+        self.formatter = self.formatter.with_timestamp(show);
+        self
+    }
+
+    pub fn with_time_format(mut self, format: impl Into<String>) -> Self {
+        self.formatter = self.formatter.with_time_format(format);
+        self
+    }
+
+    pub fn with_utc(mut self, utc: bool) -> Self {
+        self.formatter = self.formatter.with_utc(utc);
+        self
+    }
+
+    pub fn with_session_id(mut self, show: bool) -> Self {
+        self.formatter = self.formatter.with_session_id(show);
+        self
+    }
+
+    pub fn with_indent(mut self, indent: bool) -> Self {
+        self.formatter = self.formatter.with_indent(indent);
+        self
+    }
+
+    pub fn with_flatten_newlines(mut self, flatten: bool) -> Self {
+        self.formatter = self.formatter.with_flatten_newlines(flatten);
+        self
+    }
+
+    pub fn with_compact_mode(mut self, compact: bool) -> Self {
+        self.formatter = self.formatter.with_compact_mode(compact);
+        self
+    }
+
+    pub fn with_theme(mut self, theme: crate::Theme) -> Self {
+        self.formatter = self.formatter.with_theme(theme);
+        self
+    }
+
+    /// Request timeout for webhooks, whether added before or after this call
+    pub fn with_webhook_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.webhook_config.timeout = timeout;
+        let Some(timeout) = timeout else {
+            return self;
+        };
+        let with_timeout = |sender: WebhookSender| match sender.clone().with_timeout(timeout) {
+            Ok(sender) => sender,
+            Err(e) => {
+                error!("Cannot apply webhook timeout: {e:#}");
+                sender
+            }
+        };
+        self.map_senders(with_timeout)
+    }
+
+    /// Reconfigure the default webhook and every routed one alike
+    fn map_senders(mut self, f: impl Fn(WebhookSender) -> WebhookSender) -> Self {
+        self.webhook_sender = self.webhook_sender.map(&f);
+        self.webhook_routes = self
+            .webhook_routes
+            .into_iter()
+            .map(|(route, sender)| (route, f(sender)))
+            .collect();
+        self
+    }
+
+    fn build_webhook_sender(&self, url: Url, format: WebhookFormat) -> Result<WebhookSender> {
+        let config = &self.webhook_config;
+        let mut sender = WebhookSender::new(url, format)?;
+        if let Some(timeout) = config.timeout {
+            sender = sender.with_timeout(timeout)?;
+        }
+        if let Some(ref metrics) = self.metrics {
+            sender = sender.with_metrics(metrics.clone());
+        }
+        Ok(sender
+            .with_batching(config.batch)
+            .with_dry_run(config.dry_run)
+            .with_redactor(self.redactor.clone())
+            .with_template(config.template.clone())
+            .with_slack_style(config.slack_style)
+            .with_slack_identity(
+                config.slack_username.clone(),
+                config.slack_icon_emoji.clone(),
+            )
+            .with_text_wrapping(config.prefix.clone(), config.suffix.clone())
+            .with_include_raw(config.include_raw)
+            .with_preset(config.preset)
+            .with_roles(config.roles.clone())
+            .with_hide_completed_todos(config.hide_completed_todos))
+    }
+
     pub fn with_webhook(mut self, url: Option<Url>, format: WebhookFormat) -> Self {
         if let Some(webhook_url) = url {
-            match WebhookSender::new(webhook_url, format) {
+            match self.build_webhook_sender(webhook_url, format) {
                 Ok(sender) => {
                     self.webhook_sender = Some(sender);
-                    println!("Webhook configured successfully");
+                    info!("Webhook configured successfully");
                 }
                 Err(e) => {
-                    eprintln!("Failed to configure webhook: {e}");
+                    error!("Failed to configure webhook: {e}");
                 }
             }
         }
         self
     }
 
-    pub fn with_include_existing(mut self, include_existing: bool) -> Self {
-        self.include_existing = include_existing;
+    /// Send messages matching a route to that route's URL instead of the default webhook
+    pub fn with_webhook_routes(mut self, routes: Vec<WebhookRoute>, format: WebhookFormat) -> Self {
+        for route in routes {
+            match self.build_webhook_sender(route.url.clone(), format.clone()) {
+                Ok(sender) => {
+                    self.webhook_routes.push((route, sender));
+                }
+                Err(e) => error!("Failed to configure webhook route {}: {e}", route.url),
+            }
+        }
         self
     }
 
-    /// List available projects
-    pub async fn list_projects(&self) -> Result<()> {
-        let entries =
-            fs::read_dir(&self.claude_dir).context("Claude projects directory not found")?;
+    /// Batch generic webhook payloads per file read and send them as NDJSON
+    pub fn with_webhook_batch(mut self, enabled: bool) -> Self {
+        self.webhook_config.batch = enabled;
+        self.map_senders(|sender| sender.with_batching(enabled))
+    }
 
-        println!("Available projects:");
-        for entry in entries {
-            let entry = entry?;
-            if entry.file_type()?.is_dir() {
-                let project_name = entry.file_name();
-                let project_path = entry.path();
+    /// Print webhook payloads to stderr instead of posting them
+    pub fn with_webhook_dry_run(mut self, dry_run: bool) -> Self {
+        self.webhook_config.dry_run = dry_run;
+        self.map_senders(|sender| sender.with_dry_run(dry_run))
+    }
 
-                // Search for JSONL files within the project
-                if let Ok(files) = fs::read_dir(&project_path) {
-                    let jsonl_count = files
-                        .filter_map(|f| f.ok())
-                        .filter(|f| f.path().extension().and_then(|s| s.to_str()) == Some("jsonl"))
-                        .count();
+    /// Mask sensitive text on stdout, in webhook payloads, in events and in
+    /// what `--exec-formatter` programs receive
+    pub fn with_redactor(mut self, redactor: Option<Redactor>) -> Self {
+        self.formatter = self.formatter.with_redactor(redactor.clone());
+        self.redactor = redactor.clone();
+        self.map_senders(|sender| sender.with_redactor(redactor.clone()))
+    }
 
-                    println!("  {project_name:?} ({jsonl_count} sessions)");
-                }
-            }
-        }
-        Ok(())
+    /// What to do when messages arrive faster than the webhook accepts them
+    pub fn with_webhook_overflow(mut self, overflow: WebhookOverflow) -> Self {
+        self.webhook_overflow = overflow;
+        self
     }
 
-    /// Get the latest project
-    async fn get_latest_project(&self) -> Result<PathBuf> {
-        let entries =
-            fs::read_dir(&self.claude_dir).context("Claude projects directory not found")?;
+    /// Shape generic webhook payloads with a custom template
+    pub fn with_webhook_template(mut self, template: Option<WebhookTemplate>) -> Self {
+        self.webhook_config.template = template.clone();
+        self.map_senders(|sender| sender.with_template(template.clone()))
+    }
 
-        let mut latest_project: Option<(PathBuf, SystemTime)> = None;
+    /// Layout of Slack webhook messages
+    pub fn with_slack_style(mut self, style: SlackStyle) -> Self {
+        self.webhook_config.slack_style = style;
+        self.map_senders(|sender| sender.with_slack_style(style))
+    }
 
-        for entry in entries {
-            let entry = entry?;
-            if entry.file_type()?.is_dir() {
-                let project_path = entry.path();
+    /// Bot name template and icon emoji for Slack webhooks
+    pub fn with_slack_identity(
+        mut self,
+        username: Option<String>,
+        icon_emoji: Option<String>,
+    ) -> Self {
+        self.webhook_config.slack_username = username.clone();
+        self.webhook_config.slack_icon_emoji = icon_emoji.clone();
+        self.map_senders(|sender| sender.with_slack_identity(username.clone(), icon_emoji.clone()))
+    }
 
-                // Find the most recently modified JSONL file in the project
-                if let Ok(files) = fs::read_dir(&project_path) {
-                    let latest_jsonl_time = files
-                        .filter_map(|f| f.ok())
-                        .filter(|f| f.path().extension().and_then(|s| s.to_str()) == Some("jsonl"))
-                        .filter_map(|f| f.metadata().ok().and_then(|m| m.modified().ok()))
-                        .max();
+    /// Put fixed text before and after every webhook message
+    pub fn with_webhook_text_wrapping(
+        mut self,
+        prefix: Option<String>,
+        suffix: Option<String>,
+    ) -> Self {
+        self.webhook_config.prefix = prefix.clone();
+        self.webhook_config.suffix = suffix.clone();
+        self.map_senders(|sender| sender.with_text_wrapping(prefix.clone(), suffix.clone()))
+    }
 
-                    if let Some(modified) = latest_jsonl_time {
-                        if latest_project.is_none() || modified > latest_project.as_ref().unwrap().1
-                        {
-                            latest_project = Some((project_path, modified));
-                        }
-                    }
-                }
-            }
-        }
+    /// Embed each message's original log entry in generic webhook payloads
+    pub fn with_webhook_include_raw(mut self, include: bool) -> Self {
+        self.parser = self.parser.with_raw_entries(include);
+        self.webhook_config.include_raw = include;
+        self.map_senders(|sender| sender.with_include_raw(include))
+    }
 
-        latest_project
-            .map(|(path, _)| path)
-            .context("No projects found")
+    /// Pick which messages webhooks receive with a preset
+    pub fn with_webhook_preset(mut self, preset: Option<WebhookPreset>) -> Self {
+        self.webhook_config.preset = preset;
+        self.map_senders(|sender| sender.with_preset(preset))
     }
 
-    /// Monitor a specific project
-    pub async fn watch_project(&mut self, project_path: &Path) -> Result<()> {
-        let (tx, rx) = mpsc::channel();
-        let mut watcher = notify::recommended_watcher(tx)?;
+    /// Only forward these roles to webhooks; stdout is unaffected
+    pub fn with_webhook_roles(mut self, roles: Option<Vec<MessageRole>>) -> Self {
+        self.webhook_config.roles = roles.clone();
+        self.map_senders(|sender| sender.with_roles(roles.clone()))
+    }
 
-        watcher.watch(project_path, RecursiveMode::Recursive)?;
+    /// Show only open items of TodoWrite checklists posted to Slack
+    pub fn with_todo_hide_completed(mut self, hide: bool) -> Self {
+        self.webhook_config.hide_completed_todos = hide;
+        self.map_senders(|sender| sender.with_hide_completed_todos(hide))
+    }
 
-        // Check existing files if include_existing is enabled
-        if self.include_existing {
-            self.process_existing_files(project_path).await?;
+    /// Send any batched webhook payloads
+    async fn flush_webhooks(&self) {
+        let senders: Vec<&WebhookSender> = self
+            .webhook_sender
+            .iter()
+            .chain(self.webhook_routes.iter().map(|(_, sender)| sender))
+            .filter(|sender| sender.is_batching())
+            .collect();
+        if senders.is_empty() {
+            return;
+        }
+        if let Some(ref queue) = self.webhook_queue {
+            queue
+                .push(WebhookJob::Flush(senders.into_iter().cloned().collect()))
+                .await;
+            return;
+        }
+        for sender in senders {
+            flush_batch(sender, self.status.as_deref()).await;
         }
+    }
 
-        println!("Started monitoring project {project_path:?}. Press Ctrl+C to exit.");
+    /// The webhook a message should be sent to, if any
+    fn webhook_for(&self, message: &LogMessage) -> Option<&WebhookSender> {
+        self.webhook_routes
+            .iter()
+            .find(|(route, _)| route.matches(message))
+            .map(|(_, sender)| sender)
+            .or(self.webhook_sender.as_ref())
+    }
 
-        loop {
-            match rx.recv() {
-                Ok(Ok(event)) => {
-                    if let Err(e) = self.handle_file_event(event).await {
-                        eprintln!("Error processing file event: {e}");
-                    }
-                }
-                Ok(Err(e)) => eprintln!("File watching error: {e}"),
-                Err(e) => {
-                    eprintln!("Channel receive error: {e}");
-                    break;
-                }
-            }
-        }
+    pub fn with_include_existing(mut self, include_existing: bool) -> Self {
+        self.include_existing = include_existing;
+        self
+    }
 
-        Ok(())
+    /// Include existing messages, but only those at or after `anchor`
+    pub fn with_include_existing_from(mut self, anchor: Option<DateTime<Utc>>) -> Self {
+        self.existing_from = anchor;
+        self
     }
 
-    /// Monitor the latest project
-    pub async fn watch_latest(&mut self) -> Result<()> {
-        let latest = self.get_latest_project().await?;
-        self.watch_project(&latest).await
+    /// Read existing files from the beginning or from the end, instead of
+    /// filtering them by the time watching started
+    pub fn with_start_position(mut self, position: Option<StartPosition>) -> Self {
+        self.start_position = position;
+        self
     }
 
-    /// Monitor all projects
-    pub async fn watch_all(&self) -> Result<()> {
-        let (tx, mut rx) = tokio_mpsc::channel(100);
-        let entries = fs::read_dir(&self.claude_dir)?;
-
-        for entry in entries {
-            let entry = entry?;
-            if entry.file_type()?.is_dir() {
-                let project_path = entry.path();
-                let tx_clone = tx.clone();
-
-                tokio::spawn(async move {
-                    let mut watcher = LogWatcher::new();
-                    if let Err(e) = watcher.watch_project(&project_path).await {
-                        let _ = tx_clone
-                            .send(format!("Error in project {project_path:?}: {e}"))
-                            .await;
-                    }
-                });
-            }
-        }
+    /// Emit only messages after the one with `uuid`, which must exist in
+    /// one of the project's session files
+    pub fn with_after_uuid(mut self, uuid: Option<String>) -> Self {
+        self.after_uuid = uuid;
+        self
+    }
+
+    /// Also emit the `with_after_uuid` message itself
+    pub fn with_after_uuid_inclusive(mut self, inclusive: bool) -> Self {
+        self.after_uuid_inclusive = inclusive;
+        self
+    }
 
-        // Receive error messages on main thread
-        while let Some(error) = rx.recv().await {
-            eprintln!("{error}");
+    /// Whether a message predates what this watcher should emit
+    fn is_before_cutoff(&self, message: &LogMessage) -> bool {
+        if self.include_existing || self.start_position.is_some() || self.after_uuid.is_some() {
+            return false;
         }
+        message.timestamp < self.existing_from.unwrap_or(self.startup_time)
+    }
 
-        Ok(())
+    /// Replay only the last `count` existing messages before streaming
+    pub fn with_tail(mut self, count: Option<usize>) -> Self {
+        self.tail = count;
+        self
     }
 
-    /// Process existing files
-    async fn process_existing_files(&mut self, project_path: &Path) -> Result<()> {
-        let entries = fs::read_dir(project_path)?;
+    /// Keep tailing a session after it is renamed within the watched directory
+    pub fn with_follow_rename(mut self, follow: bool) -> Self {
+        self.follow_rename = follow;
+        self
+    }
 
-        for entry in entries {
-            let entry = entry?;
-            if entry.path().extension().and_then(|s| s.to_str()) == Some("jsonl") {
-                if let Err(e) = self.process_jsonl_file(&entry.path()).await {
-                    eprintln!("Error processing existing file {:?}: {}", entry.path(), e);
-                }
-            }
-        }
+    /// Tail only the most recently written session, switching to a newer
+    /// one as soon as it is created or modified
+    pub fn with_follow_latest(mut self, follow: bool) -> Self {
+        self.follow_latest = follow;
+        self
+    }
 
-        Ok(())
+    /// Return from `watch_*` after `timeout` without an emitted message.
+    /// The timer starts once any existing messages have been replayed.
+    pub fn with_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.idle_timeout = timeout;
+        self
     }
 
-    /// Handle file events
-    async fn handle_file_event(&mut self, event: Event) -> Result<()> {
-        match event.kind {
-            EventKind::Create(CreateKind::File) | EventKind::Modify(_) => {
-                for path in event.paths {
-                    if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-                        // Wait briefly for file to be completely written
-                        sleep(Duration::from_millis(100)).await;
-                        self.process_jsonl_file(&path).await?;
-                    }
-                }
-            }
-            _ => {}
-        }
-        Ok(())
+    /// Post a heartbeat to the webhook after `interval` without messages
+    pub fn with_webhook_heartbeat(mut self, interval: Option<Duration>) -> Self {
+        self.heartbeat_interval = interval;
+        self
     }
 
-    /// Process JSONL file
-    async fn process_jsonl_file(&mut self, path: &Path) -> Result<()> {
-        let messages = self.parser.parse_file(path)?;
+    /// Skip messages whose uuid was among the last `size` emitted (0 disables)
+    pub fn with_dedup_cache(mut self, size: usize) -> Self {
+        self.seen_uuids = (size > 0).then(|| Arc::new(Mutex::new(SeenUuids::new(size))));
+        self
+    }
 
-        for message in messages {
-            // Skip existing messages if include_existing is false
-            if !self.include_existing && message.timestamp < self.startup_time {
-                continue;
-            }
+    /// Print a session header whenever output switches to another session
+    pub fn with_group_sessions(mut self, group: bool) -> Self {
+        self.last_session = group.then(|| Arc::new(Mutex::new(None)));
+        self
+    }
 
-            let formatted = self.formatter.format_message(&message)?;
-            if !formatted.trim().is_empty() {
-                // Send to webhook if configured and get result
-                let webhook_status = if let Some(ref webhook) = self.webhook_sender {
-                    match webhook.send_message(&message, &formatted).await {
-                        Ok(WebhookResult::Sent) => "",
-                        Ok(WebhookResult::Skipped) => " [webhook: skipped]",
-                        Err(e) => {
-                            eprintln!("Failed to send webhook: {e}");
-                            " [webhook: failed]"
-                        }
-                    }
-                } else {
-                    ""
-                };
+    /// Only watch projects whose decoded path matches `pattern` in `watch_all`.
+    /// Patterns that are not valid regexes are matched as plain substrings.
+    pub fn with_project_name_filter(mut self, pattern: Option<&str>) -> Self {
+        self.project_filter = pattern.map(|pattern| {
+            Regex::new(pattern).unwrap_or_else(|_| {
+                Regex::new(&regex::escape(pattern)).expect("escaped pattern is a valid regex")
+            })
+        });
+        self
+    }
 
-                println!("{formatted}{webhook_status}");
-            }
-        }
+    /// Watch at most `max` projects in `watch_all`, preferring the most
+    /// recently active ones
+    pub fn with_max_sessions(mut self, max: Option<usize>) -> Self {
+        self.max_sessions = max;
+        self
+    }
 
-        Ok(())
+    /// Leave out projects whose newest session was last modified more than
+    /// `window` ago, both in `watch_all` and in `list_projects`
+    pub fn with_active_within(mut self, window: Option<Duration>) -> Self {
+        self.active_within = window;
+        self
+    }
+
+    /// Prefix each emitted message with an incrementing sequence number
+    pub fn with_numbering(mut self, number: bool) -> Self {
+        self.sequence = number.then(|| Arc::new(AtomicU64::new(0)));
+        self
+    }
+
+    /// Print at most `rate` messages per second, e.g. while replaying a
+    /// backlog. Webhooks are handed their messages without waiting.
+    pub fn with_max_rate(mut self, rate: Option<u32>) -> Self {
+        self.pacer = rate
+            .filter(|&rate| rate > 0)
+            .map(|rate| Arc::new(Mutex::new(Pacer::new(rate))));
+        self
+    }
+
+    /// Print messages to stdout (the default); webhooks, events and
+    /// diagnostics on stderr are unaffected
+    pub fn with_stdout(mut self, stdout: bool) -> Self {
+        self.stdout = stdout;
+        self
+    }
+
+    /// Print messages to `writer` instead of stdout
+    pub fn with_writer(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.writer = Arc::new(Mutex::new(writer));
+        self
+    }
+
+    /// Print the log lines of new messages unchanged instead of formatting
+    /// them. Webhooks and other outputs receive nothing.
+    pub fn with_jsonl_passthrough(mut self, passthrough: bool) -> Self {
+        self.parser = self.parser.with_raw_lines(passthrough);
+        self.jsonl_passthrough = passthrough;
+        self
+    }
+
+    /// Let an external program format stdout lines; the built-in format is
+    /// printed whenever it fails
+    pub fn with_exec_formatter(mut self, exec: Option<ExecFormatter>) -> Self {
+        self.exec_formatter = exec.map(|exec| Arc::new(tokio::sync::Mutex::new(exec)));
+        self
+    }
+
+    /// Publish every emitted message to events socket clients
+    #[cfg(unix)]
+    pub fn with_events(mut self, publisher: crate::events::EventPublisher) -> Self {
+        self.events = Some(publisher);
+        self
+    }
+
+    /// Export a span per emitted message and an event per parse failure
+    #[cfg(feature = "otel")]
+    pub fn with_telemetry(mut self, telemetry: Arc<crate::otel::Telemetry>) -> Self {
+        self.telemetry = Some(telemetry);
+        self
+    }
+
+    /// Keep a status line with running totals below the output. Callers
+    /// should only enable this when stdout is a terminal.
+    pub fn with_status_line(mut self, enabled: bool) -> Self {
+        self.status = enabled.then(|| Arc::new(Mutex::new(StatusCounts::default())));
+        self
+    }
+
+    /// Coalesce consecutive identical lines into one with an `(xN)` suffix
+    pub fn with_collapse_repeats(mut self, collapse: bool) -> Self {
+        self.collapse_repeats = collapse;
+        self
+    }
+
+    /// Recover log lines with JSON5 syntax such as trailing commas
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.parser = self.parser.with_lenient(lenient);
+        self
+    }
+
+    /// Abort watching on the first malformed log line
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.parser = self.parser.with_strict(strict);
+        self.strict = strict;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics.clone());
+        self.map_senders(|sender| sender.with_metrics(metrics.clone()))
+    }
+
+    /// The Claude projects directories, or an error if none could be determined
+    pub fn claude_dirs(&self) -> Result<&[PathBuf]> {
+        if self.claude_dirs.is_empty() {
+            anyhow::bail!(
+                "Cannot locate the Claude projects directory: HOME is not set \
+                 (use --claude-dir or CLAUDE_LOGGER_DIR)"
+            );
+        }
+        Ok(&self.claude_dirs)
+    }
+
+    /// Fail on options that cannot be honored together, such as a start
+    /// position alongside a time cutoff
+    pub fn validate(&self) -> Result<()> {
+        self.formatter.validate()?;
+        if !self.stdout
+            && self.sink.is_none()
+            && self.webhook_sender.is_none()
+            && self.webhook_routes.is_empty()
+        {
+            anyhow::bail!(
+                "Stdout is disabled but no webhook is configured; messages would go nowhere"
+            );
+        }
+        let cutoff = self.include_existing || self.existing_from.is_some();
+        if self.start_position.is_some() && (cutoff || self.tail.is_some()) {
+            anyhow::bail!(
+                "A start position cannot be combined with including existing messages or a tail"
+            );
+        }
+        if self.after_uuid.is_some()
+            && (cutoff || self.tail.is_some() || self.start_position.is_some())
+        {
+            anyhow::bail!(
+                "Resuming after a uuid cannot be combined with including existing messages, a tail or a start position"
+            );
+        }
+        if self.jsonl_passthrough
+            && (self.tail.is_some()
+                || self.sequence.is_some()
+                || self.webhook_sender.is_some()
+                || !self.webhook_routes.is_empty())
+        {
+            anyhow::bail!(
+                "JSONL passthrough cannot be combined with a tail, numbering or a webhook"
+            );
+        }
+        Ok(())
+    }
+
+    /// List available projects
+    pub async fn list_projects(
+        &self,
+        sort: ProjectSort,
+        details: bool,
+        json: bool,
+        decode_paths: bool,
+    ) -> Result<()> {
+        let claude_dirs = self.claude_dirs()?;
+        let mut projects = Vec::new();
+        for claude_dir in claude_dirs {
+            projects.extend(collect_projects(claude_dir)?);
+        }
+        projects.retain(|project| self.is_active(project.last_modified.map(SystemTime::from)));
+        sort_projects(&mut projects, sort);
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&projects)?);
+            return Ok(());
+        }
+
+        let color = std::io::stdout().is_terminal();
+        println!("Available projects:");
+        for project in &projects {
+            let mut name = if color {
+                format!("\x1b[1m{}\x1b[0m", project.name)
+            } else {
+                format!("{:?}", project.name)
+            };
+            if decode_paths {
+                name.push_str(&format!(" -> {}", project.decoded_path));
+            }
+            if claude_dirs.len() > 1 {
+                name.push_str(&format!(" [{}]", project.root.display()));
+            }
+
+            if details {
+                let last_modified = project
+                    .last_modified
+                    .map(|t| {
+                        t.with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string()
+                    })
+                    .unwrap_or_else(|| "never".to_string());
+                println!(
+                    "  {name} ({} sessions, {} messages, last modified {last_modified})",
+                    project.sessions, project.messages
+                );
+            } else {
+                println!("  {name} ({} sessions)", project.sessions);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the latest project across all Claude directories
+    pub async fn get_latest_project(&self) -> Result<PathBuf> {
+        let mut latest_project: Option<(PathBuf, SystemTime)> = None;
+
+        for claude_dir in self.claude_dirs()? {
+            let entries =
+                fs::read_dir(claude_dir).context("Claude projects directory not found")?;
+
+            for entry in entries {
+                let entry = entry?;
+                if is_dir(&entry) {
+                    let project_path = entry.path();
+
+                    // Find the most recently modified JSONL file in the project
+                    if let Some(modified) = latest_session_modified(&project_path) {
+                        if latest_project.is_none() || modified > latest_project.as_ref().unwrap().1
+                        {
+                            latest_project = Some((project_path, modified));
+                        }
+                    }
+                }
+            }
+        }
+
+        latest_project
+            .map(|(path, _)| path)
+            .context("No projects found")
+    }
+
+    /// Watch a project and yield its messages instead of printing them.
+    /// Filtering, dedup and webhooks work as for `watch_project`. The stream
+    /// ends when watching stops, e.g. on the idle timeout; once it is
+    /// dropped, watching stops at the next file event.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn stream(mut self, project_path: impl Into<PathBuf>) -> impl Stream<Item = LogMessage> {
+        let (tx, rx) = tokio_mpsc::channel(STREAM_BUFFER);
+        self.sink = Some(tx);
+        let project_path = project_path.into();
+        tokio::spawn(async move {
+            if let Err(e) = self.watch_project(&project_path).await {
+                error!("Stopped streaming {project_path:?}: {e:#}");
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+
+    /// Monitor a specific project
+    pub async fn watch_project(&mut self, project_path: &Path) -> Result<()> {
+        let worker = self.start_webhook_worker();
+        let result = self.monitor_project(project_path).await;
+        if self.flush_repeats().await > 0 {
+            self.flush_webhooks().await;
+        }
+        if let Some((queue, worker)) = worker {
+            // Deliver what is still queued before returning
+            queue.close();
+            let _ = worker.await;
+            self.webhook_queue = None;
+        }
+        result
+    }
+
+    /// Move webhook delivery to its own task so a slow endpoint does not
+    /// hold up stdout. Does nothing if a worker, e.g. the one `watch_all`
+    /// shares between projects, is already set.
+    fn start_webhook_worker(&mut self) -> Option<(WebhookQueue, JoinHandle<()>)> {
+        if self.webhook_queue.is_some()
+            || (self.webhook_sender.is_none() && self.webhook_routes.is_empty())
+        {
+            return None;
+        }
+        let queue = WebhookQueue::new(WEBHOOK_QUEUE_CAPACITY, self.webhook_overflow);
+        let status = self.status.clone();
+        #[cfg(feature = "otel")]
+        let telemetry = self.telemetry.clone();
+        #[cfg_attr(not(feature = "otel"), allow(unused_variables))]
+        let on_result = move |message: &LogMessage, result: &Result<WebhookResult>| {
+            if let Some(ref status) = status {
+                status.lock().unwrap().record_webhook(result);
+            }
+            #[cfg(feature = "otel")]
+            if let Some(ref telemetry) = telemetry {
+                telemetry.record_message(message, Some(result));
+            }
+        };
+        let worker = tokio::spawn(deliver_webhooks(
+            queue.clone(),
+            self.status.clone(),
+            self.webhook_sent.clone(),
+            on_result,
+        ));
+        self.webhook_queue = Some(queue.clone());
+        Some((queue, worker))
+    }
+
+    async fn monitor_project(&mut self, project_path: &Path) -> Result<()> {
+        let (watcher, rx) = create_watcher(project_path)?;
+        self.watch_root = Some(project_path.to_path_buf());
+        if self.follow_latest {
+            self.active_session = newest_session(project_path);
+        }
+
+        self.process_startup(project_path).await?;
+        info!("Started monitoring project {project_path:?}. Press Ctrl+C to exit.");
+
+        self.event_loop(project_path, watcher, rx).await
+    }
+
+    /// Deal with the sessions that exist before watching starts: replay
+    /// them, tail them, or skip to their end
+    async fn process_startup(&mut self, project_path: &Path) -> Result<()> {
+        if let Some(count) = self.tail {
+            for (message, formatted) in self.tail_messages(project_path, count)? {
+                self.emit_message(&message, &formatted).await;
+            }
+            self.flush_webhooks().await;
+            return Ok(());
+        }
+
+        if self.seek_startup(project_path)? {
+            self.process_existing_files(project_path).await?;
+        }
+        Ok(())
+    }
+
+    /// Move past what `after_uuid` or the start position skip in existing
+    /// sessions. Returns whether the rest of them should be read now.
+    fn seek_startup(&mut self, project_path: &Path) -> Result<bool> {
+        if let Some(uuid) = self.after_uuid.clone() {
+            let mut found = false;
+            for path in session_files(project_path) {
+                if !found
+                    && self
+                        .parser
+                        .seek_to_uuid(&path, &uuid, self.after_uuid_inclusive)?
+                {
+                    found = true;
+                } else {
+                    self.parser.skip_to_end(&path)?;
+                }
+            }
+            if !found {
+                anyhow::bail!("No message with uuid {uuid} in {project_path:?}");
+            }
+            return Ok(true);
+        }
+
+        match self.start_position {
+            Some(StartPosition::End) => {
+                for path in session_files(project_path) {
+                    self.parser.skip_to_end(&path)?;
+                }
+                Ok(false)
+            }
+            Some(StartPosition::Beginning) => Ok(true),
+            None => Ok(self.include_existing || self.existing_from.is_some()),
+        }
+    }
+
+    /// Handle watch events until the channel closes or, with an idle
+    /// timeout, until no message has been emitted for that long
+    async fn event_loop(
+        &mut self,
+        project_path: &Path,
+        mut _watcher: RecommendedWatcher,
+        mut rx: WatchReceiver,
+    ) -> Result<()> {
+        let mut last_activity = Instant::now();
+
+        loop {
+            if self.sink.as_ref().is_some_and(|sink| sink.is_closed()) {
+                debug!("Message stream dropped; stopping watch of {project_path:?}");
+                return Ok(());
+            }
+
+            let idle_left = self
+                .idle_timeout
+                .map(|timeout| timeout.saturating_sub(last_activity.elapsed()));
+            let heartbeat_left = self
+                .heartbeat_interval
+                .filter(|_| self.webhook_sender.is_some())
+                .map(|interval| {
+                    interval.saturating_sub(self.webhook_sent.lock().unwrap().elapsed())
+                });
+            let repeat_left = self
+                .pending_repeat
+                .as_ref()
+                .map(|pending| REPEAT_QUIET_PERIOD.saturating_sub(pending.last_seen.elapsed()));
+
+            let wait = [idle_left, heartbeat_left, repeat_left]
+                .into_iter()
+                .flatten()
+                .min();
+            let received = match wait {
+                Some(wait) => match timeout(wait, rx.recv()).await {
+                    Ok(received) => received,
+                    Err(_) if repeat_left == Some(wait) => {
+                        if self.flush_repeats().await > 0 {
+                            self.flush_webhooks().await;
+                            last_activity = Instant::now();
+                        }
+                        continue;
+                    }
+                    Err(_) if idle_left == Some(wait) => {
+                        info!(
+                            "No new messages for {}s in {project_path:?}, exiting.",
+                            self.idle_timeout.unwrap_or_default().as_secs()
+                        );
+                        return Ok(());
+                    }
+                    Err(_) => {
+                        self.send_heartbeat(project_path).await;
+                        continue;
+                    }
+                },
+                None => rx.recv().await,
+            };
+
+            match received {
+                Some(Ok(event))
+                    if matches!(event.kind, EventKind::Remove(_)) && !project_path.exists() =>
+                {
+                    warn!("Project directory {project_path:?} was removed; stopping watch");
+                    return Ok(());
+                }
+                Some(Ok(event)) => match self.handle_file_event(event).await {
+                    Ok(0) => {}
+                    Ok(_) => last_activity = Instant::now(),
+                    Err(e) => {
+                        if self.strict {
+                            return Err(e);
+                        }
+                        error!("Error processing file event: {e}");
+                    }
+                },
+                Some(Err(e)) if !project_path.exists() => {
+                    warn!("Project directory {project_path:?} is gone ({e}); stopping watch");
+                    return Ok(());
+                }
+                Some(Err(e)) if is_fatal_watch_error(&e) => {
+                    warn!("File watching error: {e}; re-creating watcher");
+                    (_watcher, rx) = self.reconnect(project_path).await?;
+                }
+                Some(Err(e)) => warn!("File watching error: {e}"),
+                None => {
+                    error!("File watcher stopped sending events");
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tell the default webhook this project is still being watched,
+    /// unless it got a message or heartbeat within the interval, e.g. from
+    /// another project's watcher
+    async fn send_heartbeat(&self, project_path: &Path) {
+        let (Some(sender), Some(interval)) = (&self.webhook_sender, self.heartbeat_interval) else {
+            return;
+        };
+        {
+            let mut sent = self.webhook_sent.lock().unwrap();
+            if sent.elapsed() < interval {
+                return;
+            }
+            *sent = Instant::now();
+        }
+        let project = self.heartbeat_project.clone().unwrap_or_else(|| {
+            project_path
+                .file_name()
+                .map(|name| decode_project_path(&name.to_string_lossy()))
+                .unwrap_or_default()
+        });
+        match self.webhook_queue {
+            Some(ref queue) => {
+                queue
+                    .push(WebhookJob::Heartbeat {
+                        sender: Box::new(sender.clone()),
+                        project,
+                    })
+                    .await
+            }
+            None => {
+                if let Err(e) = sender.send_heartbeat(&project).await {
+                    warn!("Failed to send webhook heartbeat: {e}");
+                }
+            }
+        }
+    }
+
+    /// Re-create the watcher with exponential backoff, then pick up anything
+    /// written to the project's sessions while the watch was down
+    async fn reconnect(
+        &mut self,
+        project_path: &Path,
+    ) -> Result<(RecommendedWatcher, WatchReceiver)> {
+        let mut delay = RECONNECT_INITIAL_DELAY;
+        for attempt in 1..=RECONNECT_ATTEMPTS {
+            sleep(delay).await;
+            match create_watcher(project_path) {
+                Ok(watch) => {
+                    info!("Re-established file watcher for {project_path:?}");
+                    for path in session_files(project_path) {
+                        if let Err(e) = self.process_jsonl_file(&path).await {
+                            error!("Error catching up on {path:?}: {e}");
+                        }
+                    }
+                    return Ok(watch);
+                }
+                Err(e) => {
+                    warn!("Reconnect attempt {attempt}/{RECONNECT_ATTEMPTS} failed: {e}")
+                }
+            }
+            delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+        }
+        anyhow::bail!("Could not re-establish file watcher for {project_path:?}")
+    }
+
+    /// Map a real working directory (e.g. `/home/me/repos/foo`) to its
+    /// project directory under the Claude dir, falling back to `path` itself
+    pub fn resolve_project_path(&self, path: &Path) -> PathBuf {
+        let Ok(claude_dirs) = self.claude_dirs() else {
+            return path.to_path_buf();
+        };
+        let absolute = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let encoded = encode_project_path(&absolute);
+        claude_dirs
+            .iter()
+            .map(|claude_dir| claude_dir.join(&encoded))
+            .find(|candidate| candidate.is_dir())
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+
+    /// Monitor the latest project
+    pub async fn watch_latest(&mut self) -> Result<()> {
+        let latest = self.get_latest_project().await?;
+        self.watch_project(&latest).await
+    }
+
+    /// Monitor all projects
+    pub async fn watch_all(&self) -> Result<()> {
+        let (tx, mut rx) = tokio_mpsc::channel(100);
+        // One webhook worker delivers for every project, in order
+        let mut shared = self.clone();
+        let worker = shared.start_webhook_worker();
+        // Heartbeats stand for every project
+        shared.heartbeat_project = Some("all projects".to_string());
+
+        for project_path in self.projects_to_watch()? {
+            let tx_clone = tx.clone();
+
+            // Each project gets its own copy of the configuration; the
+            // dedup cache, metrics and webhook queue stay shared
+            let mut watcher = shared.clone();
+            tokio::spawn(async move {
+                if let Err(e) = watcher.watch_project(&project_path).await {
+                    let _ = tx_clone
+                        .send(format!("Error in project {project_path:?}: {e}"))
+                        .await;
+                }
+            });
+        }
+
+        // Receive error messages on main thread until every project is done
+        drop(tx);
+        while let Some(message) = rx.recv().await {
+            error!("{message}");
+        }
+
+        if let Some((queue, worker)) = worker {
+            queue.close();
+            let _ = worker.await;
+        }
+        Ok(())
+    }
+
+    /// Project directories `watch_all` should spawn a watcher for
+    pub fn projects_to_watch(&self) -> Result<Vec<PathBuf>> {
+        let mut projects = Vec::new();
+        for claude_dir in self.claude_dirs()? {
+            for entry in fs::read_dir(claude_dir)? {
+                let entry = entry?;
+                if !is_dir(&entry) {
+                    continue;
+                }
+                if let Some(ref filter) = self.project_filter {
+                    let decoded = decode_project_path(&entry.file_name().to_string_lossy());
+                    if !filter.is_match(&decoded) {
+                        continue;
+                    }
+                }
+                if !self.is_active(latest_session_modified(&entry.path())) {
+                    continue;
+                }
+                projects.push(entry.path());
+            }
+        }
+
+        if let Some(max) = self.max_sessions.filter(|&max| projects.len() > max) {
+            projects.sort_by_key(|p| std::cmp::Reverse(latest_session_modified(p)));
+            let skipped: Vec<String> = projects
+                .split_off(max)
+                .iter()
+                .filter_map(|p| p.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect();
+            warn!(
+                "Watching the {max} most recently active projects; skipping {}: {}",
+                skipped.len(),
+                skipped.join(", ")
+            );
+        }
+        Ok(projects)
+    }
+
+    /// Whether a project last modified at `modified` falls within the
+    /// activity window, if one is set
+    fn is_active(&self, modified: Option<SystemTime>) -> bool {
+        let Some(window) = self.active_within else {
+            return true;
+        };
+        // Projects without sessions or a readable mtime are never active;
+        // a modification time in the future counts as active
+        modified.is_some_and(|modified| !modified.elapsed().is_ok_and(|age| age > window))
+    }
+
+    /// Re-emit a finished session, pausing between messages for their
+    /// original gap divided by `speed` (0 emits everything at once)
+    pub async fn replay(&mut self, session_file: &Path, speed: f64) -> Result<usize> {
+        let messages = self.parse_messages(session_file)?;
+        let mut previous: Option<DateTime<Utc>> = None;
+        let mut emitted = 0;
+
+        for message in messages {
+            if let Some(pause) = previous.and_then(|p| replay_pause(p, message.timestamp, speed)) {
+                sleep(pause).await;
+            }
+            previous = Some(message.timestamp);
+
+            match self.formatter.format_visible(&message)? {
+                Some(formatted) => emitted += self.output(message, formatted).await?,
+                None => self.forward_short(&message).await?,
+            }
+        }
+        emitted += self.flush_repeats().await;
+        self.flush_webhooks().await;
+
+        Ok(emitted)
+    }
+
+    /// Process existing files, including those in subdirectories, returning
+    /// the number of messages emitted
+    async fn process_existing_files(&mut self, project_path: &Path) -> Result<usize> {
+        let mut emitted = 0;
+        for path in session_files(project_path) {
+            match self.process_jsonl_file(&path).await {
+                Ok(count) => emitted += count,
+                Err(e) if self.strict => return Err(e),
+                Err(e) => error!("Error processing existing file {path:?}: {e}"),
+            }
+        }
+
+        Ok(emitted)
+    }
+
+    /// Handle file events, returning the number of messages emitted
+    async fn handle_file_event(&mut self, event: Event) -> Result<usize> {
+        debug!(kind = ?event.kind, paths = ?event.paths, "File event");
+        let mut emitted = 0;
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both))
+                if self.follow_rename && event.paths.len() == 2 =>
+            {
+                let (from, to) = (&event.paths[0], &event.paths[1]);
+                let inside_root = self
+                    .watch_root
+                    .as_ref()
+                    .is_some_and(|root| to.starts_with(root));
+                if self.active_session.as_ref() == Some(from) {
+                    self.active_session = Some(to.clone());
+                }
+                if inside_root && self.parser.rename(from, to) {
+                    info!("Following renamed session {from:?} -> {to:?}");
+                    emitted += self.process_jsonl_file(to).await?;
+                }
+            }
+            EventKind::Create(CreateKind::File) | EventKind::Modify(_) => {
+                for path in event.paths {
+                    let is_session = path.extension().and_then(|s| s.to_str()) == Some("jsonl")
+                        || self.parser.is_tracking(&path);
+                    if self.follow_latest && is_session && !self.follow_session(&path) {
+                        continue;
+                    }
+                    if is_session && path.exists() {
+                        // Wait briefly for file to be completely written
+                        sleep(Duration::from_millis(100)).await;
+                        emitted += self.process_jsonl_file(&path).await?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(emitted)
+    }
+
+    /// Whether `path` is the active session, switching to it when it is at
+    /// least as recently modified as the current one
+    fn follow_session(&mut self, path: &Path) -> bool {
+        let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+        match &self.active_session {
+            Some(active) if active == path => true,
+            Some(active) if modified(path) < modified(active) => false,
+            active => {
+                if active.is_some() {
+                    info!("Switching to newer session {path:?}");
+                }
+                self.active_session = Some(path.to_path_buf());
+                true
+            }
+        }
+    }
+
+    /// Process JSONL file, returning the number of messages emitted
+    async fn process_jsonl_file(&mut self, path: &Path) -> Result<usize> {
+        let messages = self.parse_messages(path)?;
+        let mut emitted = 0;
+
+        for message in messages {
+            // Skip existing messages unless they were asked for
+            if self.is_before_cutoff(&message) {
+                continue;
+            }
+
+            if self.jsonl_passthrough {
+                emitted += usize::from(self.pass_through(&message));
+                continue;
+            }
+
+            match self.formatter.format_visible(&message)? {
+                Some(formatted) => emitted += self.output(message, formatted).await?,
+                None => self.forward_short(&message).await?,
+            }
+        }
+        // A held-back line waits for repeats in later appends; the event
+        // loop prints it once they stop coming
+        self.flush_webhooks().await;
+
+        Ok(emitted)
+    }
+
+    /// Parse new lines from a file, reporting malformed lines
+    fn parse_messages(&mut self, path: &Path) -> Result<Vec<LogMessage>> {
+        let mut messages = match self.parser.parse_file(path) {
+            Ok(messages) => messages,
+            Err(e) => {
+                error!("Failed to parse {path:?}: {e:#}");
+                if let Some(ref metrics) = self.metrics {
+                    metrics.record_parse_errors(1);
+                }
+                #[cfg(feature = "otel")]
+                if let Some(ref telemetry) = self.telemetry {
+                    telemetry.record_parse_errors(path, 1, Some(&format!("{e:#}")));
+                }
+                return Err(e);
+            }
+        };
+
+        let malformed = self.parser.last_stats().malformed;
+        if malformed > 0 {
+            warn!("{malformed} malformed lines skipped in {path:?}");
+            if let Some(ref metrics) = self.metrics {
+                metrics.record_parse_errors(malformed as u64);
+            }
+            #[cfg(feature = "otel")]
+            if let Some(ref telemetry) = self.telemetry {
+                telemetry.record_parse_errors(path, malformed as u64, None);
+            }
+        }
+
+        if let Some(ref mut tasks) = self.tasks {
+            for message in &mut messages {
+                tasks.resolve(message);
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Number of messages in a project that would be emitted under the
+    /// current filters and startup options, without printing or sending
+    /// anything
+    pub fn count_messages(&mut self, project_path: &Path) -> Result<usize> {
+        if let Some(count) = self.tail {
+            let messages = self.tail_messages(project_path, count)?;
+            return Ok(messages
+                .iter()
+                .filter(|(message, _)| self.is_new(message))
+                .count());
+        }
+
+        self.seek_startup(project_path)?;
+        let mut count = 0;
+        for path in session_files(project_path) {
+            for message in self.parse_messages(&path)? {
+                if self.is_before_cutoff(&message)
+                    || self.formatter.format_visible(&message)?.is_none()
+                {
+                    continue;
+                }
+                if self.is_new(&message) {
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Parse every session in the project up to EOF and keep the last
+    /// `count` messages that would actually be displayed
+    fn tail_messages(
+        &mut self,
+        project_path: &Path,
+        count: usize,
+    ) -> Result<Vec<(LogMessage, String)>> {
+        let mut messages = Vec::new();
+        for path in session_files(project_path) {
+            for message in self.parse_messages(&path)? {
+                if let Some(formatted) = self.formatter.format_visible(&message)? {
+                    messages.push((message, formatted));
+                }
+            }
+        }
+
+        messages.sort_by_key(|(message, _)| message.timestamp);
+        let skip = messages.len().saturating_sub(count);
+        Ok(messages.split_off(skip))
+    }
+
+    /// Emit a line, or hold it back if it may be repeated.
+    /// Returns the number of lines actually emitted.
+    async fn output(&mut self, message: LogMessage, formatted: String) -> Result<usize> {
+        if !self.collapse_repeats {
+            return Ok(usize::from(self.emit_message(&message, &formatted).await));
+        }
+
+        let body = self.formatter.format_body(&message)?;
+        if let Some(ref mut pending) = self.pending_repeat {
+            if pending.body == body {
+                pending.count += 1;
+                pending.last_seen = Instant::now();
+                return Ok(0);
+            }
+        }
+
+        let emitted = self.flush_repeats().await;
+        self.pending_repeat = Some(PendingRepeat {
+            message,
+            formatted,
+            body,
+            count: 1,
+            last_seen: Instant::now(),
+        });
+        Ok(emitted)
+    }
+
+    /// Emit the held-back line, if any
+    async fn flush_repeats(&mut self) -> usize {
+        let Some(pending) = self.pending_repeat.take() else {
+            return 0;
+        };
+        usize::from(self.emit_message(&pending.message, &pending.text()).await)
+    }
+
+    /// Header to print before `message` if it belongs to a different
+    /// session than the previously emitted one
+    fn session_header(&self, message: &LogMessage) -> Option<String> {
+        let mut last_session = self.last_session.as_ref()?.lock().unwrap();
+        if last_session.as_deref() == Some(message.session_id.as_str()) {
+            return None;
+        }
+
+        *last_session = Some(message.session_id.clone());
+        Some(
+            self.formatter
+                .format_session_header(&message.session_id, &message.project_name),
+        )
+    }
+
+    /// Record `message` as seen, returning false if it already was
+    fn is_new(&self, message: &LogMessage) -> bool {
+        match self.seen_uuids {
+            Some(ref seen) => seen.lock().unwrap().insert(&message.uuid),
+            None => true,
+        }
+    }
+
+    /// Print the message's original log line.
+    /// Returns false if the message was already emitted.
+    fn pass_through(&self, message: &LogMessage) -> bool {
+        let Some(ref line) = message.raw_line else {
+            return false;
+        };
+        if !self.is_new(message) {
+            return false;
+        }
+        write_output(&self.writer, &format!("{line}\n"));
+        true
+    }
+
+    /// Print a formatted message and forward it to the webhook.
+    /// Returns false if the message was already emitted.
+    async fn emit_message(&mut self, message: &LogMessage, formatted: &str) -> bool {
+        if !self.is_new(message) {
+            return false;
+        }
+
+        let print = self.stdout && self.sink.is_none();
+        if print && self.status.is_some() {
+            // Erase the status line so the message takes its place
+            write_output(&self.writer, "\r\x1b[2K");
+        }
+
+        if print {
+            if let Some(header) = self.session_header(message) {
+                write_output(&self.writer, &format!("{header}\n"));
+            }
+        }
+
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_message(&message.role);
+        }
+
+        // Escape codes are for the terminal only
+        let plain = strip_ansi(formatted);
+
+        let webhook_status = self.send_to_webhook(message, &plain).await;
+
+        match self.sink {
+            Some(ref sink) => {
+                let _ = sink.send(message.clone()).await;
+            }
+            None if !self.stdout => {}
+            None => {
+                if let Some(ref pacer) = self.pacer {
+                    let wait = pacer.lock().unwrap().reserve();
+                    if !wait.is_zero() {
+                        sleep(wait).await;
+                    }
+                }
+                let line = self.stdout_line(message, formatted, &plain).await;
+                let line = self.numbered(&line);
+                let line = if message.is_sidechain {
+                    nest_sidechain(&line)
+                } else {
+                    line
+                };
+                write_output(&self.writer, &format!("{line}{webhook_status}\n"));
+            }
+        }
+
+        #[cfg(unix)]
+        if let Some(ref events) = self.events {
+            events.publish(&self.redacted(message), &plain);
+        }
+
+        if let Some(status) = self.status.as_ref().filter(|_| print) {
+            let mut counts = status.lock().unwrap();
+            counts.record(message);
+            let webhook = self.webhook_sender.is_some() || !self.webhook_routes.is_empty();
+            write_output(&self.writer, &counts.render(webhook));
+        }
+        true
+    }
+
+    /// Hand the message to the webhook task, or send it inline and
+    /// describe the result for the end of the message line
+    async fn send_to_webhook(&self, message: &LogMessage, plain: &str) -> &'static str {
+        match (self.webhook_for(message), &self.webhook_queue) {
+            (Some(webhook), Some(queue)) => {
+                queue
+                    .push(WebhookJob::Send(Box::new(QueuedMessage {
+                        sender: webhook.clone(),
+                        message: message.clone(),
+                        formatted: plain.to_string(),
+                    })))
+                    .await;
+                ""
+            }
+            (Some(webhook), None) => {
+                let result = webhook.send_message(message, plain).await;
+                if let Some(ref status) = self.status {
+                    status.lock().unwrap().record_webhook(&result);
+                }
+                #[cfg(feature = "otel")]
+                if let Some(ref telemetry) = self.telemetry {
+                    telemetry.record_message(message, Some(&result));
+                }
+                webhook_status(message, &result)
+            }
+            (None, _) => {
+                #[cfg(feature = "otel")]
+                if let Some(ref telemetry) = self.telemetry {
+                    telemetry.record_message(message, None);
+                }
+                ""
+            }
+        }
+    }
+
+    /// Send a message hidden on stdout only for being short to the
+    /// webhooks, unless the minimum length applies to them too
+    async fn forward_short(&self, message: &LogMessage) -> Result<()> {
+        if self.min_content_len_webhooks || self.webhook_for(message).is_none() {
+            return Ok(());
+        }
+        let Some(formatted) = self.formatter.format_visible_any_length(message)? else {
+            return Ok(());
+        };
+        if self.is_new(message) {
+            self.send_to_webhook(message, &strip_ansi(&formatted)).await;
+        }
+        Ok(())
+    }
+
+    /// The line printed for `message`: its formatted text, or what the
+    /// `--exec-formatter` program makes of it
+    async fn stdout_line(&self, message: &LogMessage, formatted: &str, plain: &str) -> String {
+        let Some(ref exec) = self.exec_formatter else {
+            return formatted.to_string();
+        };
+        let message = self.redacted(message);
+        match exec.lock().await.format(&message, plain).await {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("{e:#}");
+                formatted.to_string()
+            }
+        }
+    }
+
+    /// `message` with its content masked, for outputs that pass the
+    /// content on alongside the formatted line
+    fn redacted<'a>(&self, message: &'a LogMessage) -> Cow<'a, LogMessage> {
+        match self.redactor {
+            Some(ref redactor) => {
+                let mut message = message.clone();
+                message.content = redactor.redact(&message.content).into_owned();
+                Cow::Owned(message)
+            }
+            None => Cow::Borrowed(message),
+        }
+    }
+
+    /// Prefix a line with the next sequence number when numbering is on
+    fn numbered(&self, formatted: &str) -> String {
+        match self.sequence {
+            Some(ref sequence) => {
+                let number = sequence.fetch_add(1, Ordering::Relaxed) + 1;
+                format!("#{number} {formatted}")
+            }
+            None => formatted.to_string(),
+        }
+    }
+}
+
+/// Log a webhook result and describe it for the end of the message line
+fn webhook_status(message: &LogMessage, result: &Result<WebhookResult>) -> &'static str {
+    debug!(uuid = %message.uuid, ?result, "Webhook result");
+    match result {
+        Ok(WebhookResult::Sent | WebhookResult::Queued | WebhookResult::DryRun) => "",
+        Ok(WebhookResult::Skipped) => " [webhook: skipped]",
+        Err(e) if e.is::<RateLimited>() => {
+            warn!(uuid = %message.uuid, "{e}");
+            " [webhook: rate limited]"
+        }
+        Err(e) => {
+            error!(uuid = %message.uuid, "Failed to send webhook: {e}");
+            " [webhook: failed]"
+        }
+    }
+}
+
+/// Webhook task: deliver queued jobs in order until the queue is closed,
+/// passing each message's result to `on_result` and noting in `sent` when
+/// something last went out
+async fn deliver_webhooks(
+    queue: WebhookQueue,
+    status: Option<Arc<Mutex<StatusCounts>>>,
+    sent: Arc<Mutex<Instant>>,
+    on_result: impl Fn(&LogMessage, &Result<WebhookResult>),
+) {
+    while let Some(job) = queue.pop().await {
+        match job {
+            WebhookJob::Send(queued) => {
+                let result = queued
+                    .sender
+                    .send_message(&queued.message, &queued.formatted)
+                    .await;
+                if matches!(result, Ok(WebhookResult::Sent)) {
+                    *sent.lock().unwrap() = Instant::now();
+                }
+                webhook_status(&queued.message, &result);
+                on_result(&queued.message, &result);
+            }
+            WebhookJob::Flush(senders) => {
+                for sender in senders {
+                    if flush_batch(&sender, status.as_deref()).await > 0 {
+                        *sent.lock().unwrap() = Instant::now();
+                    }
+                }
+            }
+            WebhookJob::Heartbeat { sender, project } => {
+                if let Err(e) = sender.send_heartbeat(&project).await {
+                    warn!("Failed to send webhook heartbeat: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Send a sender's batched payloads, reporting the outcome in the log and
+/// the status counts. Returns how many were sent.
+async fn flush_batch(sender: &WebhookSender, status: Option<&Mutex<StatusCounts>>) -> usize {
+    let result = sender.flush().await;
+    if let Err(ref e) = result {
+        error!("Failed to send webhook batch, keeping it for the next flush: {e:#}");
+    }
+    if let Some(status) = status {
+        status.lock().unwrap().record_flush(&result);
+    }
+    result.unwrap_or(0)
+}
+
+/// Parse a `--include-existing-from` anchor: an RFC 3339 timestamp or a
+/// duration such as `1h` or `30m` meaning that long before now
+pub fn parse_time_anchor(s: &str) -> Result<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(s) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    let ago = humantime::parse_duration(s)
+        .with_context(|| format!("Expected an RFC 3339 timestamp or a duration, got {s:?}"))?;
+    Ok(Utc::now() - chrono::Duration::from_std(ago)?)
+}
+
+/// Delay between two replayed messages, scaled down by `speed`
+fn replay_pause(previous: DateTime<Utc>, next: DateTime<Utc>, speed: f64) -> Option<Duration> {
+    if speed <= 0.0 {
+        return None;
+    }
+    let gap = (next - previous).to_std().ok()?;
+    Some(gap.div_f64(speed))
+}
+
+/// Start a recursive watch on `path`
+fn create_watcher(path: &Path) -> Result<(RecommendedWatcher, WatchReceiver)> {
+    // Events are awaited, so waiting for them never ties up a runtime
+    // thread that other tasks, such as webhook delivery, need
+    let (tx, rx) = tokio_mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })?;
+    watcher.watch(path, RecursiveMode::Recursive)?;
+    Ok((watcher, rx))
+}
+
+/// Whether a backend error leaves the watch unusable
+fn is_fatal_watch_error(error: &notify::Error) -> bool {
+    matches!(
+        error.kind,
+        notify::ErrorKind::Io(_)
+            | notify::ErrorKind::PathNotFound
+            | notify::ErrorKind::WatchNotFound
+    )
+}
+
+/// Whether an entry is a directory, following symlinks so relocated
+/// project directories are not skipped
+fn is_dir(entry: &fs::DirEntry) -> bool {
+    fs::metadata(entry.path()).is_ok_and(|meta| meta.is_dir())
+}
+
+/// JSONL session files in a project directory and its subdirectories,
+/// matching the recursive watch, in path order
+pub(crate) fn session_files(project_path: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    collect_session_files(project_path, &mut files);
+    files.sort();
+    files
+}
+
+fn collect_session_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => collect_session_files(&path, files),
+            Ok(_) if path.extension().and_then(|s| s.to_str()) == Some("jsonl") => files.push(path),
+            _ => {}
+        }
+    }
+}
+
+/// Most recently modified session file in a project directory
+fn newest_session(project_path: &Path) -> Option<PathBuf> {
+    session_files(project_path)
+        .into_iter()
+        .filter_map(|p| Some((fs::metadata(&p).and_then(|m| m.modified()).ok()?, p)))
+        .max()
+        .map(|(_, p)| p)
+}
+
+/// Modification time of the newest session file in a project
+fn latest_session_modified(project_path: &Path) -> Option<SystemTime> {
+    session_files(project_path)
+        .iter()
+        .filter_map(|p| fs::metadata(p).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+/// Collect summary information for every project under the claude directory
+pub(crate) fn collect_projects(claude_dir: &Path) -> Result<Vec<ProjectInfo>> {
+    let entries = fs::read_dir(claude_dir).context("Claude projects directory not found")?;
+
+    let mut projects = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !is_dir(&entry) {
+            continue;
+        }
+
+        let project_path = entry.path();
+        let sessions = session_files(&project_path);
+        let mut messages = 0;
+        let mut parse = ParseStats::default();
+        for session in &sessions {
+            let mut parser = LogParser::new();
+            if let Ok(parsed) = parser.parse_file(session) {
+                messages += parsed.len();
+                parse += parser.last_stats();
+            }
+        }
+
+        let name = entry.file_name().to_string_lossy().into_owned();
+        projects.push(ProjectInfo {
+            decoded_path: decode_project_path(&name),
+            name,
+            root: claude_dir.to_path_buf(),
+            sessions: sessions.len(),
+            messages,
+            last_modified: latest_session_modified(&project_path).map(DateTime::<Utc>::from),
+            path: project_path,
+            parse,
+        });
+    }
+
+    Ok(projects)
+}
+
+/// Sort projects in place according to the requested order
+fn sort_projects(projects: &mut [ProjectInfo], sort: ProjectSort) {
+    match sort {
+        ProjectSort::Name => projects.sort_by(|a, b| a.name.cmp(&b.name)),
+        // Most recently active first; projects without sessions go last
+        ProjectSort::Recent => projects.sort_by_key(|p| std::cmp::Reverse(p.last_modified)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhook::tests::CapturedLogs;
+    use std::fs::File;
+    use std::io::Write;
+    use std::time::Duration as StdDuration;
+    use tempfile::TempDir;
+
+    fn write_session(dir: &Path, name: &str, modified: SystemTime) {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join(name);
+        fs::write(
+            &path,
+            r#"{"type":"user","message":{"role":"user","content":"hi"},"timestamp":"2025-01-01T00:00:00Z","sessionId":"s","uuid":"u1"}
+"#,
+        )
+        .unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tail_emits_last_messages() {
+        let root = TempDir::new().unwrap();
+        let lines: String = (0..10)
+            .map(|i| {
+                format!(
+                    r#"{{"type":"user","message":{{"role":"user","content":"message {i}"}},"timestamp":"2025-01-01T00:00:0{i}Z","sessionId":"s","uuid":"u{i}"}}"#
+                ) + "\n"
+            })
+            .collect();
+        fs::write(root.path().join("session.jsonl"), lines).unwrap();
+
+        let mut watcher = LogWatcher::new();
+        let tail = watcher.tail_messages(root.path(), 3).unwrap();
+
+        let contents: Vec<_> = tail.iter().map(|(m, _)| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["message 7", "message 8", "message 9"]);
+
+        // The session has been consumed up to EOF, so live mode starts fresh
+        assert!(watcher.tail_messages(root.path(), 3).unwrap().is_empty());
+    }
+
+    fn message_line(uuid: &str, content: &str) -> String {
+        format!(
+            r#"{{"type":"user","message":{{"role":"user","content":"{content}"}},"timestamp":"2025-01-01T00:00:00Z","sessionId":"s","uuid":"{uuid}"}}"#
+        ) + "\n"
+    }
+
+    #[tokio::test]
+    async fn test_follow_rename_continues_emitting() {
+        let root = TempDir::new().unwrap();
+        let original = root.path().join("session.jsonl");
+        let renamed = root.path().join("renamed.jsonl");
+        fs::write(&original, message_line("u1", "first")).unwrap();
+
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_follow_rename(true);
+        watcher.watch_root = Some(root.path().to_path_buf());
+        assert_eq!(watcher.process_jsonl_file(&original).await.unwrap(), 1);
+
+        fs::rename(&original, &renamed).unwrap();
+        let rename = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(original.clone())
+            .add_path(renamed.clone());
+        assert_eq!(watcher.handle_file_event(rename).await.unwrap(), 0);
+
+        let mut file = File::options().append(true).open(&renamed).unwrap();
+        file.write_all(message_line("u2", "second").as_bytes())
+            .unwrap();
+        let modify = Event::new(EventKind::Modify(ModifyKind::Any)).add_path(renamed);
+        assert_eq!(watcher.handle_file_event(modify).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_follow_latest_switches_to_newer_session() {
+        let root = TempDir::new().unwrap();
+        let old = root.path().join("old.jsonl");
+        fs::write(&old, message_line("u1", "old session")).unwrap();
+
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_follow_latest(true);
+        watcher.active_session = newest_session(root.path());
+        assert_eq!(watcher.active_session.as_ref(), Some(&old));
+
+        // A brand-new session re-points the tail
+        let new = root.path().join("new.jsonl");
+        fs::write(&new, message_line("u2", "new session")).unwrap();
+        let create = Event::new(EventKind::Create(CreateKind::File)).add_path(new.clone());
+        assert_eq!(watcher.handle_file_event(create).await.unwrap(), 1);
+        assert_eq!(watcher.active_session.as_ref(), Some(&new));
+
+        // Stale writes to the old session are ignored
+        append(&old, &message_line("u3", "late write"));
+        let stale = SystemTime::now() - Duration::from_secs(3600);
+        File::options()
+            .append(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(stale)
+            .unwrap();
+        let modify = Event::new(EventKind::Modify(ModifyKind::Any)).add_path(old);
+        assert_eq!(watcher.handle_file_event(modify).await.unwrap(), 0);
+        assert_eq!(watcher.active_session.as_ref(), Some(&new));
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_exits_cleanly() {
+        let root = TempDir::new().unwrap();
+        let mut watcher = LogWatcher::new().with_idle_timeout(Some(Duration::from_millis(200)));
+        let (watch, rx) = create_watcher(root.path()).unwrap();
+
+        let started = Instant::now();
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            watcher.event_loop(root.path(), watch, rx),
+        )
+        .await
+        .expect("watcher did not exit while idle");
+        assert!(result.is_ok());
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_parse_failure_logs_error() {
+        let root = TempDir::new().unwrap();
+        let missing = root.path().join("missing.jsonl");
+        let mut watcher = LogWatcher::new();
+
+        let logs = CapturedLogs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(watcher.parse_messages(&missing).is_err());
+        });
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("ERROR"));
+        assert!(output.contains("Failed to parse"));
+    }
+
+    #[tokio::test]
+    async fn test_replay_instant_emits_in_order() {
+        let root = TempDir::new().unwrap();
+        let session = root.path().join("s.jsonl");
+        let lines: Vec<String> = ["09:00:00", "10:30:00", "12:00:00"]
+            .iter()
+            .enumerate()
+            .map(|(i, time)| {
+                message_line(&format!("u{i}"), &format!("message {i}"))
+                    .replace("2025-01-01T00:00:00Z", &format!("2025-01-01T{time}Z"))
+            })
+            .collect();
+        fs::write(&session, lines.concat()).unwrap();
+
+        let output = CapturedLogs::default();
+        let mut watcher = LogWatcher::new()
+            .with_numbering(true)
+            .with_timestamp(false)
+            .with_writer(output.clone());
+        let started = Instant::now();
+        assert_eq!(watcher.replay(&session, 0.0).await.unwrap(), 3);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(
+            output.lines(),
+            [
+                "#1 👤 User: message 0",
+                "#2 👤 User: message 1",
+                "#3 👤 User: message 2"
+            ]
+        );
+
+        let nine = "2025-01-01T09:00:00Z".parse().unwrap();
+        let ten = "2025-01-01T10:00:00Z".parse().unwrap();
+        assert_eq!(replay_pause(nine, ten, 0.0), None);
+        assert_eq!(replay_pause(nine, ten, 60.0), Some(Duration::from_secs(60)));
+        assert_eq!(replay_pause(ten, nine, 1.0), None);
+    }
+
+    #[test]
+    fn test_project_name_filter_limits_watch_all() {
+        let root = TempDir::new().unwrap();
+        for name in ["-home-u-api", "-home-u-web", "-home-u-api-docs", "-tmp-a(b"] {
+            fs::create_dir(root.path().join(name)).unwrap();
+        }
+        let projects = |pattern: &str| {
+            let mut projects = LogWatcher::new()
+                .with_claude_dir(Some(root.path().to_path_buf()))
+                .with_project_name_filter(Some(pattern))
+                .projects_to_watch()
+                .unwrap();
+            projects.sort();
+            projects
+        };
+
+        assert_eq!(
+            projects("api"),
+            [
+                root.path().join("-home-u-api"),
+                root.path().join("-home-u-api-docs")
+            ]
+        );
+        assert_eq!(projects("/web$"), [root.path().join("-home-u-web")]);
+        // Invalid regexes fall back to substring matching
+        assert_eq!(projects("a(b"), [root.path().join("-tmp-a(b")]);
+    }
+
+    #[test]
+    fn test_real_project_path_resolves_to_claude_dir() {
+        let claude_dir = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        let repo_path = fs::canonicalize(repo.path()).unwrap();
+        let mangled = claude_dir.path().join(encode_project_path(&repo_path));
+        fs::create_dir(&mangled).unwrap();
+
+        let watcher = LogWatcher::new().with_claude_dir(Some(claude_dir.path().to_path_buf()));
+        assert_eq!(watcher.resolve_project_path(repo.path()), mangled);
+        // Paths that are already project directories are used as given
+        assert_eq!(watcher.resolve_project_path(&mangled), mangled);
+        let unknown = Path::new("/no/such/repo");
+        assert_eq!(watcher.resolve_project_path(unknown), unknown);
+    }
+
+    #[tokio::test]
+    async fn test_blank_messages_not_emitted() {
+        let root = TempDir::new().unwrap();
+        let path = root.path().join("session.jsonl");
+        let empty_text = r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":""}]},"timestamp":"2025-01-01T00:00:00Z","sessionId":"s","uuid":"u1"}"#;
+        fs::write(
+            &path,
+            format!("{empty_text}\n{}", message_line("u2", "  \\n\\t ")),
+        )
+        .unwrap();
+        let (url, mut requests) =
+            crate::webhook::tests::mock_server(crate::webhook::tests::OK_RESPONSE).await;
+
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_webhook(Some(url), WebhookFormat::Generic);
+        assert_eq!(watcher.process_jsonl_file(&path).await.unwrap(), 0);
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slow_webhook_does_not_delay_stdout() {
+        let root = TempDir::new().unwrap();
+        let path = root.path().join("session.jsonl");
+        let lines: String = (0..3)
+            .map(|i| message_line(&format!("u{i}"), "hello"))
+            .collect();
+        fs::write(&path, lines).unwrap();
+
+        let url = crate::webhook::tests::silent_server().await;
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_status_line(true)
+            .with_webhook_timeout(Some(Duration::from_millis(300)))
+            .with_webhook(Some(url), WebhookFormat::Generic);
+        let (queue, worker) = watcher.start_webhook_worker().unwrap();
+
+        let started = Instant::now();
+        assert_eq!(watcher.process_jsonl_file(&path).await.unwrap(), 3);
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        // The timeouts are still reported once the worker gets to them
+        queue.close();
+        worker.await.unwrap();
+        let status = watcher.status.as_ref().unwrap().lock().unwrap();
+        assert_eq!(status.webhook_failed, 3);
+        assert!(status.render(true).ends_with("failed: 3"));
+    }
+
+    #[tokio::test]
+    async fn test_flush_queued_only_for_batching_senders() {
+        let url = Url::parse("https://example.com/hook").unwrap();
+        for batch in [false, true] {
+            let mut watcher = LogWatcher::new()
+                .with_webhook(Some(url.clone()), WebhookFormat::Generic)
+                .with_webhook_batch(batch);
+            let queue = WebhookQueue::new(WEBHOOK_QUEUE_CAPACITY, WebhookOverflow::Block);
+            watcher.webhook_queue = Some(queue.clone());
+
+            watcher.flush_webhooks().await;
+            queue.close();
+            let job = queue.pop().await;
+            assert_eq!(matches!(job, Some(WebhookJob::Flush(_))), batch);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhook_timeout_applies_in_any_order() {
+        let url = crate::webhook::tests::silent_server().await;
+        let timeout = Some(Duration::from_millis(200));
+        let watchers = [
+            LogWatcher::new()
+                .with_webhook_timeout(timeout)
+                .with_webhook(Some(url.clone()), WebhookFormat::Generic),
+            LogWatcher::new()
+                .with_webhook(Some(url), WebhookFormat::Generic)
+                .with_webhook_timeout(timeout),
+        ];
+        let message = LogParser::new()
+            .parse_line(message_line("u1", "hi").trim_end())
+            .unwrap()
+            .unwrap();
+
+        for watcher in watchers {
+            let sender = watcher.webhook_sender.unwrap();
+            let started = Instant::now();
+            assert!(sender.send_message(&message, "👤 User: hi").await.is_err());
+            assert!(started.elapsed() < Duration::from_secs(2));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhook_redactor_applies_in_any_order() {
+        let (url, mut requests) =
+            crate::webhook::tests::mock_server(crate::webhook::tests::OK_RESPONSE).await;
+        let redactor = Some(Redactor::builtin());
+        let watchers = [
+            LogWatcher::new()
+                .with_redactor(redactor.clone())
+                .with_webhook(Some(url.clone()), WebhookFormat::Slack),
+            LogWatcher::new()
+                .with_webhook(Some(url), WebhookFormat::Slack)
+                .with_redactor(redactor),
+        ];
+        let message = LogParser::new()
+            .parse_line(message_line("u1", "key sk-abcdefghijklmnopqrstu").trim_end())
+            .unwrap()
+            .unwrap();
+
+        for watcher in watchers {
+            let sender = watcher.webhook_sender.unwrap();
+            sender
+                .send_message(&message, "👤 User: key sk-abcdefghijklmnopqrstu")
+                .await
+                .unwrap();
+            let request = requests.recv().await.unwrap();
+            assert!(request.contains("[REDACTED]"), "{request}");
+            assert!(!request.contains("sk-abc"), "{request}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_min_content_len_webhooks_switch() {
+        let root = TempDir::new().unwrap();
+        let session = root.path().join("session.jsonl");
+        fs::write(&session, message_line("u1", "ok")).unwrap();
+        let (url, mut requests) =
+            crate::webhook::tests::mock_server(crate::webhook::tests::OK_RESPONSE).await;
+        let watcher = |apply| {
+            LogWatcher::new()
+                .with_include_existing(true)
+                .with_stdout(false)
+                .with_min_content_len(Some(5))
+                .with_min_content_len_webhooks(apply)
+                .with_webhook(Some(url.clone()), WebhookFormat::Generic)
+        };
+
+        // Hidden on stdout, but still posted
+        assert_eq!(
+            watcher(false).process_jsonl_file(&session).await.unwrap(),
+            0
+        );
+        let request = requests.recv().await.unwrap();
+        assert!(request.contains("👤 User: ok"), "{request}");
+
+        assert_eq!(watcher(true).process_jsonl_file(&session).await.unwrap(), 0);
+        assert!(timeout(Duration::from_millis(300), requests.recv())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_watch_all_delivers_webhooks() {
+        let root = TempDir::new().unwrap();
+        let project = root.path().join("-project");
+        write_session(&project, "s.jsonl", SystemTime::now());
+        let (url, mut requests) =
+            crate::webhook::tests::mock_server(crate::webhook::tests::OK_RESPONSE).await;
+        let watcher = LogWatcher::new()
+            .with_claude_dir(Some(root.path().to_path_buf()))
+            .with_idle_timeout(Some(Duration::from_secs(2)))
+            .with_webhook(Some(url), WebhookFormat::Generic);
+
+        let watching = tokio::spawn(async move { watcher.watch_all().await });
+        sleep(Duration::from_millis(500)).await;
+        append(
+            &project.join("s.jsonl"),
+            &message_line("u2", "from watch_all").replace("2025", "2099"),
+        );
+
+        let request = timeout(Duration::from_secs(5), requests.recv())
+            .await
+            .expect("webhook not delivered")
+            .unwrap();
+        assert!(request.contains("from watch_all"));
+        watching.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_removed_project_dir_stops_watch() {
+        let root = TempDir::new().unwrap();
+        let project = root.path().join("-project");
+        write_session(&project, "s.jsonl", SystemTime::now());
+        // The idle timeout only bounds the test if removal goes unnoticed
+        let mut watcher = LogWatcher::new().with_idle_timeout(Some(Duration::from_secs(3)));
+        let (watch, rx) = create_watcher(&project).unwrap();
+
+        let removed = project.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(StdDuration::from_millis(100));
+            fs::remove_dir_all(removed).unwrap();
+        });
+
+        let started = Instant::now();
+        watcher.event_loop(&project, watch, rx).await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_sidechain_traced_to_task_across_files() {
+        let root = TempDir::new().unwrap();
+        let session = root.path().join("session.jsonl");
+        let agent = root.path().join("agent-1.jsonl");
+        let entry =
+            |uuid: &str, parent: Option<&str>, sidechain: bool, message: serde_json::Value| {
+                serde_json::json!({
+                    "type": message["role"], "message": message, "uuid": uuid,
+                    "parentUuid": parent, "isSidechain": sidechain,
+                    "timestamp": "2025-01-01T00:00:00Z", "sessionId": "s",
+                })
+                .to_string()
+                    + "\n"
+            };
+        fs::write(
+            &session,
+            entry(
+                "a1",
+                None,
+                false,
+                serde_json::json!({"role": "assistant", "content": [{
+                    "type": "tool_use", "id": "toolu_1", "name": "Task",
+                    "input": {"description": "Find callers", "prompt": "Search the repo"},
+                }]}),
+            ),
+        )
+        .unwrap();
+        fs::write(
+            &agent,
+            entry(
+                "c1",
+                None,
+                true,
+                serde_json::json!({"role": "user", "content": "Search the repo"}),
+            ) + &entry(
+                "c2",
+                Some("c1"),
+                true,
+                serde_json::json!({"role": "assistant", "content": "Found main.rs"}),
+            ),
+        )
+        .unwrap();
+
+        let (url, mut requests) =
+            crate::webhook::tests::mock_server(crate::webhook::tests::OK_RESPONSE).await;
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_hide_sidechains(false)
+            .with_webhook(Some(url), WebhookFormat::Generic);
+        watcher.process_jsonl_file(&session).await.unwrap();
+        watcher.process_jsonl_file(&agent).await.unwrap();
+
+        let mut bodies = Vec::new();
+        while let Ok(request) = requests.try_recv() {
+            bodies.push(request);
+        }
+        assert_eq!(bodies.len(), 3);
+        assert!(bodies[2].contains("[sidechain: Find callers]: Found main.rs"));
+        // The gutter is for the terminal; webhooks get the plain message
+        assert!(bodies.iter().all(|body| !body.contains('│')));
+
+        // Nothing is tracked while sidechains are hidden
+        assert!(LogWatcher::new().tasks.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_fires_when_idle() {
+        let root = TempDir::new().unwrap();
+        let (url, mut requests) =
+            crate::webhook::tests::mock_server(crate::webhook::tests::OK_RESPONSE).await;
+        let mut watcher = LogWatcher::new()
+            .with_webhook(Some(url), WebhookFormat::Generic)
+            .with_webhook_heartbeat(Some(Duration::from_millis(100)))
+            .with_idle_timeout(Some(Duration::from_millis(250)));
+        let (watch, rx) = create_watcher(root.path()).unwrap();
+
+        watcher.event_loop(root.path(), watch, rx).await.unwrap();
+
+        let request = requests.try_recv().expect("no heartbeat was sent");
+        assert!(request.contains(r#""type":"heartbeat""#));
+    }
+
+    #[tokio::test]
+    async fn test_watch_all_sends_one_heartbeat_per_interval() {
+        let root = TempDir::new().unwrap();
+        let now = SystemTime::now();
+        write_session(&root.path().join("-a"), "s.jsonl", now);
+        write_session(&root.path().join("-b"), "s.jsonl", now);
+        let (url, mut requests) =
+            crate::webhook::tests::mock_server(crate::webhook::tests::OK_RESPONSE).await;
+        let watcher = LogWatcher::new()
+            .with_claude_dir(Some(root.path().to_path_buf()))
+            .with_webhook(Some(url), WebhookFormat::Generic)
+            .with_webhook_heartbeat(Some(Duration::from_millis(300)))
+            .with_idle_timeout(Some(Duration::from_millis(450)));
+
+        watcher.watch_all().await.unwrap();
+
+        let mut heartbeats = Vec::new();
+        while let Ok(request) = requests.try_recv() {
+            heartbeats.push(request);
+        }
+        assert_eq!(heartbeats.len(), 1, "{heartbeats:?}");
+        assert!(heartbeats[0].contains(r#""project":"all projects""#));
+    }
+
+    #[tokio::test]
+    async fn test_multiple_claude_dirs_are_aggregated() {
+        let alice = TempDir::new().unwrap();
+        let bob = TempDir::new().unwrap();
+        let now = SystemTime::now();
+        write_session(
+            &alice.path().join("-a"),
+            "s.jsonl",
+            now - StdDuration::from_secs(60),
+        );
+        write_session(&bob.path().join("-b"), "s.jsonl", now);
+
+        let watcher = LogWatcher::new()
+            .with_claude_dirs(vec![alice.path().to_path_buf(), bob.path().to_path_buf()]);
+
+        let mut projects = watcher.projects_to_watch().unwrap();
+        projects.sort();
+        let mut expected = vec![alice.path().join("-a"), bob.path().join("-b")];
+        expected.sort();
+        assert_eq!(projects, expected);
+
+        assert_eq!(
+            watcher.get_latest_project().await.unwrap(),
+            bob.path().join("-b")
+        );
+        let roots: Vec<_> = collect_projects(alice.path())
+            .unwrap()
+            .into_iter()
+            .map(|p| p.root)
+            .collect();
+        assert_eq!(roots, vec![alice.path().to_path_buf()]);
+    }
+
+    #[test]
+    fn test_max_sessions_keeps_newest_projects() {
+        let root = TempDir::new().unwrap();
+        let now = SystemTime::now();
+        write_session(
+            &root.path().join("-old"),
+            "s.jsonl",
+            now - StdDuration::from_secs(300),
+        );
+        write_session(
+            &root.path().join("-mid"),
+            "s.jsonl",
+            now - StdDuration::from_secs(60),
+        );
+        write_session(&root.path().join("-new"), "s.jsonl", now);
+
+        let projects = LogWatcher::new()
+            .with_claude_dir(Some(root.path().to_path_buf()))
+            .with_max_sessions(Some(2))
+            .projects_to_watch()
+            .unwrap();
+        assert_eq!(
+            projects,
+            [root.path().join("-new"), root.path().join("-mid")]
+        );
+    }
+
+    #[test]
+    fn test_active_within_skips_stale_projects() {
+        let root = TempDir::new().unwrap();
+        let now = SystemTime::now();
+        let day = StdDuration::from_secs(24 * 60 * 60);
+        write_session(&root.path().join("-stale"), "s.jsonl", now - 2 * day);
+        write_session(&root.path().join("-active"), "old.jsonl", now - 3 * day);
+        write_session(&root.path().join("-active"), "new.jsonl", now);
+        fs::create_dir(root.path().join("-empty")).unwrap();
+
+        let watcher = LogWatcher::new()
+            .with_claude_dir(Some(root.path().to_path_buf()))
+            .with_active_within(Some(day));
+        assert_eq!(
+            watcher.projects_to_watch().unwrap(),
+            [root.path().join("-active")]
+        );
+        assert!(watcher.is_active(Some(now - day / 2)));
+        assert!(!watcher.is_active(Some(now - 2 * day)));
+        assert!(!watcher.is_active(None));
+    }
+
+    #[tokio::test]
+    async fn test_custom_claude_dir_is_honored() {
+        let root = TempDir::new().unwrap();
+        write_session(&root.path().join("-custom"), "s.jsonl", SystemTime::now());
+
+        let watcher = LogWatcher::new().with_claude_dir(Some(root.path().to_path_buf()));
+        let latest = watcher.get_latest_project().await.unwrap();
+        assert_eq!(latest, root.path().join("-custom"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_claude_dir_is_an_error() {
+        let mut watcher = LogWatcher::new();
+        // Simulate an environment without HOME
+        watcher.claude_dirs.clear();
+
+        let err = watcher
+            .list_projects(ProjectSort::Name, false, false, false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("HOME is not set"));
+        assert!(watcher.get_latest_project().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_uuid_emitted_once() {
+        let root = TempDir::new().unwrap();
+        let first = root.path().join("a.jsonl");
+        let second = root.path().join("b.jsonl");
+        fs::write(&first, message_line("same", "hello")).unwrap();
+        fs::write(&second, message_line("same", "hello")).unwrap();
+
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_dedup_cache(10);
+        assert_eq!(watcher.process_jsonl_file(&first).await.unwrap(), 1);
+        assert_eq!(watcher.process_jsonl_file(&second).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_numbering_counts_emitted_messages_only() {
+        let root = TempDir::new().unwrap();
+        let session = root.path().join("s.jsonl");
+        let summary = r#"{"type":"summary","summary":"s","leafUuid":"u1","timestamp":"2025-01-01T12:00:00Z","uuid":"x"}"#;
+        let lines = [
+            message_line("u1", "one"),
+            format!("{summary}\n"),
+            message_line("u2", "two"),
+            message_line("u2", "two"),
+            message_line("u3", "three"),
+        ];
+        fs::write(&session, lines.concat()).unwrap();
+
+        let output = CapturedLogs::default();
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_dedup_cache(10)
+            .with_numbering(true)
+            .with_timestamp(false)
+            .with_writer(output.clone());
+        assert_eq!(watcher.process_jsonl_file(&session).await.unwrap(), 3);
+        assert_eq!(
+            output.lines(),
+            ["#1 👤 User: one", "#2 👤 User: two", "#3 👤 User: three"]
+        );
+    }
+
+    /// Records what was written before each flush
+    #[derive(Clone, Default)]
+    struct FlushLog {
+        pending: Arc<Mutex<Vec<u8>>>,
+        flushed: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Write for FlushLog {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.pending.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+            self.flushed
+                .lock()
+                .unwrap()
+                .push(String::from_utf8(pending).unwrap());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_each_message_is_flushed_when_printed() {
+        let root = TempDir::new().unwrap();
+        let session = root.path().join("s.jsonl");
+        fs::write(&session, "").unwrap();
+        let output = FlushLog::default();
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_timestamp(false)
+            .with_writer(output.clone());
+
+        for (uuid, text) in [("u1", "one"), ("u2", "two")] {
+            append(&session, &message_line(uuid, text));
+            assert_eq!(watcher.process_jsonl_file(&session).await.unwrap(), 1);
+            // Nothing waits in a buffer for the next message or for exit
+            assert!(output.pending.lock().unwrap().is_empty());
+        }
+        assert_eq!(
+            *output.flushed.lock().unwrap(),
+            ["👤 User: one\n", "👤 User: two\n"]
+        );
+    }
+
+    #[test]
+    fn test_session_headers_on_switch() {
+        let watcher = LogWatcher::new().with_group_sessions(true);
+        let line = |session: &str| {
+            format!(
+                r#"{{"type":"user","message":{{"role":"user","content":"hi"}},"timestamp":"2025-01-01T00:00:00Z","sessionId":"{session}","uuid":"u"}}"#
+            )
+        };
+
+        let headers: Vec<_> = ["aaaa1111", "aaaa1111", "bbbb2222", "aaaa1111"]
+            .iter()
+            .map(|session| {
+                let message = LogParser::new()
+                    .parse_line(&line(session))
+                    .unwrap()
+                    .unwrap();
+                watcher.session_header(&message)
+            })
+            .collect();
+
+        assert!(headers[0].as_ref().unwrap().contains("session aaaa1111"));
+        assert!(headers[1].is_none());
+        assert!(headers[2].as_ref().unwrap().contains("session bbbb2222"));
+        assert!(headers[3].as_ref().unwrap().contains("session aaaa1111"));
+
+        assert!(LogWatcher::new()
+            .session_header(&LogParser::new().parse_line(&line("x")).unwrap().unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn test_webhook_routing_by_role() {
+        let default_url = Url::parse("https://example.com/default").unwrap();
+        let watcher = LogWatcher::new()
+            .with_webhook(Some(default_url), WebhookFormat::Generic)
+            .with_webhook_routes(
+                vec!["assistant=https://example.com/assistant".parse().unwrap()],
+                WebhookFormat::Generic,
+            );
+
+        let message = |role: &str| {
+            let line = format!(
+                r#"{{"type":"{role}","message":{{"role":"{role}","content":"hi"}},"timestamp":"2025-01-01T00:00:00Z","sessionId":"s","uuid":"u"}}"#
+            );
+            LogParser::new().parse_line(&line).unwrap().unwrap()
+        };
+
+        let assistant = watcher.webhook_for(&message("assistant")).unwrap();
+        assert_eq!(assistant.url().path(), "/assistant");
+        let user = watcher.webhook_for(&message("user")).unwrap();
+        assert_eq!(user.url().path(), "/default");
+    }
+
+    #[test]
+    fn test_status_counts_accumulate() {
+        let message = |session: &str| {
+            let line = format!(
+                r#"{{"type":"user","message":{{"role":"user","content":"hi"}},"timestamp":"2025-01-01T00:00:00Z","sessionId":"{session}","uuid":"u"}}"#
+            );
+            LogParser::new().parse_line(&line).unwrap().unwrap()
+        };
+
+        let mut counts = StatusCounts::default();
+        for session in ["a", "b", "a", "a"] {
+            counts.record(&message(session));
+        }
+        counts.record_webhook(&Ok(WebhookResult::Sent));
+        counts.record_webhook(&Ok(WebhookResult::Skipped));
+        counts.record_webhook(&Err(anyhow::anyhow!("down")));
+
+        assert_eq!(counts.render(false), "sessions: 2 | msgs: 4");
+        assert_eq!(
+            counts.render(true),
+            "sessions: 2 | msgs: 4 | webhook sent: 1 skipped: 1 failed: 1"
+        );
+
+        // Batched payloads are sent only once a flush succeeds
+        for _ in 0..3 {
+            counts.record_webhook(&Ok(WebhookResult::Queued));
+        }
+        counts.record_webhook(&Ok(WebhookResult::DryRun));
+        counts.record_flush(&Err(anyhow::anyhow!("down")));
+        assert_eq!(
+            counts.render(true),
+            "sessions: 2 | msgs: 4 | webhook sent: 1 skipped: 1 queued: 3 failed: 1 dry run: 1"
+        );
+        counts.record_flush(&Err(anyhow::anyhow!("down").context(DroppedPayloads(1))));
+        counts.record_flush(&Ok(2));
+        assert_eq!(
+            counts.render(true),
+            "sessions: 2 | msgs: 4 | webhook sent: 3 skipped: 1 failed: 2 dry run: 1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_line_counts_undelivered_batch_as_queued() {
+        let root = TempDir::new().unwrap();
+        let session = root.path().join("session.jsonl");
+        fs::write(&session, message_line("u1", "hello")).unwrap();
+        let (url, _requests) =
+            crate::webhook::tests::mock_server(crate::webhook::tests::RATE_LIMITED_RESPONSE).await;
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_stdout(false)
+            .with_status_line(true)
+            .with_webhook(Some(url), WebhookFormat::Generic)
+            .with_webhook_batch(true);
+
+        watcher.process_jsonl_file(&session).await.unwrap();
+        let status = watcher.status.as_ref().unwrap().lock().unwrap();
+        assert!(
+            status
+                .render(true)
+                .ends_with("webhook sent: 0 skipped: 0 queued: 1"),
+            "{}",
+            status.render(true)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_existing_files_in_subdirectories() {
+        let root = TempDir::new().unwrap();
+        write_session(root.path(), "top.jsonl", SystemTime::now());
+        write_session(
+            &root.path().join("nested").join("deeper"),
+            "nested.jsonl",
+            SystemTime::now(),
+        );
+        fs::write(root.path().join("nested").join("notes.txt"), "skip me").unwrap();
+
+        let mut watcher = LogWatcher::new().with_include_existing(true);
+        assert_eq!(
+            watcher.process_existing_files(root.path()).await.unwrap(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_include_existing_from_timestamp() {
+        let root = TempDir::new().unwrap();
+        let path = root.path().join("session.jsonl");
+        let lines: String = ["2025-01-01T10:00:00Z", "2025-01-01T11:00:00Z", "2025-01-01T12:00:00Z"]
+            .iter()
+            .enumerate()
+            .map(|(i, ts)| {
+                format!(
+                    r#"{{"type":"user","message":{{"role":"user","content":"m{i}"}},"timestamp":"{ts}","sessionId":"s","uuid":"u{i}"}}"#
+                ) + "\n"
+            })
+            .collect();
+        fs::write(&path, lines).unwrap();
+
+        let anchor = parse_time_anchor("2025-01-01T11:00:00Z").unwrap();
+        let mut watcher = LogWatcher::new().with_include_existing_from(Some(anchor));
+        assert_eq!(watcher.process_jsonl_file(&path).await.unwrap(), 2);
+
+        // Without an anchor the same history is skipped entirely
+        let mut watcher = LogWatcher::new();
+        assert_eq!(watcher.process_jsonl_file(&path).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_start_position_end_skips_existing_lines() {
+        let root = TempDir::new().unwrap();
+        let session = root.path().join("session.jsonl");
+        fs::write(&session, message_line("u1", "history")).unwrap();
+
+        let mut watcher = LogWatcher::new().with_start_position(Some(StartPosition::End));
+        watcher.process_startup(root.path()).await.unwrap();
+        assert_eq!(watcher.process_jsonl_file(&session).await.unwrap(), 0);
+
+        // Appended lines show even though their timestamp predates startup
+        append(&session, &message_line("u2", "appended"));
+        assert_eq!(watcher.process_jsonl_file(&session).await.unwrap(), 1);
+
+        let mut watcher = LogWatcher::new().with_start_position(Some(StartPosition::Beginning));
+        watcher.process_startup(root.path()).await.unwrap();
+        assert!(watcher.parser.parse_file(&session).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_after_uuid_resumes_after_message() {
+        let root = TempDir::new().unwrap();
+        let session = root.path().join("session.jsonl");
+        let lines: String = ["u1", "u2", "u3", "u4"]
+            .iter()
+            .map(|uuid| message_line(uuid, "text"))
+            .collect();
+        fs::write(&session, lines).unwrap();
+
+        let dir = root.path();
+        let emitted = |inclusive: bool| async move {
+            let (tx, mut rx) = tokio_mpsc::channel(STREAM_BUFFER);
+            let mut watcher = LogWatcher::new()
+                .with_after_uuid(Some("u2".to_string()))
+                .with_after_uuid_inclusive(inclusive);
+            watcher.sink = Some(tx);
+            watcher.process_startup(dir).await.unwrap();
+            drop(watcher);
+            let mut uuids = Vec::new();
+            while let Some(message) = rx.recv().await {
+                uuids.push(message.uuid);
+            }
+            uuids
+        };
+        assert_eq!(emitted(false).await, ["u3", "u4"]);
+        assert_eq!(emitted(true).await, ["u2", "u3", "u4"]);
+
+        let mut watcher = LogWatcher::new().with_after_uuid(Some("missing".to_string()));
+        let error = watcher.process_startup(root.path()).await.unwrap_err();
+        assert!(error.to_string().contains("missing"), "{error}");
+
+        // CRLF endings
+        let lines: String = ["u1", "u2", "u3", "u4"]
+            .iter()
+            .map(|uuid| message_line(uuid, "text").replace('\n', "\r\n"))
+            .collect();
+        fs::write(&session, lines).unwrap();
+        assert_eq!(emitted(false).await, ["u3", "u4"]);
+        assert_eq!(emitted(true).await, ["u2", "u3", "u4"]);
+
+        // A last line still being written, without its newline
+        let lines = message_line("u1", "text") + message_line("u2", "text").trim_end();
+        fs::write(&session, lines).unwrap();
+        let (tx, mut rx) = tokio_mpsc::channel(STREAM_BUFFER);
+        let mut watcher = LogWatcher::new().with_after_uuid(Some("u2".to_string()));
+        watcher.sink = Some(tx);
+        watcher.process_startup(dir).await.unwrap();
+        append(&session, &format!("\n{}", message_line("u3", "text")));
+        assert_eq!(watcher.process_jsonl_file(&session).await.unwrap(), 1);
+        drop(watcher);
+        assert_eq!(rx.recv().await.unwrap().uuid, "u3");
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_existing_summaries_not_emitted_on_append() {
+        let root = TempDir::new().unwrap();
+        let session = root.path().join("session.jsonl");
+        let summary = r#"{"type":"summary","summary":"Old work","leafUuid":"u1"}"#;
+        fs::write(
+            &session,
+            format!("{summary}\n{}", message_line("u1", "old")),
+        )
+        .unwrap();
+
+        let (tx, mut rx) = tokio_mpsc::channel(STREAM_BUFFER);
+        let mut watcher = LogWatcher::new().with_show_summaries(true);
+        watcher.sink = Some(tx);
+        let now = Utc::now().to_rfc3339();
+        append(
+            &session,
+            &format!(
+                r#"{{"type":"user","message":{{"role":"user","content":"new"}},"timestamp":"{now}","sessionId":"s","uuid":"u2"}}"#
+            ),
+        );
+        append(&session, "\n");
+        append(
+            &session,
+            r#"{"type":"summary","summary":"New work","leafUuid":"u2"}"#,
+        );
+        append(&session, "\n");
+        watcher.process_jsonl_file(&session).await.unwrap();
+        drop(watcher);
+
+        let mut emitted = Vec::new();
+        while let Some(message) = rx.recv().await {
+            emitted.push(message.content);
+        }
+        assert_eq!(emitted, ["new", "New work"]);
+    }
+
+    #[test]
+    fn test_count_matches_visible_messages() {
+        let root = TempDir::new().unwrap();
+        let assistant_tool = r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","name":"Bash","input":{"command":"ls"}}]},"timestamp":"2025-01-01T00:00:01Z","sessionId":"s","uuid":"a1"}"#;
+        let lines = [
+            message_line("u1", "first"),
+            message_line("u2", "second"),
+            format!("{assistant_tool}\n"),
+            message_line("u1", "first"),
+        ];
+        fs::write(root.path().join("session.jsonl"), lines.concat()).unwrap();
+
+        let count = |mut watcher: LogWatcher| watcher.count_messages(root.path()).unwrap();
+        assert_eq!(count(LogWatcher::new()), 0);
+        let existing = || LogWatcher::new().with_include_existing(true);
+        assert_eq!(count(existing()), 4);
+        assert_eq!(count(existing().with_dedup_cache(100)), 3);
+        assert_eq!(
+            count(existing().with_tool_display_mode(crate::ToolDisplayMode::None)),
+            3
+        );
+
+        // Startup options select the same messages a real run prints
+        let position = |position| LogWatcher::new().with_start_position(Some(position));
+        assert_eq!(count(position(StartPosition::End)), 0);
+        assert_eq!(count(position(StartPosition::Beginning)), 4);
+        assert_eq!(count(LogWatcher::new().with_tail(Some(2))), 2);
+        assert_eq!(
+            count(LogWatcher::new().with_after_uuid(Some("u2".to_string()))),
+            2
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_conflicting_options() {
+        assert!(LogWatcher::default().validate().is_ok());
+
+        let silent = LogWatcher::new().with_stdout(false);
+        assert!(silent.validate().is_err());
+
+        let conflicting = LogWatcher::new()
+            .with_include_existing(true)
+            .with_start_position(Some(StartPosition::End));
+        assert!(conflicting.validate().is_err());
+
+        let compact = LogWatcher::new()
+            .with_compact_mode(true)
+            .with_tool_display_mode(crate::ToolDisplayMode::Detailed);
+        assert!(compact.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_time_anchor_duration() {
+        let anchor = parse_time_anchor("1h").unwrap();
+        let expected = Utc::now() - chrono::Duration::hours(1);
+        assert!((anchor - expected).num_seconds().abs() < 5);
+        assert!(parse_time_anchor("yesterday-ish").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_collapse_repeated_results() {
+        let root = TempDir::new().unwrap();
+        let path = root.path().join("session.jsonl");
+        let lines: String = (0..3)
+            .map(|i| {
+                format!(
+                    r#"{{"type":"user","message":{{"role":"user","content":[{{"type":"tool_result","content":"ok"}}]}},"timestamp":"2025-01-01T00:00:0{i}Z","sessionId":"s","uuid":"u{i}"}}"#
+                ) + "\n"
+            })
+            .collect();
+        fs::write(&path, lines).unwrap();
+
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_collapse_repeats(true);
+        let mut messages = watcher.parse_messages(&path).unwrap().into_iter();
+        for message in messages.by_ref() {
+            let formatted = watcher.formatter.format_message(&message).unwrap();
+            assert_eq!(watcher.output(message, formatted).await.unwrap(), 0);
+        }
+
+        let pending = watcher.pending_repeat.as_ref().unwrap();
+        assert!(pending.text().ends_with("✅ Result (x3)"));
+        assert_eq!(watcher.flush_repeats().await, 1);
+
+        // The same file processed end-to-end yields a single line
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_collapse_repeats(true);
+        assert_eq!(watcher.process_jsonl_file(&path).await.unwrap(), 0);
+        assert_eq!(watcher.flush_repeats().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_collapse_repeats_across_appends() {
+        let root = TempDir::new().unwrap();
+        let path = root.path().join("session.jsonl");
+        fs::write(&path, "").unwrap();
+        let result = |i: usize| {
+            format!(
+                r#"{{"type":"user","message":{{"role":"user","content":[{{"type":"tool_result","content":"ok"}}]}},"timestamp":"2025-01-01T00:00:0{i}Z","sessionId":"s","uuid":"u{i}"}}"#
+            ) + "\n"
+        };
+
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_collapse_repeats(true);
+        for i in 0..3 {
+            append(&path, &result(i));
+            assert_eq!(watcher.process_jsonl_file(&path).await.unwrap(), 0);
+        }
+        let pending = watcher.pending_repeat.as_ref().unwrap();
+        assert!(
+            pending.text().ends_with("✅ Result (x3)"),
+            "{}",
+            pending.text()
+        );
+
+        // A different line prints the collapsed one and is held in turn
+        append(&path, &message_line("u9", "something else"));
+        assert_eq!(watcher.process_jsonl_file(&path).await.unwrap(), 1);
+        assert!(watcher
+            .pending_repeat
+            .as_ref()
+            .unwrap()
+            .text()
+            .contains("something else"));
+        assert_eq!(watcher.flush_repeats().await, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_stream_yields_existing_and_appended_messages() {
+        use tokio_stream::StreamExt;
+
+        let root = TempDir::new().unwrap();
+        let session = root.path().join("session.jsonl");
+        fs::write(&session, message_line("u1", "history")).unwrap();
+
+        let mut stream = Box::pin(
+            LogWatcher::new()
+                .with_include_existing(true)
+                .with_idle_timeout(Some(Duration::from_secs(10)))
+                .stream(root.path()),
+        );
+        let wait = Duration::from_secs(5);
+        let first = tokio::time::timeout(wait, stream.next()).await.unwrap();
+        assert_eq!(first.unwrap().uuid, "u1");
+
+        // The watch is running once existing messages have been streamed
+        append(&session, &message_line("u2", "new"));
+        append(&session, &message_line("u3", "new"));
+        let appended: Vec<LogMessage> = tokio::time::timeout(wait, stream.take(2).collect())
+            .await
+            .unwrap();
+        let uuids: Vec<_> = appended.iter().map(|m| m.uuid.as_str()).collect();
+        assert_eq!(uuids, ["u2", "u3"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_symlinked_project_is_listed_and_watched() {
+        use tokio_stream::StreamExt;
+
+        let storage = TempDir::new().unwrap();
+        let root = TempDir::new().unwrap();
+        let target = storage.path().join("-home-user-app");
+        write_session(&target, "s.jsonl", SystemTime::now());
+        let link = root.path().join("-home-user-app");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let names: Vec<_> = collect_projects(root.path())
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, ["-home-user-app"]);
+
+        let watcher = LogWatcher::new().with_claude_dirs(vec![root.path().to_path_buf()]);
+        assert_eq!(watcher.projects_to_watch().unwrap(), vec![link.clone()]);
+        assert_eq!(watcher.get_latest_project().await.unwrap(), link);
+
+        let mut stream = Box::pin(
+            LogWatcher::new()
+                .with_include_existing(true)
+                .with_idle_timeout(Some(Duration::from_secs(10)))
+                .stream(&link),
+        );
+        let wait = Duration::from_secs(5);
+        let first = tokio::time::timeout(wait, stream.next()).await.unwrap();
+        assert_eq!(first.unwrap().uuid, "u1");
+
+        append(&target.join("s.jsonl"), &message_line("u2", "new"));
+        let next = tokio::time::timeout(wait, stream.next()).await.unwrap();
+        assert_eq!(next.unwrap().uuid, "u2");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_redaction_applies_to_webhook_and_events() {
+        use tokio::io::{AsyncBufReadExt, BufReader};
+
+        let root = TempDir::new().unwrap();
+        let session = root.path().join("session.jsonl");
+        fs::write(
+            &session,
+            message_line("u1", "key sk-abcdefghijklmnopqrstu, mail bob@example.com"),
+        )
+        .unwrap();
+
+        let socket = root.path().join("events.sock");
+        let publisher = crate::events::EventPublisher::new();
+        tokio::spawn(crate::events::serve(
+            crate::events::bind(&socket).unwrap(),
+            publisher.clone(),
+        ));
+        let client = tokio::net::UnixStream::connect(&socket).await.unwrap();
+        while publisher.client_count() == 0 {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        let (url, mut requests) =
+            crate::webhook::tests::mock_server(crate::webhook::tests::OK_RESPONSE).await;
+        let mut watcher = LogWatcher::new()
+            .with_include_existing(true)
+            .with_stdout(false)
+            .with_webhook(Some(url), WebhookFormat::Generic)
+            .with_events(publisher)
+            .with_redactor(Some(Redactor::builtin()));
+        assert_eq!(watcher.process_jsonl_file(&session).await.unwrap(), 1);
+
+        let request = requests.recv().await.unwrap();
+        let mut event = String::new();
+        BufReader::new(client).read_line(&mut event).await.unwrap();
+        for output in [request, event] {
+            assert!(output.contains("key [REDACTED], mail [EMAIL]"), "{output}");
+            assert!(!output.contains("sk-abc"), "{output}");
+            assert!(!output.contains("bob@example.com"), "{output}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exec_formatter_gets_redacted_content() {
+        let watcher = LogWatcher::new()
+            .with_exec_formatter(Some(ExecFormatter::new("cat")))
+            .with_redactor(Some(Redactor::builtin()));
+        let message = LogParser::new()
+            .parse_line(message_line("u1", "key sk-abcdefghijklmnopqrstu").trim_end())
+            .unwrap()
+            .unwrap();
+
+        let line = watcher
+            .stdout_line(
+                &message,
+                "👤 User: key [REDACTED]",
+                "👤 User: key [REDACTED]",
+            )
+            .await;
+        let echoed: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(echoed["content"], "key [REDACTED]");
+        assert!(!line.contains("sk-abc"), "{line}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_max_rate_paces_output() {
+        let mut watcher = LogWatcher::new().with_max_rate(Some(5));
+        let message = LogParser::new()
+            .parse_line(message_line("u", "hi").trim_end())
+            .unwrap()
+            .unwrap();
+
+        let start = tokio::time::Instant::now();
+        for _ in 0..10 {
+            assert!(watcher.emit_message(&message, "👤 User: hi").await);
+        }
+        // A burst of five, then one every 200ms
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(990), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(1100), "{elapsed:?}");
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_resumes_after_watch_error() {
+        let root = TempDir::new().unwrap();
+        let session = root.path().join("session.jsonl");
+        fs::write(&session, message_line("u1", "before")).unwrap();
+
+        let mut watcher = LogWatcher::new().with_include_existing(true);
+        watcher.watch_root = Some(root.path().to_path_buf());
+        assert_eq!(watcher.process_jsonl_file(&session).await.unwrap(), 1);
+
+        // A line written while the watch is down is caught up on reconnect
+        let error = notify::Error::path_not_found();
+        assert!(is_fatal_watch_error(&error));
+        append(&session, &message_line("u2", "during outage"));
+        let (_watch, mut rx) = watcher.reconnect(root.path()).await.unwrap();
+        assert!(watcher.parser.parse_file(&session).unwrap().is_empty());
+
+        // The re-created watcher keeps delivering events
+        append(&session, &message_line("u3", "after"));
+        let mut emitted = 0;
+        while emitted == 0 {
+            let event = timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .expect("no event from re-created watcher")
+                .unwrap()
+                .unwrap();
+            emitted += watcher.handle_file_event(event).await.unwrap();
+        }
+        assert_eq!(emitted, 1);
+    }
+
+    fn append(path: &Path, line: &str) {
+        let mut file = File::options().append(true).open(path).unwrap();
+        file.write_all(line.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_sort_projects_by_recency() {
+        let root = TempDir::new().unwrap();
+        let now = SystemTime::now();
+        write_session(
+            &root.path().join("-a-old"),
+            "s1.jsonl",
+            now - StdDuration::from_secs(300),
+        );
+        write_session(&root.path().join("-b-newest"), "s2.jsonl", now);
+        write_session(
+            &root.path().join("-c-middle"),
+            "s3.jsonl",
+            now - StdDuration::from_secs(60),
+        );
+        fs::create_dir_all(root.path().join("-d-empty")).unwrap();
+
+        let mut projects = collect_projects(root.path()).unwrap();
+        sort_projects(&mut projects, ProjectSort::Recent);
+
+        let names: Vec<_> = projects.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["-b-newest", "-c-middle", "-a-old", "-d-empty"]);
+        assert_eq!(projects[0].sessions, 1);
+        assert_eq!(projects[0].messages, 1);
+        assert!(projects[3].last_modified.is_none());
     }
 }
@@ -1,32 +1,330 @@
 use anyhow::{Context, Result};
 use reqwest::Client;
 use serde_json::{json, Value};
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use url::Url;
 
-use crate::formatter::LogFormatter;
-use crate::parser::LogMessage;
-use crate::WebhookFormat;
+use crate::formatter::{
+    is_blank, primary_tool_input, short_session_id, tool_display_name, tool_icon,
+    tool_use_result_text, truncate_chars, LogFormatter,
+};
+use crate::metrics::Metrics;
+use crate::parser::{LogMessage, MessageRole};
+use crate::redact::Redactor;
+use crate::{write_output, SharedWriter, SlackStyle, WebhookFormat, WebhookPreset};
+
+/// Host that Slack incoming webhooks are served from
+const SLACK_WEBHOOK_HOST: &str = "hooks.slack.com";
+
+/// Reject URLs a webhook cannot be posted to
+pub fn check_webhook_url(url: &Url) -> Result<()> {
+    match url.scheme() {
+        "http" | "https" => Ok(()),
+        scheme => anyhow::bail!(
+            "Unsupported webhook URL scheme {scheme:?} in {url}: expected http or https"
+        ),
+    }
+}
+
+/// Parse a webhook URL, accepting only http and https
+pub fn parse_webhook_url(s: &str) -> Result<Url> {
+    let url = Url::parse(s).with_context(|| format!("Invalid webhook URL {s:?}"))?;
+    check_webhook_url(&url)?;
+    Ok(url)
+}
+
+/// Request timeout used unless `with_timeout` overrides it
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug)]
 pub enum WebhookResult {
     Sent,
     Skipped,
+    /// Held back until the next `flush` (batch mode)
+    Queued,
+    /// Printed to stderr instead of being posted (dry-run mode)
+    DryRun,
+}
+
+/// Undelivered batch payloads kept for the next flush before the oldest
+/// are dropped
+pub const MAX_BATCH_BACKLOG: usize = 1000;
+
+/// Payloads a failed flush dropped because the backlog was full; attached
+/// to the flush error as context
+#[derive(Debug, Clone, Copy)]
+pub struct DroppedPayloads(pub usize);
+
+impl std::fmt::Display for DroppedPayloads {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Webhook batch backlog full; dropped the {} oldest payloads",
+            self.0
+        )
+    }
+}
+
+/// The endpoint answered 429 Too Many Requests
+#[derive(Debug, Clone)]
+pub struct RateLimited {
+    /// Wait hint from the `Retry-After` header, if one was given
+    pub retry_after: Option<Duration>,
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Webhook rate limited (429 Too Many Requests)")?;
+        match self.retry_after {
+            Some(delay) => write!(f, "; retry after {}", humantime::format_duration(delay)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Parse a `Retry-After` value: delay in seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = at.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or_default())
+}
+
+/// Turn a non-success response into an error, with rate-limit details for 429
+fn check_status(response: &reqwest::Response, what: &str) -> Result<()> {
+    let status = response.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        return Err(RateLimited { retry_after }.into());
+    }
+    if !status.is_success() {
+        anyhow::bail!("{what} failed with status: {status}");
+    }
+    Ok(())
+}
+
+/// Which messages a webhook route applies to
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteMatch {
+    Role(MessageRole),
+    Project(String),
+}
+
+/// A `--webhook-route` entry: `ROLE=URL` or `project=NAME:URL`
+#[derive(Debug, Clone)]
+pub struct WebhookRoute {
+    pub matcher: RouteMatch,
+    pub url: Url,
+}
+
+impl WebhookRoute {
+    pub fn matches(&self, message: &LogMessage) -> bool {
+        match &self.matcher {
+            RouteMatch::Role(role) => &message.role == role,
+            RouteMatch::Project(name) => &message.project_name == name,
+        }
+    }
+}
+
+impl FromStr for WebhookRoute {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (key, value) = s
+            .split_once('=')
+            .context("Expected ROLE=URL or project=NAME:URL")?;
+
+        let (matcher, url) = match key {
+            "user" => (RouteMatch::Role(MessageRole::User), value),
+            "assistant" => (RouteMatch::Role(MessageRole::Assistant), value),
+            "system" => (RouteMatch::Role(MessageRole::System), value),
+            "summary" => (RouteMatch::Role(MessageRole::Summary), value),
+            "project" => {
+                let (name, url) = value.split_once(':').context("Expected project=NAME:URL")?;
+                (RouteMatch::Project(name.to_string()), url)
+            }
+            _ => anyhow::bail!(
+                "Unknown route key {key:?}: expected user, assistant, system, summary or project"
+            ),
+        };
+
+        Ok(Self {
+            matcher,
+            url: parse_webhook_url(url)?,
+        })
+    }
 }
 
+/// Placeholders a `WebhookTemplate` may use
+const TEMPLATE_PLACEHOLDERS: &[&str] = &[
+    "timestamp",
+    "role",
+    "content",
+    "session_id",
+    "project",
+    "uuid",
+];
+
+/// Custom shape for generic payloads: any JSON document whose string values
+/// may contain `{{timestamp}}`, `{{role}}`, `{{content}}`, `{{session_id}}`,
+/// `{{project}}` or `{{uuid}}`
+#[derive(Debug, Clone)]
+pub struct WebhookTemplate(Value);
+
+impl WebhookTemplate {
+    fn render(&self, message: &LogMessage, formatted_content: &str) -> Value {
+        let timestamp = message.timestamp.to_rfc3339();
+        let role = format!("{:?}", message.role);
+        let fields = [
+            ("timestamp", timestamp.as_str()),
+            ("role", role.as_str()),
+            ("content", formatted_content),
+            ("session_id", message.session_id.as_str()),
+            ("project", message.project_name.as_str()),
+            ("uuid", message.uuid.as_str()),
+        ];
+        substitute(&self.0, &fields)
+    }
+}
+
+impl FromStr for WebhookTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let template: Value = serde_json::from_str(s).context("Template is not valid JSON")?;
+        check_placeholders(&template)?;
+        Ok(Self(template))
+    }
+}
+
+/// Fail on placeholders that `render` would leave unfilled
+fn check_placeholders(value: &Value) -> Result<()> {
+    match value {
+        Value::String(text) => check_text_placeholders(text, TEMPLATE_PLACEHOLDERS),
+        Value::Array(items) => items.iter().try_for_each(check_placeholders),
+        Value::Object(map) => map.values().try_for_each(check_placeholders),
+        _ => Ok(()),
+    }
+}
+
+/// Fill placeholders in every string value of `value`
+fn substitute(value: &Value, fields: &[(&str, &str)]) -> Value {
+    match value {
+        Value::String(text) => Value::String(fill_placeholders(text, fields)),
+        Value::Array(items) => items.iter().map(|item| substitute(item, fields)).collect(),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, item)| (key.clone(), substitute(item, fields)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Fail on `{{name}}` placeholders in `text` that are not in `allowed`
+fn check_text_placeholders(text: &str, allowed: &[&str]) -> Result<()> {
+    for name in text
+        .split("{{")
+        .skip(1)
+        .filter_map(|rest| rest.split_once("}}").map(|(name, _)| name))
+    {
+        if !allowed.contains(&name) {
+            anyhow::bail!(
+                "Unknown placeholder {{{{{name}}}}}: expected one of {}",
+                allowed.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Bot name shown on Slack messages unless `with_slack_identity` sets one
+pub const DEFAULT_SLACK_USERNAME: &str = "Claude Code / {{project}} | {{session}}";
+
+/// Placeholders a Slack username may use
+const SLACK_USERNAME_PLACEHOLDERS: &[&str] = &["project", "session", "role"];
+
+/// Check a Slack username template for placeholders that cannot be filled
+pub fn validate_slack_username(template: &str) -> Result<String, String> {
+    check_text_placeholders(template, SLACK_USERNAME_PLACEHOLDERS)
+        .map(|()| template.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Replace `{{name}}` in one pass, so placeholder-like text inside a
+/// substituted value is left alone
+fn fill_placeholders(text: &str, fields: &[(&str, &str)]) -> String {
+    let mut output = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let filled = after.split_once("}}").and_then(|(name, tail)| {
+            let (_, value) = fields.iter().find(|(field, _)| *field == name)?;
+            Some((*value, tail))
+        });
+        match filled {
+            Some((value, tail)) => {
+                output.push_str(value);
+                rest = tail;
+            }
+            None => {
+                output.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+#[derive(Clone)]
 pub struct WebhookSender {
     client: Client,
     url: Url,
     format: WebhookFormat,
     formatter: LogFormatter,
+    metrics: Option<Arc<Metrics>>,
+    /// Generic payloads waiting to be sent as one NDJSON request
+    batch: Option<Arc<Mutex<Vec<Value>>>>,
+    include_raw: bool,
+    dry_run: bool,
+    /// Where dry runs print payloads, stderr unless replaced
+    dry_run_writer: SharedWriter,
+    slack_style: SlackStyle,
+    template: Option<WebhookTemplate>,
+    redactor: Option<Redactor>,
+    /// Roles that are forwarded; `None` forwards every role
+    roles: Option<Vec<MessageRole>>,
+    /// Replaces the low-information filter when set
+    preset: Option<WebhookPreset>,
+    /// Bot name template for Slack, see `DEFAULT_SLACK_USERNAME`
+    slack_username: String,
+    slack_icon_emoji: Option<String>,
+    /// Fixed text put before and after every message
+    prefix: Option<String>,
+    suffix: Option<String>,
 }
 
 impl WebhookSender {
     pub fn new(url: Url, format: WebhookFormat) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(10))
-            .build()
-            .context("Failed to create HTTP client")?;
+        check_webhook_url(&url)?;
+        if matches!(format, WebhookFormat::Slack) && url.host_str() != Some(SLACK_WEBHOOK_HOST) {
+            tracing::warn!("Slack webhook URL {url} is not on {SLACK_WEBHOOK_HOST}");
+        }
+        let client = build_client(DEFAULT_TIMEOUT)?;
 
         let formatter =
             LogFormatter::new().with_tool_display_mode(crate::ToolDisplayMode::Detailed);
@@ -36,21 +334,180 @@ impl WebhookSender {
             url,
             format,
             formatter,
+            metrics: None,
+            batch: None,
+            include_raw: false,
+            dry_run: false,
+            dry_run_writer: Arc::new(Mutex::new(std::io::stderr())),
+            slack_style: SlackStyle::default(),
+            template: None,
+            redactor: None,
+            roles: None,
+            preset: None,
+            slack_username: DEFAULT_SLACK_USERNAME.to_string(),
+            slack_icon_emoji: None,
+            prefix: None,
+            suffix: None,
         })
     }
 
+    /// Give up on a request after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self> {
+        self.client = build_client(timeout)?;
+        Ok(self)
+    }
+
+    /// Queue Generic payloads and send them as newline-delimited JSON on `flush`
+    pub fn with_batching(mut self, enabled: bool) -> Self {
+        if enabled && matches!(self.format, WebhookFormat::Slack) {
+            tracing::warn!("Webhook batching is only supported for the generic format; ignoring");
+            return self;
+        }
+        self.batch = enabled.then(|| Arc::new(Mutex::new(Vec::new())));
+        self
+    }
+
+    /// Whether payloads wait for `flush` instead of being posted right away
+    pub fn is_batching(&self) -> bool {
+        self.batch.is_some()
+    }
+
+    /// Print payloads to stderr instead of posting them
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Print dry-run payloads to `writer` instead of stderr
+    pub fn with_dry_run_writer(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.dry_run_writer = Arc::new(Mutex::new(writer));
+        self
+    }
+
+    /// Shape generic payloads with `template` instead of the built-in fields
+    pub fn with_template(mut self, template: Option<WebhookTemplate>) -> Self {
+        if template.is_some() && matches!(self.format, WebhookFormat::Slack) {
+            tracing::warn!("Webhook templates only apply to the generic format; ignoring");
+            return self;
+        }
+        self.template = template;
+        self
+    }
+
+    /// Mask sensitive text in every string of the payload
+    pub fn with_redactor(mut self, redactor: Option<Redactor>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// Layout of Slack messages
+    pub fn with_slack_style(mut self, style: SlackStyle) -> Self {
+        self.slack_style = style;
+        self
+    }
+
+    /// Post to Slack as `username`, a template with `{{project}}`,
+    /// `{{session}}` and `{{role}}` placeholders, and with `icon_emoji`
+    /// (e.g. `:robot:`) as the avatar
+    pub fn with_slack_identity(
+        mut self,
+        username: Option<String>,
+        icon_emoji: Option<String>,
+    ) -> Self {
+        if let Some(username) = username {
+            self.slack_username = username;
+        }
+        self.slack_icon_emoji = icon_emoji;
+        self
+    }
+
+    /// Put `prefix` before every message, separated by a space, and
+    /// `suffix` after it on its own line. Slack receives both unescaped,
+    /// so mentions such as `<!here>` work.
+    pub fn with_text_wrapping(mut self, prefix: Option<String>, suffix: Option<String>) -> Self {
+        self.prefix = prefix;
+        self.suffix = suffix;
+        self
+    }
+
+    /// Choose which messages are posted with a preset instead of the
+    /// default low-information filter
+    pub fn with_preset(mut self, preset: Option<WebhookPreset>) -> Self {
+        self.preset = preset;
+        self
+    }
+
+    /// Only forward messages with one of these roles; others are `Skipped`
+    pub fn with_roles(mut self, roles: Option<Vec<MessageRole>>) -> Self {
+        self.roles = roles;
+        self
+    }
+
+    /// Leave completed items out of Slack TodoWrite checklists
+    pub fn with_hide_completed_todos(mut self, hide: bool) -> Self {
+        self.formatter = self.formatter.with_hide_completed_todos(hide);
+        self
+    }
+
+    /// Embed the original log entry under `raw` in generic payloads
+    pub fn with_include_raw(mut self, include: bool) -> Self {
+        self.include_raw = include;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
     /// Send message to webhook
     pub async fn send_message(
         &self,
         message: &LogMessage,
         formatted_content: &str,
     ) -> Result<WebhookResult> {
-        // Skip low-information messages for webhook (but not for stdout)
-        if self.is_low_information_message_for_webhook(message) {
+        let result = self.deliver(message, formatted_content).await;
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_webhook(&result);
+        }
+        result
+    }
+
+    /// Filter, format and POST a single message
+    async fn deliver(
+        &self,
+        message: &LogMessage,
+        formatted_content: &str,
+    ) -> Result<WebhookResult> {
+        // Blank messages are never emitted; low-information ones are still
+        // shown on stdout
+        if is_blank(formatted_content) || self.is_filtered(message) {
             return Ok(WebhookResult::Skipped);
         }
+        if let Some(ref roles) = self.roles {
+            if !roles.contains(&message.role) {
+                return Ok(WebhookResult::Skipped);
+            }
+        }
 
-        let payload = self.format_message(message, formatted_content)?;
+        let mut payload = self.format_message(message, formatted_content)?;
+        if let Some(ref redactor) = self.redactor {
+            redactor.redact_value(&mut payload);
+        }
+
+        if self.dry_run {
+            self.print_dry_run(&payload)?;
+            return Ok(WebhookResult::DryRun);
+        }
+
+        if let Some(ref batch) = self.batch {
+            batch.lock().unwrap().push(payload);
+            return Ok(WebhookResult::Queued);
+        }
 
         let response = self
             .client
@@ -59,15 +516,122 @@ impl WebhookSender {
             .send()
             .await
             .context("Failed to send webhook request")?;
+        check_status(&response, "Webhook request")?;
+
+        Ok(WebhookResult::Sent)
+    }
 
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!(
-                "Webhook request failed with status: {}",
-                response.status()
-            ));
+    /// Show the request a dry run would have made
+    fn print_dry_run(&self, payload: &Value) -> Result<()> {
+        let text = format!(
+            "[webhook dry run] POST {}\n{}\n",
+            self.url,
+            serde_json::to_string_pretty(payload)?
+        );
+        write_output(&self.dry_run_writer, &text);
+        Ok(())
+    }
+
+    /// Post a minimal "still watching" payload in this sender's format
+    pub async fn send_heartbeat(&self, project: &str) -> Result<()> {
+        let payload = match self.format {
+            WebhookFormat::Generic => json!({
+                "type": "heartbeat",
+                "status": "watching",
+                "project": project,
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+            }),
+            WebhookFormat::Slack => json!({
+                "text": format!("💓 claude-logger is still watching {}", escape_slack(project)),
+            }),
+        };
+
+        if self.dry_run {
+            self.print_dry_run(&payload)?;
+            return Ok(());
         }
 
-        Ok(WebhookResult::Sent)
+        let response = self
+            .client
+            .post(self.url.clone())
+            .json(&payload)
+            .send()
+            .await
+            .context("Failed to send webhook heartbeat")?;
+        check_status(&response, "Webhook heartbeat")
+    }
+
+    /// Send all queued payloads as one NDJSON request, returning how many
+    /// were sent. If the request fails they stay queued for the next flush,
+    /// up to `MAX_BATCH_BACKLOG`; beyond that the oldest are dropped and
+    /// the error carries `DroppedPayloads`.
+    pub async fn flush(&self) -> Result<usize> {
+        let Some(ref batch) = self.batch else {
+            return Ok(0);
+        };
+        let payloads = std::mem::take(&mut *batch.lock().unwrap());
+        if payloads.is_empty() {
+            return Ok(0);
+        }
+
+        let Err(e) = self.post_ndjson(&payloads).await else {
+            if let Some(ref metrics) = self.metrics {
+                for _ in &payloads {
+                    metrics.record_webhook(&Ok(WebhookResult::Sent));
+                }
+            }
+            return Ok(payloads.len());
+        };
+
+        // Ahead of anything queued meanwhile, to keep the order
+        let dropped = {
+            let mut queued = batch.lock().unwrap();
+            let newer = std::mem::replace(&mut *queued, payloads);
+            queued.extend(newer);
+            let dropped = queued.len().saturating_sub(MAX_BATCH_BACKLOG);
+            queued.drain(..dropped);
+            dropped
+        };
+        if dropped == 0 {
+            return Err(e);
+        }
+
+        // A payload only counts as failed once it is given up on
+        if let Some(ref metrics) = self.metrics {
+            let outcome = Err(match e.downcast_ref::<RateLimited>() {
+                Some(limited) => limited.clone().into(),
+                None => anyhow::anyhow!("{e}"),
+            });
+            for _ in 0..dropped {
+                metrics.record_webhook(&outcome);
+            }
+        }
+        Err(e.context(DroppedPayloads(dropped)))
+    }
+
+    async fn post_ndjson(&self, payloads: &[Value]) -> Result<()> {
+        let response = self
+            .client
+            .post(self.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(to_ndjson(payloads)?)
+            .send()
+            .await
+            .context("Failed to send webhook batch")?;
+        check_status(&response, "Webhook batch request")
+    }
+
+    /// Whether the preset, or by default the low-information filter, keeps
+    /// this message off the webhook (it is still shown on stdout)
+    fn is_filtered(&self, message: &LogMessage) -> bool {
+        match self.preset {
+            None => self.is_low_information_message_for_webhook(message),
+            Some(WebhookPreset::All) => false,
+            Some(WebhookPreset::AssistantText) => {
+                message.role != MessageRole::Assistant || !has_prose(message)
+            }
+            Some(WebhookPreset::Errors) => !has_error_result(message),
+        }
     }
 
     /// Check if this message should be filtered out for webhook posting
@@ -82,17 +646,7 @@ impl WebhookSender {
         };
 
         // If message contains meaningful text content, send it
-        let has_text = arr.iter().filter_map(|item| item.as_object()).any(|obj| {
-            obj.get("type")
-                .and_then(|t| t.as_str())
-                .filter(|&t| t == "text")
-                .and_then(|_| obj.get("text"))
-                .and_then(|text| text.as_str())
-                .map(|text| !text.trim().is_empty())
-                .unwrap_or(false)
-        });
-
-        if has_text {
+        if has_prose(message) {
             return false;
         }
 
@@ -122,125 +676,506 @@ impl WebhookSender {
     /// Format message according to webhook format
     fn format_message(&self, message: &LogMessage, formatted_content: &str) -> Result<Value> {
         match self.format {
-            WebhookFormat::Generic => self.format_generic(message, formatted_content),
+            WebhookFormat::Generic => self.format_generic(message, &self.wrap(formatted_content)),
             WebhookFormat::Slack => {
-                let slack_content = self.format_content_for_slack(message);
-                self.format_slack(message, &slack_content)
+                let sections = self.slack_sections(&self.format_content_for_slack(message));
+                match self.slack_style {
+                    SlackStyle::Blocks => self.format_slack(message, &sections),
+                    SlackStyle::Attachments => self.format_slack_attachments(message, &sections),
+                }
             }
         }
     }
 
+    /// Add the prefix and suffix, if any, around a message's text
+    fn wrap(&self, content: &str) -> String {
+        let mut text = String::new();
+        if let Some(ref prefix) = self.prefix {
+            text.push_str(prefix);
+            text.push(' ');
+        }
+        text.push_str(content);
+        if let Some(ref suffix) = self.suffix {
+            text.push('\n');
+            text.push_str(suffix);
+        }
+        text
+    }
+
+    /// Escape, wrap and split Slack parts into section texts. Prose is
+    /// split on line boundaries. Each code part gets a section of its own,
+    /// cut to fit together with its header and any prefix or suffix, so a
+    /// fence is never broken across sections. Only the prefix and suffix
+    /// are left unescaped, so mentions such as `<!here>` work.
+    fn slack_sections(&self, parts: &[SlackPart]) -> Vec<String> {
+        let prefix = self
+            .prefix
+            .as_ref()
+            .map(|prefix| format!("{prefix} "))
+            .unwrap_or_default();
+        let suffix = self
+            .suffix
+            .as_ref()
+            .map(|suffix| format!("\n{suffix}"))
+            .unwrap_or_default();
+        let last = parts.len().saturating_sub(1);
+
+        let mut sections = Vec::new();
+        let mut prose = Vec::new();
+        for (i, part) in parts.iter().enumerate() {
+            let lead = if i == 0 { prefix.as_str() } else { "" };
+            let trail = if i == last { suffix.as_str() } else { "" };
+            match part {
+                SlackPart::Text(text) => prose.push(format!("{lead}{}{trail}", escape_slack(text))),
+                SlackPart::Code { header, code } => {
+                    if !prose.is_empty() {
+                        sections.extend(split_for_slack(&prose.join("\n"), SLACK_SECTION_LIMIT));
+                        prose.clear();
+                    }
+                    let opening = format!("{lead}{}\n```\n", escape_slack(header));
+                    let closing = format!("\n```{trail}");
+                    let budget = SLACK_SECTION_LIMIT
+                        .saturating_sub(opening.chars().count() + closing.chars().count());
+                    let code = escape_slack(&truncate_for_slack_fence(code, budget));
+                    sections.push(format!("{opening}{code}{closing}"));
+                }
+            }
+        }
+        if !prose.is_empty() || sections.is_empty() {
+            sections.extend(split_for_slack(&prose.join("\n"), SLACK_SECTION_LIMIT));
+        }
+        sections
+    }
+
     /// Format message content specifically for Slack
-    fn format_content_for_slack(&self, message: &LogMessage) -> String {
-        let Some(ref raw_content) = message.raw_content else {
-            return message.content.clone();
+    fn format_content_for_slack(&self, message: &LogMessage) -> Vec<SlackPart> {
+        let Some(Value::Array(ref arr)) = message.raw_content else {
+            return vec![SlackPart::Text(message.content.clone())];
         };
+        let blocks: Vec<_> = arr
+            .iter()
+            .filter_map(Value::as_object)
+            .map(|obj| (obj.get("type").and_then(Value::as_str), obj))
+            .collect();
+        if !blocks.iter().any(|(kind, _)| *kind == Some("tool_use")) {
+            return vec![self
+                .format_command_output_for_slack(message)
+                .unwrap_or_else(|| SlackPart::Text(message.content.clone()))];
+        }
 
-        let Value::Array(arr) = raw_content else {
-            return message.content.clone();
+        // Text and tool calls in order, as on stdout
+        let mut parts = Vec::new();
+        for (kind, obj) in blocks {
+            match kind {
+                Some("tool_use") => parts.extend(self.format_tool_use_for_slack(obj)),
+                Some("text") => {
+                    if let Some(text) = obj.get("text").and_then(|t| t.as_str()) {
+                        if !text.trim().is_empty() {
+                            parts.push(SlackPart::Text(text.to_string()));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if parts.is_empty() {
+            parts.push(SlackPart::Text(String::new()));
+        }
+        parts
+    }
+
+    /// The stdout and stderr of a Bash result as a code block, if the
+    /// message carries one
+    fn format_command_output_for_slack(&self, message: &LogMessage) -> Option<SlackPart> {
+        let result = message.tool_use_result.as_ref()?;
+        if message_kind(message).0 != "tool_result" || result.get("stdout").is_none() {
+            return None;
+        }
+        let code = tool_use_result_text(result).filter(|code| !is_blank(code))?;
+        let icon = if has_error_result(message) {
+            "❌"
+        } else {
+            "✅"
         };
+        Some(SlackPart::Code {
+            header: format!("{icon} Result:"),
+            code,
+        })
+    }
 
-        for item in arr {
-            let Some(obj) = item.as_object() else {
-                continue;
-            };
-            let Some(content_type) = obj.get("type").and_then(|t| t.as_str()) else {
-                continue;
-            };
-
-            if content_type == "tool_use" {
-                let Some(tool_name) = obj.get("name").and_then(|n| n.as_str()) else {
-                    continue;
-                };
-
-                if tool_name == "TodoWrite" {
-                    let Some(input) = obj.get("input") else {
-                        continue;
-                    };
-                    let Some(todos) = input.get("todos") else {
-                        continue;
-                    };
-
-                    let slack_todos = self.formatter.format_todos_for_slack(todos);
-                    return format!("📝 TodoWrite: {slack_todos}");
-                }
+    /// One tool call for Slack: a checklist for TodoWrite, a code block
+    /// for Bash, and the main input for other tools
+    fn format_tool_use_for_slack(&self, obj: &serde_json::Map<String, Value>) -> Option<SlackPart> {
+        let tool_name = obj.get("name").and_then(|n| n.as_str())?;
 
-                // Handle other tools with generic format
-                let tool_icon = if tool_name == "TodoWrite" {
-                    "📝"
-                } else {
-                    "🔧"
-                };
-                if let Some(input) = obj.get("input") {
-                    let input_str = self.format_tool_input_for_slack(input);
-                    return format!("{tool_icon} {tool_name}: {input_str}");
-                } else {
-                    return format!("{tool_icon} {tool_name}");
-                }
+        if tool_name == "TodoWrite" {
+            let todos = obj.get("input")?.get("todos")?;
+            let slack_todos = self.formatter.format_todos_for_slack(todos);
+            return Some(SlackPart::Text(format!("📝 TodoWrite: {slack_todos}")));
+        }
+
+        if tool_name == "Bash" {
+            if let Some(command) = obj
+                .get("input")
+                .and_then(|input| input.get("command"))
+                .and_then(|c| c.as_str())
+            {
+                return Some(SlackPart::Code {
+                    header: format!("{} Bash:", tool_icon(tool_name)),
+                    code: command.to_string(),
+                });
             }
         }
 
-        message.content.clone()
+        // Handle other tools with generic format
+        let tool_icon = tool_icon(tool_name);
+        let display_name = tool_display_name(tool_name);
+        Some(SlackPart::Text(match obj.get("input") {
+            Some(input) => {
+                let input_str = self.format_tool_input_for_slack(input);
+                format!("{tool_icon} {display_name}: {input_str}")
+            }
+            None => format!("{tool_icon} {display_name}"),
+        }))
     }
 
     /// Format tool input for Slack (simpler than terminal version)
     fn format_tool_input_for_slack(&self, input: &Value) -> String {
         match input {
             Value::Object(obj) => {
-                if let Some(command) = obj.get("command") {
-                    if let Some(cmd_str) = command.as_str() {
-                        let truncated = cmd_str.chars().take(50).collect::<String>();
-                        return truncated + if cmd_str.len() > 50 { "..." } else { "" };
-                    }
-                }
-                "(...)".to_string()
-            }
-            Value::String(s) => {
-                let truncated = s.chars().take(50).collect::<String>();
-                truncated + if s.len() > 50 { "..." } else { "" }
+                primary_tool_input(obj, 50).unwrap_or_else(|| "(...)".to_string())
             }
+            Value::String(s) => truncate_chars(s, 50),
             _ => "(...)".to_string(),
         }
     }
 
     /// Generic JSON format
     fn format_generic(&self, message: &LogMessage, formatted_content: &str) -> Result<Value> {
-        Ok(json!({
-            "timestamp": message.timestamp.to_rfc3339(),
-            "role": format!("{:?}", message.role),
-            "content": formatted_content,
-            "session_id": message.session_id,
-            "uuid": message.uuid
-        }))
+        let mut payload = match self.template {
+            Some(ref template) => template.render(message, formatted_content),
+            None => {
+                let (message_type, tool_name) = message_kind(message);
+                let mut payload = json!({
+                    "timestamp": message.timestamp.to_rfc3339(),
+                    "role": format!("{:?}", message.role),
+                    "message_type": message_type,
+                    "content": formatted_content,
+                    "session_id": message.session_id,
+                    "uuid": message.uuid
+                });
+                if let Some(tool_name) = tool_name {
+                    payload["tool_name"] = json!(tool_name);
+                }
+                payload
+            }
+        };
+        if self.include_raw && payload.is_object() {
+            if let Some(ref raw) = message.raw_entry {
+                payload["raw"] = raw.clone();
+            }
+        }
+        Ok(payload)
     }
 
     /// Slack webhook format
-    fn format_slack(&self, message: &LogMessage, formatted_content: &str) -> Result<Value> {
-        let session_short = &message.session_id[..8.min(message.session_id.len())];
-        let username = format!("Claude Code / {} | {}", message.project_name, session_short);
-        let text = formatted_content.to_string();
-
-        Ok(json!({
-            "text": text,
-            "username": username,
-            "blocks": [
-                {
-                    "type": "section",
-                    "text": {
-                        "type": "mrkdwn",
-                        "text": text
-                    }
+    fn format_slack(&self, message: &LogMessage, sections: &[String]) -> Result<Value> {
+        Ok(self.with_slack_sender(
+            message,
+            json!({
+                "text": truncate_chars(&sections.join("\n"), SLACK_SECTION_LIMIT),
+                "blocks": slack_blocks(sections)
+            }),
+        ))
+    }
+
+    /// Like `format_slack`, but wraps the blocks in an attachment whose
+    /// colored bar identifies the role
+    fn format_slack_attachments(&self, message: &LogMessage, sections: &[String]) -> Result<Value> {
+        Ok(self.with_slack_sender(
+            message,
+            json!({
+                "text": truncate_chars(&sections.join("\n"), SLACK_SECTION_LIMIT),
+                "attachments": [{
+                    "color": role_color(&message.role),
+                    "blocks": slack_blocks(sections)
+                }]
+            }),
+        ))
+    }
+
+    /// Add the bot name and icon to a Slack payload
+    fn with_slack_sender(&self, message: &LogMessage, mut payload: Value) -> Value {
+        let session = short_session_id(&message.session_id);
+        let role = format!("{:?}", message.role);
+        let fields = [
+            ("project", message.project_name.as_str()),
+            ("session", session),
+            ("role", role.as_str()),
+        ];
+        payload["username"] = json!(fill_placeholders(&self.slack_username, &fields));
+        if let Some(ref icon) = self.slack_icon_emoji {
+            payload["icon_emoji"] = json!(icon);
+        }
+        payload
+    }
+}
+
+/// Whether a message has non-blank text, either as plain string content or
+/// in a `text` block
+fn has_prose(message: &LogMessage) -> bool {
+    match message.raw_content {
+        Some(Value::Array(ref blocks)) => blocks.iter().any(|block| {
+            block["type"] == "text" && block["text"].as_str().is_some_and(|t| !is_blank(t))
+        }),
+        _ => !is_blank(&message.content),
+    }
+}
+
+/// Whether a message carries a `tool_result` flagged with `is_error`
+fn has_error_result(message: &LogMessage) -> bool {
+    let Some(Value::Array(ref blocks)) = message.raw_content else {
+        return false;
+    };
+    blocks
+        .iter()
+        .any(|block| block["type"] == "tool_result" && block["is_error"] == true)
+}
+
+/// What a message carries, from its content blocks: `tool_use` (with the
+/// first tool's name), `tool_result`, `text` or `thinking`, in that order
+/// of precedence
+pub(crate) fn message_kind(message: &LogMessage) -> (&'static str, Option<&str>) {
+    let Some(Value::Array(ref blocks)) = message.raw_content else {
+        return ("text", None);
+    };
+    let has = |kind: &str| blocks.iter().any(|b| b["type"] == kind);
+
+    if let Some(tool_use) = blocks.iter().find(|b| b["type"] == "tool_use") {
+        return ("tool_use", tool_use["name"].as_str());
+    }
+    if has("tool_result") {
+        ("tool_result", None)
+    } else if has("thinking") && !has("text") {
+        ("thinking", None)
+    } else {
+        ("text", None)
+    }
+}
+
+/// A piece of a Slack message, before escaping
+enum SlackPart {
+    Text(String),
+    /// Shown as `header` over a code block, which stays in one section
+    Code {
+        header: String,
+        code: String,
+    },
+}
+
+/// mrkdwn section blocks for already-escaped section texts
+fn slack_blocks(sections: &[String]) -> Vec<Value> {
+    sections
+        .iter()
+        .map(|chunk| {
+            json!({
+                "type": "section",
+                "text": {
+                    "type": "mrkdwn",
+                    "text": chunk
                 }
-            ]
-        }))
+            })
+        })
+        .collect()
+}
+
+/// Attachment bar color for each role
+fn role_color(role: &MessageRole) -> &'static str {
+    match role {
+        MessageRole::User => "#1d9bd1",
+        MessageRole::Assistant => "#2eb67d",
+        MessageRole::System => "#ecb22e",
+        MessageRole::Summary => "#9e9ea6",
+    }
+}
+
+/// Serialize payloads as newline-delimited JSON, one object per line
+fn to_ndjson(payloads: &[Value]) -> Result<String> {
+    let mut body = String::new();
+    for payload in payloads {
+        body.push_str(&serde_json::to_string(payload)?);
+        body.push('\n');
     }
+    Ok(body)
+}
+
+/// Escape the characters Slack reserves for links and mentions in mrkdwn.
+/// Formatting markers such as `*bold*` are left untouched.
+fn escape_slack(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Truncate text so that, once escaped, it fits in `budget` characters.
+/// Used for code fences, which must not be split across section blocks.
+fn truncate_for_slack_fence(text: &str, budget: usize) -> String {
+    let budget = budget.saturating_sub(3);
+    let mut used = 0;
+    let mut result = String::new();
+    for c in text.chars() {
+        used += match c {
+            '&' => 5,
+            '<' | '>' => 4,
+            _ => 1,
+        };
+        if used > budget {
+            result.push_str("...");
+            return result;
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Maximum length Slack accepts for the text of a single section block
+const SLACK_SECTION_LIMIT: usize = 3000;
+
+/// Split text into chunks of at most `limit` characters, breaking on line
+/// boundaries where possible and hard-splitting lines that are too long
+fn split_for_slack(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+
+    for line in text.split('\n') {
+        let line_len = line.chars().count();
+        let separator = usize::from(!current.is_empty());
+
+        if current_len + separator + line_len <= limit {
+            if separator == 1 {
+                current.push('\n');
+            }
+            current.push_str(line);
+            current_len += separator + line_len;
+            continue;
+        }
+
+        if !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut pieces = chars.chunks(limit).map(|c| c.iter().collect::<String>());
+        current = pieces.next().unwrap_or_default();
+        for piece in pieces {
+            chunks.push(std::mem::replace(&mut current, piece));
+        }
+        current_len = current.chars().count();
+    }
+
+    if !current.is_empty() || chunks.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn build_client(timeout: Duration) -> Result<Client> {
+    Client::builder()
+        .timeout(timeout)
+        .build()
+        .context("Failed to create HTTP client")
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
-    use crate::parser::MessageRole;
     use chrono::Utc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// Start a one-shot HTTP server that replies with `response` and
+    /// forwards the raw request it received
+    pub(crate) async fn mock_server(response: &'static str) -> (Url, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/hook", listener.local_addr().unwrap())).unwrap();
+        let (tx, rx) = mpsc::channel(10);
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                loop {
+                    let read = stream.read(&mut buffer).await.unwrap_or(0);
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request);
+                    if read == 0 || request_complete(&text) {
+                        break;
+                    }
+                }
+                let _ = tx
+                    .send(String::from_utf8_lossy(&request).into_owned())
+                    .await;
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        (url, rx)
+    }
+
+    /// Whether the headers and the full Content-Length body have arrived
+    fn request_complete(request: &str) -> bool {
+        let Some((headers, body)) = request.split_once("\r\n\r\n") else {
+            return false;
+        };
+        let length = headers
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().ok())?
+            })
+            .unwrap_or(0);
+        body.len() >= length
+    }
+
+    /// Collects written output, such as tracing logs or printed messages,
+    /// for assertions
+    #[derive(Clone, Default)]
+    pub(crate) struct CapturedLogs(pub(crate) Arc<Mutex<Vec<u8>>>);
+
+    impl CapturedLogs {
+        pub(crate) fn lines(&self) -> Vec<String> {
+            let output = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+            output.lines().map(str::to_string).collect()
+        }
+    }
+
+    impl Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for CapturedLogs {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    pub(crate) const OK_RESPONSE: &str =
+        "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+    pub(crate) const RATE_LIMITED_RESPONSE: &str = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 30\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
 
     fn create_test_message() -> LogMessage {
         LogMessage {
@@ -251,6 +1186,13 @@ mod tests {
             uuid: "test-uuid".to_string(),
             project_name: "test-project".to_string(),
             raw_content: None,
+            tool_use_result: None,
+            is_meta: false,
+            is_sidechain: false,
+            parent_uuid: None,
+            task: None,
+            raw_entry: None,
+            raw_line: None,
         }
     }
 
@@ -269,15 +1211,647 @@ mod tests {
         assert!(result.get("timestamp").is_some());
     }
 
+    #[test]
+    fn test_slack_tool_icon() {
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let sender = WebhookSender::new(url, WebhookFormat::Slack).unwrap();
+        let mut message = create_test_message();
+        message.raw_content = Some(json!([
+            {"type": "tool_use", "name": "Bash", "input": {"command": "ls"}}
+        ]));
+
+        let content = sender
+            .slack_sections(&sender.format_content_for_slack(&message))
+            .join("\n");
+        assert!(content.starts_with("💻 Bash"));
+
+        message.raw_content = Some(json!([
+            {"type": "tool_use", "name": "mcp__github__create_issue", "input": {"title": "Bug"}}
+        ]));
+        let content = sender
+            .slack_sections(&sender.format_content_for_slack(&message))
+            .join("\n");
+        assert!(content.starts_with("🔌 github/create_issue"));
+    }
+
+    #[test]
+    fn test_slack_keeps_prose_around_tool_calls() {
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let sender = WebhookSender::new(url, WebhookFormat::Slack).unwrap();
+        let mut message = create_test_message();
+        message.raw_content = Some(json!([
+            {"type": "text", "text": "Let me check the tests."},
+            {"type": "tool_use", "name": "Bash", "input": {"command": "cargo test"}},
+            {"type": "tool_use", "name": "Read", "input": {"file_path": "src/lib.rs"}}
+        ]));
+
+        let content = sender
+            .slack_sections(&sender.format_content_for_slack(&message))
+            .join("\n");
+        let bash = content.find("💻 Bash:\n```\ncargo test\n```").unwrap();
+        let read = content.find("src/lib.rs").unwrap();
+        assert!(
+            content.starts_with("Let me check the tests.\n"),
+            "{content}"
+        );
+        assert!(bash < read, "{content}");
+    }
+
+    #[test]
+    fn test_generic_message_type() {
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let sender = WebhookSender::new(url, WebhookFormat::Generic).unwrap();
+        let mut message = create_test_message();
+        message.role = MessageRole::Assistant;
+        message.raw_content = Some(json!([
+            {"type": "text", "text": "Running it"},
+            {"type": "tool_use", "name": "Bash", "input": {"command": "ls"}}
+        ]));
+
+        let payload = sender.format_generic(&message, "content").unwrap();
+        assert_eq!(payload["message_type"], "tool_use");
+        assert_eq!(payload["tool_name"], "Bash");
+
+        message.raw_content = Some(json!([
+            {"type": "tool_result", "tool_use_id": "t1", "content": "ok"}
+        ]));
+        let payload = sender.format_generic(&message, "content").unwrap();
+        assert_eq!(payload["message_type"], "tool_result");
+        assert!(payload.get("tool_name").is_none());
+
+        message.raw_content = Some(json!([{"type": "thinking", "thinking": "hmm"}]));
+        let payload = sender.format_generic(&message, "content").unwrap();
+        assert_eq!(payload["message_type"], "thinking");
+
+        message.raw_content = None;
+        let payload = sender.format_generic(&message, "content").unwrap();
+        assert_eq!(payload["message_type"], "text");
+    }
+
+    #[test]
+    fn test_generic_template() {
+        let template: WebhookTemplate =
+            r#"{"text":"[{{role}}] {{content}}","meta":{"project":"{{project}}","id":"{{uuid}}"},"tags":["{{session_id}}"]}"#
+                .parse()
+                .unwrap();
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let sender = WebhookSender::new(url, WebhookFormat::Generic)
+            .unwrap()
+            .with_template(Some(template));
+        let mut message = create_test_message();
+        message.project_name = "my-project".to_string();
+
+        // Placeholder-like text in a value is not expanded again
+        let result = sender.format_generic(&message, "say \"{{uuid}}\"").unwrap();
+
+        assert_eq!(
+            result,
+            json!({
+                "text": "[User] say \"{{uuid}}\"",
+                "meta": {"project": "my-project", "id": "test-uuid"},
+                "tags": ["test-session-12345"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_templates_rejected() {
+        assert!("{\"text\": {{content}}}"
+            .parse::<WebhookTemplate>()
+            .is_err());
+        let err = r#"{"text":"{{body}}"}"#.parse::<WebhookTemplate>().unwrap_err();
+        assert!(err.to_string().contains("{{body}}"));
+    }
+
     #[test]
     fn test_slack_format() {
         let url = Url::parse("https://example.com/webhook").unwrap();
         let sender = WebhookSender::new(url, WebhookFormat::Slack).unwrap();
         let message = create_test_message();
 
-        let result = sender.format_slack(&message, "Formatted content").unwrap();
+        let result = sender
+            .format_slack(&message, &["Formatted content".to_string()])
+            .unwrap();
 
         assert!(result.get("text").is_some());
         assert!(result.get("blocks").is_some());
     }
+
+    #[test]
+    fn test_slack_custom_username_and_icon() {
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let sender = WebhookSender::new(url.clone(), WebhookFormat::Slack).unwrap();
+        let message = create_test_message();
+
+        let result = sender.format_message(&message, "Hi").unwrap();
+        assert_eq!(result["username"], "Claude Code / test-project | test-ses");
+        assert!(result.get("icon_emoji").is_none());
+
+        let sender = sender.with_slack_identity(
+            Some("{{role}} in {{project}}".to_string()),
+            Some(":robot:".to_string()),
+        );
+        let result = sender.format_message(&message, "Hi").unwrap();
+        assert_eq!(result["username"], "User in test-project");
+        assert_eq!(result["icon_emoji"], ":robot:");
+
+        assert!(validate_slack_username("{{session}}").is_ok());
+        assert!(validate_slack_username("{{uuid}}").is_err());
+    }
+
+    #[test]
+    fn test_prefix_and_suffix_wrap_payload_text() {
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let mut message = create_test_message();
+        message.content = "Deployed".to_string();
+        let wrap = |sender: WebhookSender| {
+            sender.with_text_wrapping(
+                Some("[prod] <!here>".to_string()),
+                Some("-- bot".to_string()),
+            )
+        };
+
+        let generic = wrap(WebhookSender::new(url.clone(), WebhookFormat::Generic).unwrap());
+        let payload = generic.format_message(&message, "Deployed").unwrap();
+        assert_eq!(payload["content"], "[prod] <!here> Deployed\n-- bot");
+
+        let slack = wrap(WebhookSender::new(url, WebhookFormat::Slack).unwrap());
+        let payload = slack.format_message(&message, "Deployed").unwrap();
+        assert_eq!(payload["text"], "[prod] <!here> Deployed\n-- bot");
+
+        // The added text counts toward Slack's section limit
+        message.content = "a".repeat(SLACK_SECTION_LIMIT - 10);
+        let payload = slack.format_message(&message, "").unwrap();
+        let blocks = payload["blocks"].as_array().unwrap();
+        let texts: Vec<_> = blocks
+            .iter()
+            .map(|block| block["text"]["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(texts.len(), 2);
+        assert!(texts
+            .iter()
+            .all(|text| text.chars().count() <= SLACK_SECTION_LIMIT));
+        assert!(texts[0].starts_with("[prod]") && texts[1].ends_with("\n-- bot"));
+    }
+
+    #[test]
+    fn test_slack_attachments_format() {
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let sender = WebhookSender::new(url, WebhookFormat::Slack)
+            .unwrap()
+            .with_slack_style(SlackStyle::Attachments);
+        let message = create_test_message();
+
+        let result = sender.format_message(&message, "ignored").unwrap();
+
+        assert!(result.get("blocks").is_none());
+        let attachments = result["attachments"].as_array().unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0]["color"], "#1d9bd1");
+        assert_eq!(attachments[0]["blocks"][0]["text"]["text"], "Test message");
+    }
+
+    #[test]
+    fn test_slack_splits_long_content() {
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let sender = WebhookSender::new(url, WebhookFormat::Slack).unwrap();
+        let message = create_test_message();
+        let line = "x".repeat(99);
+        let content = vec![line.as_str(); 50].join("\n");
+        assert_eq!(content.len(), 4999);
+
+        let sections = sender.slack_sections(&[SlackPart::Text(content.clone())]);
+        let result = sender.format_slack(&message, &sections).unwrap();
+        let blocks = result["blocks"].as_array().unwrap();
+
+        assert!(blocks.len() > 1);
+        let mut rejoined = Vec::new();
+        for block in blocks {
+            let text = block["text"]["text"].as_str().unwrap();
+            assert!(text.chars().count() <= SLACK_SECTION_LIMIT);
+            assert!(!text.starts_with('\n') && !text.ends_with('\n'));
+            rejoined.push(text);
+        }
+        assert_eq!(rejoined.join("\n"), content);
+        assert!(result["text"].as_str().unwrap().chars().count() <= SLACK_SECTION_LIMIT + 3);
+    }
+
+    #[test]
+    fn test_split_for_slack_hard_splits_long_lines() {
+        let chunks = split_for_slack(&"y".repeat(7000), SLACK_SECTION_LIMIT);
+        let lengths: Vec<_> = chunks.iter().map(|c| c.len()).collect();
+        assert_eq!(lengths, vec![3000, 3000, 1000]);
+    }
+
+    #[test]
+    fn test_slack_escapes_special_characters() {
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let sender = WebhookSender::new(url, WebhookFormat::Slack).unwrap();
+        let mut message = create_test_message();
+        message.content = "if a < b && c > d { *ok* }".to_string();
+
+        let payload = sender.format_message(&message, "ignored").unwrap();
+        let text = payload["blocks"][0]["text"]["text"].as_str().unwrap();
+        assert_eq!(text, "if a &lt; b &amp;&amp; c &gt; d { *ok* }");
+        assert_eq!(payload["text"].as_str().unwrap(), text);
+    }
+
+    #[test]
+    fn test_slack_fences_bash_command() {
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let sender = WebhookSender::new(url, WebhookFormat::Slack).unwrap();
+        let mut message = create_test_message();
+        message.raw_content = Some(json!([
+            {"type": "tool_use", "name": "Bash", "input": {"command": "cargo test && echo done"}}
+        ]));
+
+        let payload = sender.format_message(&message, "ignored").unwrap();
+        let text = payload["blocks"][0]["text"]["text"].as_str().unwrap();
+        assert_eq!(text, "💻 Bash:\n```\ncargo test &amp;&amp; echo done\n```");
+
+        // Long commands are truncated inside the fence and stay in one block
+        message.raw_content = Some(json!([
+            {"type": "tool_use", "name": "Bash", "input": {"command": "<".repeat(5000)}}
+        ]));
+        let payload = sender.format_message(&message, "ignored").unwrap();
+        let blocks = payload["blocks"].as_array().unwrap();
+        assert_eq!(blocks.len(), 1);
+        let text = blocks[0]["text"]["text"].as_str().unwrap();
+        assert!(text.chars().count() <= SLACK_SECTION_LIMIT);
+        assert!(text.ends_with("...\n```"));
+
+        // Prose before the command and a prefix and suffix around the
+        // message never push the fence across blocks
+        let sender = sender.with_text_wrapping(Some("[prod]".into()), Some("-- bot".into()));
+        message.raw_content = Some(json!([
+            {"type": "text", "text": "Running it now.\n".repeat(200)},
+            {"type": "tool_use", "name": "Bash", "input": {"command": "<".repeat(5000)}}
+        ]));
+        let payload = sender.format_message(&message, "ignored").unwrap();
+        let blocks = payload["blocks"].as_array().unwrap();
+        let texts: Vec<_> = blocks
+            .iter()
+            .map(|block| block["text"]["text"].as_str().unwrap())
+            .collect();
+        assert!(texts[0].starts_with("[prod] Running it now."));
+        let last = texts.last().unwrap();
+        assert!(last.starts_with("💻 Bash:\n```\n&lt;"), "{last}");
+        assert!(last.ends_with("...\n```\n-- bot"), "{last}");
+        for text in &texts {
+            assert!(text.chars().count() <= SLACK_SECTION_LIMIT);
+            assert_eq!(text.matches("```").count() % 2, 0, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_slack_fences_bash_result() {
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let sender = WebhookSender::new(url, WebhookFormat::Slack).unwrap();
+        let mut message = create_test_message();
+        message.raw_content = Some(json!([{"type": "tool_result", "content": "ok"}]));
+        message.tool_use_result = Some(json!({"stdout": "a < b", "stderr": "warn"}));
+
+        let payload = sender.format_message(&message, "ignored").unwrap();
+        let text = payload["blocks"][0]["text"]["text"].as_str().unwrap();
+        assert_eq!(text, "✅ Result:\n```\na &lt; b\nwarn\n```");
+
+        message.raw_content = Some(json!([
+            {"type": "tool_result", "content": "boom", "is_error": true}
+        ]));
+        let payload = sender.format_message(&message, "ignored").unwrap();
+        let text = payload["blocks"][0]["text"]["text"].as_str().unwrap();
+        assert!(text.starts_with("❌ Result:\n```\n"), "{text}");
+
+        // Other tools' results stay plain text
+        message.tool_use_result = Some(json!({"file": {"content": "fn main() {}"}}));
+        let payload = sender.format_message(&message, "ignored").unwrap();
+        assert_eq!(payload["blocks"][0]["text"]["text"], "Test message");
+    }
+
+    #[test]
+    fn test_parse_webhook_routes() {
+        let route: WebhookRoute = "assistant=https://example.com/a".parse().unwrap();
+        assert_eq!(route.matcher, RouteMatch::Role(MessageRole::Assistant));
+        assert_eq!(route.url.as_str(), "https://example.com/a");
+
+        let route: WebhookRoute = "project=foo:https://example.com/foo".parse().unwrap();
+        assert_eq!(route.matcher, RouteMatch::Project("foo".to_string()));
+        assert_eq!(route.url.as_str(), "https://example.com/foo");
+
+        assert!("robot=https://example.com".parse::<WebhookRoute>().is_err());
+        assert!("project=https://example.com"
+            .parse::<WebhookRoute>()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_makes_no_request() {
+        let (url, mut requests) = mock_server(OK_RESPONSE).await;
+        let output = CapturedLogs::default();
+        let sender = WebhookSender::new(url, WebhookFormat::Slack)
+            .unwrap()
+            .with_dry_run(true)
+            .with_dry_run_writer(output.clone());
+
+        let result = sender
+            .send_message(&create_test_message(), "content")
+            .await
+            .unwrap();
+        assert!(matches!(result, WebhookResult::DryRun));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(requests.try_recv().is_err());
+
+        // The payload is printed instead
+        let printed = output.lines().join("\n");
+        let (request, payload) = printed.split_once('\n').unwrap();
+        assert_eq!(request, format!("[webhook dry run] POST {}", sender.url()));
+        let payload: Value = serde_json::from_str(payload).unwrap();
+        assert_eq!(payload["text"], "Test message", "{payload}");
+    }
+
+    #[test]
+    fn test_generic_payload_include_raw() {
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let mut message = create_test_message();
+        message.raw_entry = Some(json!({"type": "user", "uuid": "test-uuid"}));
+
+        let sender = WebhookSender::new(url.clone(), WebhookFormat::Generic).unwrap();
+        let payload = sender.format_message(&message, "content").unwrap();
+        assert!(payload.get("raw").is_none());
+
+        let sender = WebhookSender::new(url, WebhookFormat::Generic)
+            .unwrap()
+            .with_include_raw(true);
+        let payload = sender.format_message(&message, "content").unwrap();
+        assert_eq!(payload["raw"]["type"], "user");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_error_includes_retry_after() {
+        let (url, _requests) = mock_server(RATE_LIMITED_RESPONSE).await;
+        let metrics = Arc::new(Metrics::default());
+        let sender = WebhookSender::new(url, WebhookFormat::Generic)
+            .unwrap()
+            .with_metrics(metrics.clone());
+
+        let err = sender
+            .send_message(&create_test_message(), "content")
+            .await
+            .unwrap_err();
+        let limited = err.downcast_ref::<RateLimited>().unwrap();
+        assert_eq!(limited.retry_after, Some(Duration::from_secs(30)));
+        assert!(err.to_string().contains("retry after 30s"));
+        assert!(metrics
+            .render()
+            .contains("claude_logger_webhook_rate_limited_total 1"));
+    }
+
+    #[test]
+    fn test_unsupported_scheme_rejected() {
+        let url = Url::parse("file:///tmp/hook").unwrap();
+        let err = WebhookSender::new(url, WebhookFormat::Generic)
+            .err()
+            .expect("file:// URL was accepted");
+        assert!(err.to_string().contains(r#"scheme "file""#));
+
+        assert!(parse_webhook_url("ftp://example.com/hook").is_err());
+        assert!("user=file:///tmp/hook".parse::<WebhookRoute>().is_err());
+        assert!(parse_webhook_url("https://example.com/hook").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_redaction_applies_to_payload() {
+        let (url, mut requests) = mock_server(OK_RESPONSE).await;
+        let sender = WebhookSender::new(url, WebhookFormat::Slack)
+            .unwrap()
+            .with_redactor(Some(Redactor::builtin()));
+        let mut message = create_test_message();
+        // Slack content is rebuilt from the message, not the stdout line
+        message.content = "key sk-abcdefghijklmnopqrstu, mail bob@example.com".to_string();
+
+        sender.send_message(&message, "ignored").await.unwrap();
+
+        let request = requests.recv().await.unwrap();
+        assert!(request.contains("key [REDACTED], mail [EMAIL]"));
+        assert!(!request.contains("sk-abc"));
+        assert!(!request.contains("bob@example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_blank_content_skipped() {
+        let (url, mut requests) = mock_server(OK_RESPONSE).await;
+        let sender = WebhookSender::new(url, WebhookFormat::Generic).unwrap();
+        let mut message = create_test_message();
+
+        for content in ["", " \n\t "] {
+            message.content = content.to_string();
+            let result = sender.send_message(&message, content).await.unwrap();
+            assert!(matches!(result, WebhookResult::Skipped));
+        }
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_roles_restrict_forwarding() {
+        let (url, mut requests) = mock_server(OK_RESPONSE).await;
+        let sender = WebhookSender::new(url, WebhookFormat::Generic)
+            .unwrap()
+            .with_roles(Some(vec![MessageRole::User, MessageRole::Assistant]));
+        let mut message = create_test_message();
+
+        message.role = MessageRole::System;
+        let result = sender.send_message(&message, "System: note").await.unwrap();
+        assert!(matches!(result, WebhookResult::Skipped));
+        assert!(requests.try_recv().is_err());
+
+        message.role = MessageRole::Assistant;
+        let result = sender.send_message(&message, "Claude: hi").await.unwrap();
+        assert!(matches!(result, WebhookResult::Sent));
+        assert!(requests.recv().await.is_some());
+    }
+
+    #[test]
+    fn test_presets() {
+        let message = |role: MessageRole, content: Value| LogMessage {
+            role,
+            raw_content: Some(content),
+            ..create_test_message()
+        };
+        let messages = [
+            message(
+                MessageRole::Assistant,
+                json!([{"type": "text", "text": "Done."}]),
+            ),
+            message(
+                MessageRole::Assistant,
+                json!([{"type": "tool_use", "name": "Read", "input": {}}]),
+            ),
+            message(
+                MessageRole::User,
+                json!([{"type": "tool_result", "content": "ok"}]),
+            ),
+            message(
+                MessageRole::User,
+                json!([{"type": "tool_result", "content": "boom", "is_error": true}]),
+            ),
+            message(
+                MessageRole::User,
+                json!([{"type": "text", "text": "Thanks"}]),
+            ),
+        ];
+        let url = Url::parse("https://example.com/webhook").unwrap();
+        let sender = WebhookSender::new(url, WebhookFormat::Generic).unwrap();
+        let kept = |preset| -> Vec<bool> {
+            let sender = sender.clone().with_preset(preset);
+            messages.iter().map(|m| !sender.is_filtered(m)).collect()
+        };
+
+        assert_eq!(kept(None), [true, false, false, false, true]);
+        assert_eq!(kept(Some(WebhookPreset::All)), [true; 5]);
+        assert_eq!(
+            kept(Some(WebhookPreset::AssistantText)),
+            [true, false, false, false, false]
+        );
+        assert_eq!(
+            kept(Some(WebhookPreset::Errors)),
+            [false, false, false, true, false]
+        );
+    }
+
+    /// Start a server that accepts connections but never answers
+    pub(crate) async fn silent_server() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/hook", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                open.push(stream);
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_configured_timeout_is_applied() {
+        let url = silent_server().await;
+        let sender = WebhookSender::new(url, WebhookFormat::Generic)
+            .unwrap()
+            .with_timeout(Duration::from_millis(100))
+            .unwrap();
+        let started = std::time::Instant::now();
+        let err = sender
+            .send_message(&create_test_message(), "content")
+            .await
+            .unwrap_err();
+
+        let timed_out = err
+            .chain()
+            .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+            .any(reqwest::Error::is_timeout);
+        assert!(timed_out, "expected a timeout, got {err:#}");
+        assert!(started.elapsed() < DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after(" 5 "), Some(Duration::from_secs(5)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn test_generic_batch_sends_ndjson() {
+        let (url, mut requests) = mock_server(OK_RESPONSE).await;
+        let sender = WebhookSender::new(url, WebhookFormat::Generic)
+            .unwrap()
+            .with_batching(true);
+
+        let mut message = create_test_message();
+        for i in 0..3 {
+            message.uuid = format!("uuid-{i}");
+            let result = sender.send_message(&message, "content").await.unwrap();
+            assert!(matches!(result, WebhookResult::Queued));
+        }
+        assert_eq!(sender.flush().await.unwrap(), 3);
+        assert_eq!(sender.flush().await.unwrap(), 0);
+
+        let request = requests.recv().await.unwrap();
+        let (headers, body) = request.split_once("\r\n\r\n").unwrap();
+        assert!(headers
+            .to_ascii_lowercase()
+            .contains("content-type: application/x-ndjson"));
+
+        let lines: Vec<_> = body.lines().collect();
+        assert_eq!(lines.len(), 3);
+        for (i, line) in lines.iter().enumerate() {
+            let value: Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value["uuid"], format!("uuid-{i}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_stays_queued() {
+        let (url, _requests) = mock_server(RATE_LIMITED_RESPONSE).await;
+        let sender = WebhookSender::new(url, WebhookFormat::Generic)
+            .unwrap()
+            .with_batching(true);
+
+        let mut message = create_test_message();
+        for i in 0..3 {
+            message.uuid = format!("uuid-{i}");
+            sender.send_message(&message, "content").await.unwrap();
+        }
+        let err = sender.flush().await.unwrap_err();
+        assert!(err.is::<RateLimited>());
+
+        message.uuid = "uuid-3".to_string();
+        sender.send_message(&message, "content").await.unwrap();
+        let queued = sender.batch.as_ref().unwrap().lock().unwrap().clone();
+        let uuids: Vec<_> = queued.iter().map(|payload| &payload["uuid"]).collect();
+        assert_eq!(uuids, ["uuid-0", "uuid-1", "uuid-2", "uuid-3"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_backlog_is_capped() {
+        let (url, _requests) = mock_server(RATE_LIMITED_RESPONSE).await;
+        let metrics = Arc::new(Metrics::default());
+        let sender = WebhookSender::new(url, WebhookFormat::Generic)
+            .unwrap()
+            .with_batching(true)
+            .with_metrics(metrics.clone());
+
+        let mut message = create_test_message();
+        for i in 0..MAX_BATCH_BACKLOG {
+            message.uuid = format!("uuid-{i}");
+            sender.send_message(&message, "content").await.unwrap();
+        }
+        // Retrying a full backlog drops nothing and counts nothing as failed
+        for _ in 0..2 {
+            let err = sender.flush().await.unwrap_err();
+            assert!(err.downcast_ref::<DroppedPayloads>().is_none());
+        }
+        assert!(metrics
+            .render()
+            .contains(r#"claude_logger_webhook_requests_total{result="failed"} 0"#));
+
+        for i in 0..5 {
+            message.uuid = format!("new-{i}");
+            sender.send_message(&message, "content").await.unwrap();
+        }
+        let err = sender.flush().await.unwrap_err();
+        assert_eq!(err.downcast_ref::<DroppedPayloads>().unwrap().0, 5);
+        assert!(err.is::<RateLimited>());
+        assert!(metrics
+            .render()
+            .contains(r#"claude_logger_webhook_requests_total{result="failed"} 5"#));
+
+        let queued = sender.batch.as_ref().unwrap().lock().unwrap().clone();
+        assert_eq!(queued.len(), MAX_BATCH_BACKLOG);
+        assert_eq!(queued[0]["uuid"], "uuid-5");
+        assert_eq!(queued[MAX_BATCH_BACKLOG - 1]["uuid"], "new-4");
+    }
 }
@@ -0,0 +1,48 @@
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+// Dated in the future so they count as new messages
+const LINES: [&str; 3] = [
+    r#"{"type":"user","message":{"role":"user","content":"first"},"timestamp":"2099-01-01T00:00:00Z","sessionId":"s","uuid":"u1"}"#,
+    r#"{ "uuid": "u2", "type": "assistant", "sessionId": "s", "timestamp": "2099-01-01T00:00:01.500+09:00", "message": {"role": "assistant", "content": [{"type": "text", "text": "café 😀"}]} }"#,
+    r#"{"type":"user","message":{"role":"user","content":"third"},"timestamp":"2099-01-01T00:00:02Z","sessionId":"s","uuid":"u3"}"#,
+];
+
+#[test]
+fn test_passthrough_prints_appended_lines_unchanged() {
+    let project = tempfile::TempDir::new().unwrap();
+    let session = project.path().join("session.jsonl");
+    fs::write(&session, "").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_claude-logger"))
+        .args(["watch", "--jsonl-passthrough", "--idle-timeout", "2"])
+        .arg("--project-path")
+        .arg(project.path())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Give the watcher time to start before writing
+    thread::sleep(Duration::from_millis(500));
+
+    let mut file = OpenOptions::new().append(true).open(&session).unwrap();
+    for line in LINES {
+        writeln!(file, "{line}").unwrap();
+    }
+    // A snapshot is not a message and is not printed
+    writeln!(
+        file,
+        r#"{{"type":"file-history-snapshot","messageId":"m"}}"#
+    )
+    .unwrap();
+    // Repeated messages are deduplicated
+    writeln!(file, "{}", LINES[0]).unwrap();
+    drop(file);
+
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    let printed: Vec<String> = stdout.lines().map(Result::unwrap).collect();
+    assert!(child.wait().unwrap().success());
+    assert_eq!(printed, LINES);
+}
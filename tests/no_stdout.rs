@@ -0,0 +1,62 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::process::Command;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Accept one webhook request and pass its raw text back
+fn webhook_server() -> (String, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        while let Ok(read @ 1..) = stream.read(&mut buffer) {
+            request.extend_from_slice(&buffer[..read]);
+            if String::from_utf8_lossy(&request).contains("\"content\"") {
+                break;
+            }
+        }
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        let _ = tx.send(String::from_utf8_lossy(&request).into_owned());
+    });
+    (url, rx)
+}
+
+#[test]
+fn test_no_stdout_only_posts_to_webhook() {
+    let project = tempfile::TempDir::new().unwrap();
+    fs::write(
+        project.path().join("session.jsonl"),
+        r#"{"type":"user","message":{"role":"user","content":"relay me"},"timestamp":"2025-01-01T00:00:00Z","sessionId":"s","uuid":"u1"}
+"#,
+    )
+    .unwrap();
+    let (url, requests) = webhook_server();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_claude-logger"))
+        .args([
+            "watch",
+            "--include-existing",
+            "--idle-timeout",
+            "1",
+            "--no-stdout",
+        ])
+        .arg("--project-path")
+        .arg(project.path())
+        .args(["--webhook-url", &url])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stdout.is_empty(), "{output:?}");
+    let request = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(request.contains("relay me"));
+}